@@ -0,0 +1,87 @@
+use crate::chip8::Opcode;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+
+/// Addresses of the instructions reachable from `start`, following jumps,
+/// calls and both outcomes of skips. Computed jumps can't be followed, and
+/// anything that doesn't decode is taken to be data.
+pub fn reachable(memory: &[u8], start: u16) -> BTreeSet<u16> {
+    let mut seen = BTreeSet::new();
+    let mut todo = vec![start];
+    while let Some(addr) = todo.pop() {
+        if addr as usize + 2 > memory.len() || !seen.insert(addr) {
+            continue;
+        }
+        match successors(&Opcode::from_slice(&memory[addr as usize..]), addr) {
+            Some(next) => todo.extend(next),
+            None => {
+                seen.remove(&addr);
+            }
+        }
+    }
+    seen
+}
+
+/// Where execution can go after `op` at `addr`, or `None` if it doesn't
+/// decode. Returns and computed jumps go somewhere that can't be known, and
+/// going on past the top of the address space goes nowhere.
+pub fn successors(op: &Opcode, addr: u16) -> Option<Vec<u16>> {
+    let next = match (op.n0, op.n1, op.n2, op.n3) {
+        (0x0, 0x0, 0xE, 0xE) | (0x0, 0x0, 0xF, 0xD) | (0xB, ..) => vec![], // RTN, EXIT, J0N
+        (0x1, ..) => vec![Some(op.a)],
+        (0x2, ..) => vec![Some(op.a), addr.checked_add(2)],
+        (0x3 | 0x4 | 0x5 | 0x9, ..) | (0xE, _, 0x9, 0xE) | (0xE, _, 0xA, 0x1) => {
+            vec![addr.checked_add(2), addr.checked_add(4)]
+        }
+        _ if op.mnemonic() == "???" && op.extension().is_none() => return None,
+        _ => vec![addr.checked_add(2)],
+    };
+    Some(next.into_iter().flatten().collect())
+}
+
+/// Reachable instructions from extensions to CHIP-8, by extension: how many
+/// there are and where the first is.
+pub fn extensions(memory: &[u8], start: u16) -> BTreeMap<&'static str, (usize, u16)> {
+    let mut found = BTreeMap::new();
+    for addr in reachable(memory, start) {
+        if let Some(extension) = Opcode::from_slice(&memory[addr as usize..]).extension() {
+            found.entry(extension).or_insert((0, addr)).0 += 1;
+        }
+    }
+    found
+}
+
+/// Addresses that reachable jumps and calls go to, the places worth a label.
+pub fn branch_targets(memory: &[u8], start: u16) -> BTreeSet<u16> {
+    reachable(memory, start)
+        .into_iter()
+        .map(|addr| Opcode::from_slice(&memory[addr as usize..]))
+        .filter(|op| op.n0 == 0x1 || op.n0 == 0x2)
+        .map(|op| op.a)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn successors_stop_at_the_top_of_memory() {
+        let skip = Opcode::from_slice(&[0x30, 0x00]);
+        assert_eq!(successors(&skip, 0xFFFC), Some(vec![0xFFFE]));
+        let call = Opcode::from_slice(&[0x23, 0x00]);
+        assert_eq!(successors(&call, 0xFFFE), Some(vec![0x300]));
+    }
+    #[test]
+    fn reaches_the_last_word_of_memory() {
+        let mut memory = [0; 0x206];
+        memory[0x200..].copy_from_slice(&[0x60, 0x00, 0x61, 0x00, 0x62, 0x00]);
+        assert_eq!(
+            reachable(&memory, 0x200).into_iter().collect::<Vec<_>>(),
+            [0x200, 0x202, 0x204]
+        );
+    }
+}
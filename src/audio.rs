@@ -0,0 +1,172 @@
+use rodio::cpal::{
+    self,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    BufferSize, OutputCallbackInfo, Sample, SampleFormat, StreamConfig,
+};
+use std::{
+    error::Error,
+    f32::consts::TAU,
+    result::Result,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    time::Duration,
+};
+
+pub const PITCH: f32 = 440.0;
+pub const VOLUME: f32 = 0.2;
+
+/// Volume steps from silent to twice `VOLUME`, starting halfway.
+pub const VOLUME_STEPS: u32 = 10;
+
+/// The buzzer, a tone written straight to the output device so the size of
+/// its buffer, and so how far beeps trail the screen, can be chosen.
+pub struct Beeper {
+    _stream: cpal::Stream,
+    on: Arc<AtomicBool>,
+    /// How loud, in steps out of `VOLUME_STEPS`.
+    level: Arc<AtomicU32>,
+    /// Microseconds from a callback starting to its first sample playing,
+    /// plus the length of the buffer it fills; zero until known.
+    latency: Arc<AtomicU64>,
+    /// What went wrong playing, kept for the log since the terminal is in
+    /// raw mode and printing would garble the screen.
+    errors: Receiver<String>,
+}
+
+impl Beeper {
+    /// Opens the named output device, or the default one, asking for
+    /// `buffer` frames per callback or leaving it to the device.
+    pub fn open(name: Option<&str>, buffer: Option<u32>) -> Result<Beeper, Box<dyn Error>> {
+        let host = cpal::default_host();
+        let device = match name {
+            Some(name) => host
+                .output_devices()?
+                .find(|device| device.name().is_ok_and(|found| found == name))
+                .ok_or(format!(
+                    "no audio output device named {} (--list-audio shows them)",
+                    name
+                ))?,
+            None => host
+                .default_output_device()
+                .ok_or("no audio output device")?,
+        };
+        let supported = device.default_output_config()?;
+        let config = StreamConfig {
+            buffer_size: buffer.map_or(BufferSize::Default, BufferSize::Fixed),
+            ..supported.config()
+        };
+        let on = Arc::new(AtomicBool::new(false));
+        let level = Arc::new(AtomicU32::new(VOLUME_STEPS / 2));
+        let latency = Arc::new(AtomicU64::new(0));
+        let (sender, errors) = channel();
+        let stream = match supported.sample_format() {
+            SampleFormat::F32 => build::<f32>(&device, &config, &on, &level, &latency, sender),
+            SampleFormat::I16 => build::<i16>(&device, &config, &on, &level, &latency, sender),
+            SampleFormat::U16 => build::<u16>(&device, &config, &on, &level, &latency, sender),
+        }
+        .map_err(|error| match buffer {
+            Some(frames) => format!(
+                "can't use an audio buffer of {} frames ({:?} supported): {}",
+                frames,
+                supported.buffer_size(),
+                error
+            ),
+            None => error.to_string(),
+        })?;
+        stream.play()?;
+        Ok(Beeper {
+            _stream: stream,
+            on,
+            level,
+            latency,
+            errors,
+        })
+    }
+
+    pub fn set(&self, on: bool) {
+        self.on.store(on, Ordering::Relaxed);
+    }
+
+    /// Turns the volume up or down by `steps`, returning the new level.
+    pub fn change_volume(&self, steps: i32) -> u32 {
+        let level = self.level.load(Ordering::Relaxed) as i32 + steps;
+        let level = level.clamp(0, VOLUME_STEPS as i32) as u32;
+        self.level.store(level, Ordering::Relaxed);
+        level
+    }
+
+    /// How long a beep takes to be heard, once the device has said.
+    pub fn latency(&self) -> Option<Duration> {
+        match self.latency.load(Ordering::Relaxed) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+
+    /// The errors from the output device since last asked.
+    pub fn errors(&self) -> impl Iterator<Item = String> + '_ {
+        self.errors.try_iter()
+    }
+}
+
+/// The names of the output devices, to pick one with `--audio-device`, with
+/// the one used when none is picked marked.
+pub fn devices() -> Result<Vec<String>, Box<dyn Error>> {
+    let host = cpal::default_host();
+    let default = host.default_output_device().and_then(|d| d.name().ok());
+    let mut names = vec![];
+    for device in host.output_devices()? {
+        let name = device.name()?;
+        names.push(match Some(&name) == default.as_ref() {
+            true => format!("{} (default)", name),
+            false => name,
+        });
+    }
+    if names.is_empty() {
+        return Err("no audio output devices".into());
+    }
+    Ok(names)
+}
+
+fn build<T: Sample>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    on: &Arc<AtomicBool>,
+    level: &Arc<AtomicU32>,
+    latency: &Arc<AtomicU64>,
+    errors: Sender<String>,
+) -> Result<cpal::Stream, cpal::BuildStreamError> {
+    let (on, level, latency) = (on.clone(), level.clone(), latency.clone());
+    let channels = config.channels as usize;
+    let rate = config.sample_rate.0 as f32;
+    let mut phase = 0.0f32;
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], info: &OutputCallbackInfo| {
+            let timestamp = info.timestamp();
+            let ahead = (timestamp.playback)
+                .duration_since(&timestamp.callback)
+                .unwrap_or_default();
+            let buffered = Duration::from_secs_f32((data.len() / channels) as f32 / rate);
+            latency.store((ahead + buffered).as_micros() as u64, Ordering::Relaxed);
+
+            let on = on.load(Ordering::Relaxed);
+            let volume = VOLUME * 2.0 * level.load(Ordering::Relaxed) as f32 / VOLUME_STEPS as f32;
+            for frame in data.chunks_mut(channels) {
+                let value = if on {
+                    volume * (phase * TAU).sin()
+                } else {
+                    0.0
+                };
+                phase = (phase + PITCH / rate).fract();
+                frame.fill(Sample::from(&value));
+            }
+        },
+        move |error| {
+            let _ = errors.send(error.to_string());
+        },
+    )
+}
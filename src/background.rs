@@ -0,0 +1,130 @@
+use crate::multiplexer::Multiplexer;
+#[cfg(unix)]
+use std::time::Duration;
+
+/// How long to wait for the terminal to say what its background is.
+/// Terminals answer the request for their attributes sent after it, so
+/// this only runs out when the reply is lost.
+#[cfg(unix)]
+const REPLY_TIME: Duration = Duration::from_millis(300);
+
+/// Asks for the background color (OSC 11), then for the terminal's
+/// attributes (DA1), which every terminal answers, so the wait ends there
+/// whether it knew its background or not.
+const QUERY: &str = "\x1b]11;?\x07\x1b[c";
+
+/// Whether the terminal is light or dark behind what's drawn on it.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+impl Background {
+    pub const NAMES: [&'static str; 3] = ["auto", "dark", "light"];
+
+    /// The one named, `auto` for asking the terminal, as for `--background`.
+    pub fn parse(name: &str) -> Option<Option<Background>> {
+        match name {
+            "auto" => Some(None),
+            "dark" => Some(Some(Background::Dark)),
+            "light" => Some(Some(Background::Light)),
+            _ => None,
+        }
+    }
+
+    /// Asks the terminal, which has to be in raw mode, for its background.
+    /// screen keeps the question to itself unless it's passed through;
+    /// tmux answers it for the terminal outside.
+    pub fn query(multiplexer: Option<Multiplexer>) -> Option<Background> {
+        let query = match multiplexer {
+            Some(Multiplexer::Screen) => Multiplexer::Screen.passthrough(QUERY),
+            _ => QUERY.to_string(),
+        };
+        let reply = ask(&query)?;
+        let rgb = parse_reply(&reply)?;
+        // Relative luminance, halfway between black and white
+        let luminance = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+        Some(match luminance > 0.5 {
+            true => Background::Light,
+            false => Background::Dark,
+        })
+    }
+}
+
+/// Sends the query and reads what comes back until the attributes do,
+/// reading straight from stdin so nothing is left buffered for the
+/// keyboard to see later.
+#[cfg(unix)]
+fn ask(query: &str) -> Option<Vec<u8>> {
+    use std::{
+        io::{stdin, stdout, IsTerminal, Write},
+        os::fd::AsRawFd,
+        time::Instant,
+    };
+
+    if !stdin().is_terminal() {
+        return None;
+    }
+    let mut out = stdout();
+    out.write_all(query.as_bytes()).ok()?;
+    out.flush().ok()?;
+    let fd = stdin().as_raw_fd();
+    let deadline = Instant::now() + REPLY_TIME;
+    let mut reply = Vec::new();
+    while !attributes_seen(&reply) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: waits on the one descriptor given
+        if unsafe { libc::poll(&mut poll, 1, left.as_millis() as libc::c_int) } <= 0 {
+            break;
+        }
+        let mut buf = [0; 64];
+        // SAFETY: reads at most the buffer's length into it
+        let read = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+        if read <= 0 {
+            break;
+        }
+        reply.extend_from_slice(&buf[..read as usize]);
+    }
+    Some(reply)
+}
+
+/// Consoles on Windows don't answer, or do but only through the event
+/// reader, so the background is left to `--background` there.
+#[cfg(not(unix))]
+fn ask(_query: &str) -> Option<Vec<u8>> {
+    None
+}
+
+/// Whether the reply to DA1, `ESC [ ? ... c`, has come in.
+#[cfg(unix)]
+fn attributes_seen(reply: &[u8]) -> bool {
+    let Some(start) = reply.windows(3).position(|w| w == b"\x1b[?") else {
+        return false;
+    };
+    reply[start..].contains(&b'c')
+}
+
+/// The color in a reply of `ESC ] 11 ; rgb:RRRR/GGGG/BBBB`, each component
+/// one to four hex digits, as fractions of full brightness.
+fn parse_reply(reply: &[u8]) -> Option<[f32; 3]> {
+    let start = reply.windows(4).position(|w| w == b"]11;")? + 4;
+    let rest = reply[start..].strip_prefix(b"rgb:")?;
+    let end = rest.iter().position(|&b| b == 0x07 || b == 0x1b)?;
+    let text = std::str::from_utf8(&rest[..end]).ok()?;
+    let mut rgb = [0.0; 3];
+    let mut components = text.split('/');
+    for value in &mut rgb {
+        let hex = components
+            .next()
+            .filter(|hex| (1..=4).contains(&hex.len()))?;
+        let max = (1u32 << (4 * hex.len())) - 1;
+        *value = u32::from_str_radix(hex, 16).ok()? as f32 / max as f32;
+    }
+    Some(rgb)
+}
@@ -0,0 +1,225 @@
+use crate::{
+    background::Background,
+    chip8::{Chip8, ADDR_PROGRAM_END},
+    debugger::Debugger,
+    input::Hotkeys,
+    read_rom,
+    render::Renderer,
+    syntax::Syntax,
+    ui::{
+        Console, InputHistory, Log, MemoryMap, Palette, PanelData, Panels, SoundHistory, View,
+        Workspace,
+    },
+    INSTRUCTIONS_PER_FRAME, TIMER_RATE,
+};
+use ratatui::layout::Rect;
+#[cfg(feature = "count-allocations")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    path::Path,
+    result::Result,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "usage: fe_o8 bench [FILE] [--frames N] [--ipf N] [--render]";
+
+/// The terminal the render benchmark draws to, big enough for every panel.
+const RENDER_AREA: Rect = Rect::new(0, 0, 200, 60);
+
+/// Frames drawn before counting, for buffers to grow to the size they stay.
+const WARM_UP: u64 = 60;
+
+/// Counts heap allocations for the render benchmark. It's the allocator for
+/// the whole program, costing an atomic add per allocation, so it's only
+/// built with the `count-allocations` feature.
+#[cfg(feature = "count-allocations")]
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "count-allocations")]
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, size)
+    }
+}
+
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Run when no ROM is given: a loop drawing a 15 row sprite, moving it
+/// across the display so it wraps onto the screen and is clipped at the
+/// edges, which is most of what a game does at high speeds.
+const SPRITES: [u8; 25] = [
+    0xA2, 0x0A, // I = sprite
+    0xD0, 0x1F, // draw it at V0, V1
+    0x70, 0x05, // V0 += 5
+    0x71, 0x03, // V1 += 3
+    0x12, 0x02, // draw again
+    0xFF, 0x81, 0xBD, 0xA5, 0xA5, 0xBD, 0x81, 0xFF, 0x3C, 0x66, 0xC3, 0xC3, 0x66, 0x3C, 0x18,
+];
+
+/// Runs a ROM headless with no frame pacing and reports how fast the
+/// interpreter went, overall and per instruction. `--ipf` runs more
+/// instructions a frame, as fast games and `--speed` do.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let mut path = None;
+    let mut frames: u64 = 10_000;
+    let mut ipf = INSTRUCTIONS_PER_FRAME;
+    let mut render = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => frames = args.next().ok_or(USAGE)?.parse()?,
+            "--ipf" => ipf = args.next().ok_or(USAGE)?.parse::<usize>()?.max(1),
+            "--render" => render = true,
+            _ if arg.starts_with("--") => return Err(USAGE.into()),
+            _ => path = Some(arg),
+        }
+    }
+    let rom = match path {
+        Some(path) => read_rom(Path::new(&path), ADDR_PROGRAM_END as usize)?,
+        None => SPRITES.to_vec(),
+    };
+    if render {
+        return bench_render(&rom, frames, ipf);
+    }
+
+    // Throughput is measured on its own, since timing every instruction
+    // costs more than most instructions do.
+    let mut chip8 = Chip8::new();
+    chip8.load(&rom);
+    let start = Instant::now();
+    for _ in 0..frames {
+        chip8.run_frame(ipf)?;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    let instructions = frames * ipf as u64;
+    println!(
+        "{} frames of {} instructions, {} instructions in {:.3}s",
+        frames, ipf, instructions, elapsed
+    );
+    println!("{:.0} instructions/s", instructions as f64 / elapsed);
+    println!("{:.0} frames/s", frames as f64 / elapsed);
+
+    let mut timings: BTreeMap<&'static str, (u64, Duration)> = BTreeMap::new();
+    let mut chip8 = Chip8::new();
+    chip8.load(&rom);
+    for _ in 0..frames {
+        for _ in 0..ipf {
+            let mnemonic = chip8.fetch()?.mnemonic();
+            let start = Instant::now();
+            chip8.step()?;
+            let (count, total) = timings.entry(mnemonic).or_default();
+            *count += 1;
+            *total += start.elapsed();
+        }
+        chip8.tick_timers();
+    }
+    println!();
+    println!(
+        "{:<4} {:>12} {:>12} {:>10}",
+        "op", "count", "total ms", "ns/op"
+    );
+    for (mnemonic, (count, total)) in timings {
+        println!(
+            "{:<4} {:>12} {:>12.3} {:>10.1}",
+            mnemonic,
+            count,
+            total.as_secs_f64() * 1e3,
+            total.as_nanos() as f64 / count as f64
+        );
+    }
+    Ok(())
+}
+
+/// Draws frames of the ROM running in each workspace, as the main loop
+/// does, and reports how fast and, with the `count-allocations` feature,
+/// how many heap allocations each frame made once drawing had settled.
+fn bench_render(rom: &[u8], frames: u64, ipf: usize) -> Result<(), Box<dyn Error>> {
+    println!("{:<8} {:>12} {:>14}", "panels", "frames/s", "allocs/frame");
+    for workspace in [Workspace::Play, Workspace::Debug, Workspace::Analyze] {
+        let mut chip8 = Chip8::new();
+        chip8.load(rom);
+        let mut debugger = Debugger::default();
+        let (log, console, inputs, sounds) = (
+            Log::default(),
+            Console::default(),
+            InputHistory::default(),
+            SoundHistory::default(),
+        );
+        let (palette, panels) = (Palette::default(), Panels::from(workspace));
+        let mut data = PanelData::default();
+        let mut renderer = Renderer::new(None, false, false);
+        renderer.resize(RENDER_AREA);
+        let mut allocations = 0;
+        let mut elapsed = Duration::ZERO;
+        for frame in 0..WARM_UP + frames {
+            debugger.run(&mut chip8, ipf, &Default::default(), None);
+            chip8.tick_timers();
+            let before = ALLOCATIONS.load(Ordering::Relaxed);
+            let start = Instant::now();
+            data.update(&chip8, &debugger, &panels);
+            let view = View {
+                chip8: &chip8,
+                debugger: &debugger,
+                log: &log,
+                console: &console,
+                inputs: &inputs,
+                sounds: &sounds,
+                data: &data,
+                palette: &palette,
+                panels: &panels,
+                fullscreen: false,
+                monochrome: false,
+                background: Background::Dark,
+                legend: None,
+                title: None,
+                frame_time: Duration::from_secs(1) / TIMER_RATE,
+                refresh: TIMER_RATE,
+                instructions_per_frame: ipf,
+                paused: false,
+                beeping: false,
+                halted: None,
+                profile_label: "",
+                syntax: Syntax::default(),
+                memory_map: MemoryMap::default(),
+                rom_size: rom.len(),
+                rewind: None,
+                speedrun: None,
+                stuck: None,
+                hotkeys: &Hotkeys::default(),
+            };
+            renderer.draw(|area, buf| view.render(area, buf))?;
+            renderer.output().clear();
+            if frame >= WARM_UP {
+                elapsed += start.elapsed();
+                allocations += ALLOCATIONS.load(Ordering::Relaxed) - before;
+            }
+        }
+        let allocations = match cfg!(feature = "count-allocations") {
+            true => format!("{:.1}", allocations as f64 / frames as f64),
+            false => "-".to_string(),
+        };
+        println!(
+            "{:<8} {:>12.0} {:>14}",
+            workspace.name(),
+            frames as f64 / elapsed.as_secs_f64(),
+            allocations
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use crate::{
+    load,
+    options::Options,
+    quirks::{Profile, Quirks},
+    read_rom,
+    ui::Palette,
+    INSTRUCTIONS_PER_FRAME,
+};
+use std::{error::Error, fs, path::Path, result::Result};
+
+const USAGE: &str = "usage: fe_o8 c8b OUT.c8b [Options] FILE";
+
+const MAGIC: &[u8] = b"CBF";
+const VERSION: u8 = 0;
+
+/// Platforms, as numbered in the header.
+const PLATFORMS: [Profile; 2] = [Profile::Vip, Profile::Schip];
+
+/// Property keys.
+const NAME: u8 = 0;
+const DESIGNER: u8 = 1;
+const TICKRATE: u8 = 2;
+const COLORS: u8 = 3;
+const KEYMAP: u8 = 4;
+const QUIRKS: u8 = 5;
+
+/// Whether a ROM file is a c8b container.
+pub fn is_c8b(file: &[u8]) -> bool {
+    file.starts_with(MAGIC)
+}
+
+/// A ROM along with how it wants to be run, in the proposed c8b container:
+/// ```text
+/// "CBF" version platform count  (platform 0 is CHIP-8, 1 SUPER-CHIP)
+/// count × key length:u16 value  (big endian)
+/// code
+/// ```
+/// Properties fe_o8 doesn't know are skipped. Those it does: 0 the name,
+/// 1 the designer, 2 instructions per frame as a u16, 3 the background,
+/// plane 1, plane 2 and overlap colors as RGB, 4 the keyboard keys for
+/// keypad keys 0 through F as ASCII, and 5 the quirks, a byte with a bit per
+/// name in `Quirks::NAMES` from the lowest up, then the stack limit or 0 for
+/// none.
+#[derive(Default)]
+pub struct Bundle {
+    pub platform: Option<Profile>,
+    pub name: Option<String>,
+    pub designer: Option<String>,
+    pub tickrate: Option<usize>,
+    pub colors: Option<[[u8; 3]; 4]>,
+    pub keymap: Option<[char; 16]>,
+    pub quirks: Option<Quirks>,
+    pub code: Vec<u8>,
+}
+
+impl Bundle {
+    pub fn decode(file: &[u8]) -> Result<Bundle, String> {
+        let mut rest = file.strip_prefix(MAGIC).ok_or("not a c8b container")?;
+        let mut take = |length: usize| -> Result<&[u8], String> {
+            if rest.len() < length {
+                return Err("c8b container cut short".into());
+            }
+            let (taken, after) = rest.split_at(length);
+            rest = after;
+            Ok(taken)
+        };
+        let header = take(3)?;
+        let (version, platform, count) = (header[0], header[1], header[2]);
+        if version != VERSION {
+            return Err(format!("c8b version {} isn't supported", version));
+        }
+        let mut bundle = Bundle {
+            platform: Some(
+                *PLATFORMS
+                    .get(platform as usize)
+                    .ok_or(format!("c8b platform {} isn't supported", platform))?,
+            ),
+            ..Bundle::default()
+        };
+        for _ in 0..count {
+            let property = take(3)?;
+            let key = property[0];
+            let value = take(u16::from_be_bytes([property[1], property[2]]) as usize)?;
+            let malformed = || format!("c8b property {} is malformed", key);
+            match key {
+                NAME => bundle.name = Some(String::from_utf8_lossy(value).into_owned()),
+                DESIGNER => bundle.designer = Some(String::from_utf8_lossy(value).into_owned()),
+                TICKRATE => {
+                    let &[high, low] = value else {
+                        return Err(malformed());
+                    };
+                    bundle.tickrate = Some(u16::from_be_bytes([high, low]) as usize);
+                }
+                COLORS => {
+                    let mut colors = [[0; 3]; 4];
+                    if value.len() != 12 {
+                        return Err(malformed());
+                    }
+                    for (color, rgb) in colors.iter_mut().zip(value.chunks(3)) {
+                        color.copy_from_slice(rgb);
+                    }
+                    bundle.colors = Some(colors);
+                }
+                KEYMAP => {
+                    let keymap: [u8; 16] = value.try_into().map_err(|_| malformed())?;
+                    bundle.keymap = Some(keymap.map(|key| key.to_ascii_lowercase() as char));
+                }
+                QUIRKS => {
+                    let &[bits, stack_limit] = value else {
+                        return Err(malformed());
+                    };
+                    let mut quirks = Quirks::default();
+                    for (bit, name) in Quirks::NAMES.iter().enumerate() {
+                        quirks.set(name, bits & 1 << bit != 0)?;
+                    }
+                    quirks.stack_limit = (stack_limit != 0).then_some(stack_limit as usize);
+                    bundle.quirks = Some(quirks);
+                }
+                _ => (),
+            }
+        }
+        bundle.code = rest.to_vec();
+        Ok(bundle)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut properties: Vec<(u8, Vec<u8>)> = vec![];
+        if let Some(name) = &self.name {
+            properties.push((NAME, name.as_bytes().to_vec()));
+        }
+        if let Some(designer) = &self.designer {
+            properties.push((DESIGNER, designer.as_bytes().to_vec()));
+        }
+        if let Some(tickrate) = self.tickrate {
+            let tickrate = tickrate.min(u16::MAX as usize) as u16;
+            properties.push((TICKRATE, tickrate.to_be_bytes().to_vec()));
+        }
+        if let Some(colors) = self.colors {
+            properties.push((COLORS, colors.concat()));
+        }
+        if let Some(keymap) = self.keymap {
+            properties.push((KEYMAP, keymap.map(|key| key as u8).to_vec()));
+        }
+        if let Some(quirks) = self.quirks {
+            let flags = [
+                quirks.add_i_sets_vf,
+                quirks.shift_vx,
+                quirks.jump_vx,
+                quirks.load_store_increments_i,
+                quirks.logic_resets_vf,
+            ];
+            let bits =
+                (flags.iter().enumerate()).fold(0, |bits, (bit, &on)| bits | (on as u8) << bit);
+            let stack_limit = quirks.stack_limit.map_or(0, |limit| limit.min(255) as u8);
+            properties.push((QUIRKS, vec![bits, stack_limit]));
+        }
+
+        let platform = PLATFORMS
+            .iter()
+            .position(|&platform| Some(platform) == self.platform)
+            .unwrap_or(0);
+        let mut file = MAGIC.to_vec();
+        file.extend([VERSION, platform as u8, properties.len() as u8]);
+        for (key, value) in properties {
+            file.push(key);
+            file.extend((value.len() as u16).to_be_bytes());
+            file.extend(value);
+        }
+        file.extend(&self.code);
+        file
+    }
+
+    pub fn palette(&self) -> Option<Palette> {
+        self.colors.map(Palette::from_rgb)
+    }
+}
+
+/// Writes a ROM into a c8b container along with the settings it runs with:
+/// its quirks, as detected or given, the palette and its speed.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let out = args.next().ok_or(USAGE)?;
+    let options = Options::parse(args)?;
+    let [path] = options.paths.as_slice() else {
+        return Err(USAGE.into());
+    };
+    let loaded = load(Some(path), &options, &mut Default::default())?;
+    let quirks = loaded.chip8.quirks;
+    // Keep what a container being rewritten says that fe_o8 has no settings for
+    let file = fs::read(path)?;
+    let original = match is_c8b(&file) {
+        true => Bundle::decode(&file)?,
+        false => Bundle::default(),
+    };
+    let bundle = Bundle {
+        platform: Some(if quirks == Profile::Schip.quirks() {
+            Profile::Schip
+        } else {
+            Profile::Vip
+        }),
+        name: (original.name).or_else(|| Some(path.file_stem()?.to_string_lossy().into_owned())),
+        designer: original.designer,
+        tickrate: Some(
+            (options.speed)
+                .or(loaded.instructions_per_frame)
+                .unwrap_or(INSTRUCTIONS_PER_FRAME),
+        ),
+        colors: (options.palette.as_ref())
+            .or(loaded.palette.as_ref())
+            .and_then(Palette::rgb),
+        keymap: loaded.keymap,
+        quirks: Some(quirks),
+        code: read_rom(path, options.memory_size)?,
+    };
+    fs::write(Path::new(&out), bundle.encode())?;
+    Ok(())
+}
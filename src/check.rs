@@ -0,0 +1,83 @@
+use crate::{
+    analysis::{reachable, successors},
+    chip8::{Opcode, ADDR_START_PROGRAM},
+    load_rom,
+    options::parse_address,
+};
+use std::{collections::BTreeSet, error::Error, path::Path, result::Result};
+
+const USAGE: &str = "usage: fe_o8 check [--load-addr ADDR] [--entry ADDR] FILE";
+
+/// Decodes a ROM without running it and lists what would go wrong: code that
+/// runs into something that isn't an instruction, or off the end of the
+/// program, jumps and calls that land outside it, and instructions from
+/// extensions fe_o8 doesn't run. Fails if there are any, for scripts.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let (mut start, mut entry) = (ADDR_START_PROGRAM, None);
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--load-addr" => start = parse_address(&args.next().ok_or(USAGE)?)?,
+            "--entry" => entry = Some(parse_address(&args.next().ok_or(USAGE)?)?),
+            _ if arg.starts_with("--") => return Err(USAGE.into()),
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.ok_or(USAGE)?;
+    let (chip8, rom) = load_rom(Path::new(&path), start, entry)?;
+    let memory = &chip8.memory;
+    let end = start + rom.len() as u16;
+    let code = reachable(memory, chip8.entry);
+    let decode = |addr: u16| Opcode::from_slice(&memory[addr as usize..]);
+
+    // Code outside the program is reported where it's jumped to
+    let program = code.range(start..end);
+    let mut problems = BTreeSet::new();
+    for &addr in program.clone() {
+        let op = decode(addr);
+        if let Some(extension) = op.extension() {
+            problems.insert((addr, format!("{} instruction", extension)));
+        }
+        let branch = matches!(op.n0, 0x1 | 0x2);
+        for next in successors(&op, addr).unwrap_or_default() {
+            let problem = if next as usize + 2 > memory.len() {
+                "runs off the end of memory".to_string()
+            } else if !(start..end).contains(&next) {
+                match branch && next == op.a {
+                    true => format!("goes to {:03X}, outside the program", next),
+                    false => "runs off the end of the program".to_string(),
+                }
+            } else if !code.contains(&next) {
+                let word = u16::from_be_bytes([memory[next as usize], memory[next as usize + 1]]);
+                format!(
+                    "goes on to {:04X} at {:03X}, which isn't an instruction",
+                    word, next
+                )
+            } else {
+                continue;
+            };
+            problems.insert((addr, problem));
+        }
+    }
+
+    for (addr, problem) in &problems {
+        let op = decode(*addr);
+        println!(
+            "{:03X}  {:X}{:X}{:X}{:X}  {}",
+            addr, op.n0, op.n1, op.n2, op.n3, problem
+        );
+    }
+    let words = rom.len().div_ceil(2);
+    println!(
+        "{}: {} instructions reachable, {} of {} words taken as data, {} problems",
+        path,
+        program.clone().count(),
+        words.saturating_sub(program.count()),
+        words,
+        problems.len()
+    );
+    match problems.len() {
+        0 => Ok(()),
+        count => Err(format!("{} problems found", count).into()),
+    }
+}
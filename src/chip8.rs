@@ -0,0 +1,1013 @@
+use crate::{
+    peripheral::{self, Peripheral},
+    quirks::Quirks,
+};
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{error::Error, fmt, mem, ops::Range};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+/// Start of the program area, where ROMs are loaded and execution begins
+/// unless they're loaded or entered elsewhere.
+pub const ADDR_START_PROGRAM: u16 = 0x200;
+/// End of the standard 4K of memory, and so of the program area unless the
+/// machine is given more.
+pub const ADDR_PROGRAM_END: u16 = 0x1000;
+/// The most memory a machine can have, as used by XO-CHIP.
+pub const MAX_MEMORY_SIZE: usize = 0x10000;
+/// Where the built-in hex font is stored.
+pub const ADDR_FONT: u16 = 0x050;
+
+pub const FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+const FONT_ADDR: [u16; 16] = [
+    0x050, // 0
+    0x055, // 1
+    0x05A, // 2
+    0x05F, // 3
+    0x064, // 4
+    0x069, // 5
+    0x06E, // 6
+    0x073, // 7
+    0x078, // 8
+    0x07D, // 9
+    0x082, // A
+    0x087, // B
+    0x08C, // C
+    0x091, // D
+    0x096, // E
+    0x09A, // F
+];
+
+/// 64-bit FNV-1a, used wherever a hash has to stay the same across builds.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Something a program did that the machine can't carry on from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Chip8Error {
+    /// A call at `pc` would nest deeper than the stack limit allows.
+    StackOverflow { pc: u16, depth: usize },
+    /// A return at `pc` with nothing on the stack.
+    StackUnderflow { pc: u16 },
+    /// An instruction at `pc` this interpreter doesn't run, and the
+    /// extension to CHIP-8 it comes from, if it's from one.
+    UnknownInstruction {
+        pc: u16,
+        opcode: u16,
+        extension: Option<&'static str>,
+    },
+    /// An instruction at `pc` fetched or touched memory from `addr` on,
+    /// past its end.
+    OutOfBounds { pc: u16, addr: u16 },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Chip8Error::StackOverflow { pc, depth } => {
+                write!(
+                    f,
+                    "call stack overflow at {:03X} ({} calls deep)",
+                    pc, depth
+                )
+            }
+            Chip8Error::StackUnderflow { pc } => {
+                write!(f, "return with an empty call stack at {:03X}", pc)
+            }
+            Chip8Error::UnknownInstruction {
+                pc,
+                opcode,
+                extension: Some(extension),
+            } => write!(
+                f,
+                "{:04X} at {:03X} is a {} instruction; this ROM needs a {} interpreter",
+                opcode, pc, extension, extension
+            ),
+            Chip8Error::UnknownInstruction {
+                pc,
+                opcode,
+                extension: None,
+            } => write!(f, "unknown instruction {:04X} at {:03X}", opcode, pc),
+            Chip8Error::OutOfBounds { pc, addr } => write!(
+                f,
+                "the instruction at {:03X} goes past the end of memory from {:03X}",
+                pc, addr
+            ),
+        }
+    }
+}
+
+impl Error for Chip8Error {}
+
+/// What an instruction did that a frontend may want to react to, so it needn't
+/// compare the whole machine before and after every step. Errors come back as
+/// a `Chip8Error` instead.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SideEffects {
+    /// The display changed; `dirty_rows` says which rows.
+    pub display: bool,
+    /// The sound timer was set from silent to sounding, `Some(true)`, or the
+    /// other way, `Some(false)`.
+    pub sound: Option<bool>,
+    /// BKY is waiting for a key to be pressed and released, and will run
+    /// again next step.
+    pub waiting_for_key: bool,
+    /// The program has ended by jumping to itself.
+    pub halted: bool,
+}
+
+pub struct Chip8 {
+    pub memory: Vec<u8>,
+    pub display: [u64; 32],
+    /// A bit for each display row changed since the frontend last looked.
+    pub dirty_rows: u32,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub delay: u8,
+    pub sound: u8,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub keys: [bool; 16],
+    pub last_keys: [bool; 16],
+    pub quirks: Quirks,
+    pub peripherals: Vec<Box<dyn Peripheral>>,
+    /// Where the ROM was loaded.
+    pub program_start: u16,
+    /// Where the program began running, and so where following its code
+    /// starts.
+    pub entry: u16,
+    /// Where RND gets its bytes, seeded from the OS with `std` and with
+    /// `seed` without it.
+    rng: SmallRng,
+}
+
+/// Instructions grouped by what they work on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    /// Jumps, calls, returns and skips.
+    Flow,
+    Alu,
+    /// Anything that moves I or reads or writes memory through it.
+    Memory,
+    Draw,
+    Input,
+    Timer,
+    Unknown,
+}
+
+#[derive(Debug)]
+pub struct Opcode {
+    pub n0: u8,
+    pub n1: u8,
+    pub n2: u8,
+    pub n3: u8,
+    pub a: u16,
+    pub v: u8,
+}
+impl Opcode {
+    pub fn from_slice(slice: &[u8]) -> Opcode {
+        assert!(slice.len() >= 2);
+        Opcode {
+            n0: (slice[0] & 0xF0) >> 4,
+            n1: slice[0] & 0x0F,
+            n2: (slice[1] & 0xF0) >> 4,
+            n3: slice[1] & 0x0F,
+            a: (slice[0] as u16 & 0x0F) << 8 | slice[1] as u16,
+            v: slice[1],
+        }
+    }
+
+    /// The three-letter code used for this instruction throughout the interpreter.
+    pub fn mnemonic(&self) -> &'static str {
+        match (self.n0, self.n1, self.n2, self.n3) {
+            (0x0, 0x0, 0xE, 0x0) => "CLR",
+            (0x0, 0x0, 0xE, 0xE) => "RTN",
+            (0x1, ..) => "JMP",
+            (0x2, ..) => "CAL",
+            (0x3, ..) => "SEQ",
+            (0x4, ..) => "SNE",
+            (0x5, _, _, 0x0) => "SER",
+            (0x6, ..) => "CAN",
+            (0x7, ..) => "CAD",
+            (0x8, _, _, 0x0) => "ASN",
+            (0x8, _, _, 0x1) => "ORR",
+            (0x8, _, _, 0x2) => "AND",
+            (0x8, _, _, 0x3) => "XOR",
+            (0x8, _, _, 0x4) => "ADD",
+            (0x8, _, _, 0x5) => "SXY",
+            (0x8, _, _, 0x6) => "RSH",
+            (0x8, _, _, 0x7) => "SYX",
+            (0x8, _, _, 0xE) => "LSH",
+            (0x9, _, _, 0x0) => "SNR",
+            (0xA, ..) => "CAI",
+            (0xB, ..) => "J0N",
+            (0xC, ..) => "RND",
+            (0xD, ..) => "DRW",
+            (0xE, _, 0x9, 0xE) => "KYP",
+            (0xE, _, 0xA, 0x1) => "KYR",
+            (0xF, _, 0x0, 0x7) => "DLX",
+            (0xF, _, 0x0, 0xA) => "BKY",
+            (0xF, _, 0x1, 0x5) => "DYS",
+            (0xF, _, 0x1, 0x8) => "SND",
+            (0xF, _, 0x1, 0xE) => "ADI",
+            (0xF, _, 0x2, 0x9) => "RCH",
+            (0xF, _, 0x3, 0x3) => "BCD",
+            (0xF, _, 0x5, 0x5) => "RST",
+            (0xF, _, 0x6, 0x5) => "RLD",
+            _ => "???",
+        }
+    }
+
+    /// What kind of thing the instruction does, for highlighting listings.
+    pub fn class(&self) -> Class {
+        match self.mnemonic() {
+            "JMP" | "CAL" | "RTN" | "J0N" | "SEQ" | "SNE" | "SER" | "SNR" => Class::Flow,
+            "CAN" | "CAD" | "ASN" | "ORR" | "AND" | "XOR" | "ADD" | "SXY" | "RSH" | "SYX"
+            | "LSH" | "RND" => Class::Alu,
+            "CAI" | "ADI" | "RCH" | "BCD" | "RST" | "RLD" => Class::Memory,
+            "CLR" | "DRW" => Class::Draw,
+            "KYP" | "KYR" | "BKY" => Class::Input,
+            "DLX" | "DYS" | "SND" => Class::Timer,
+            _ => Class::Unknown,
+        }
+    }
+
+    /// The extension this instruction belongs to, if it isn't plain CHIP-8.
+    pub fn extension(&self) -> Option<&'static str> {
+        match (self.n0, self.n1, self.n2, self.n3) {
+            (0x0, 0x0, 0xC, _) | (0x0, 0x0, 0xF, 0xB..=0xF) => Some("SCHIP"),
+            (0xD, _, _, 0x0) | (0xF, _, 0x3, 0x0) | (0xF, _, 0x7, 0x5) | (0xF, _, 0x8, 0x5) => {
+                Some("SCHIP")
+            }
+            (0x0, 0x0, 0xD, _) | (0x5, _, _, 0x2) | (0x5, _, _, 0x3) => Some("XO-CHIP"),
+            (0xF, 0x0, 0x0, 0x0)
+            | (0xF, _, 0x0, 0x1)
+            | (0xF, 0x0, 0x0, 0x2)
+            | (0xF, _, 0x3, 0xA) => Some("XO-CHIP"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn new_rng() -> SmallRng {
+    SmallRng::from_entropy()
+}
+
+#[cfg(not(feature = "std"))]
+fn new_rng() -> SmallRng {
+    SmallRng::seed_from_u64(0)
+}
+
+impl Chip8 {
+    pub fn new() -> Chip8 {
+        Chip8::with_memory(ADDR_PROGRAM_END as usize)
+    }
+
+    /// A machine with a given amount of memory, between 4K and 64K.
+    pub fn with_memory(size: usize) -> Chip8 {
+        assert!((ADDR_PROGRAM_END as usize..=MAX_MEMORY_SIZE).contains(&size));
+        let mut chip8 = Chip8 {
+            memory: vec![0; size],
+            display: [0; 32],
+            dirty_rows: u32::MAX,
+            pc: ADDR_START_PROGRAM,
+            stack: vec![],
+            delay: 0x0,
+            sound: 0x0,
+            v: [0; 16],
+            i: 0x0,
+            keys: [false; 16],
+            last_keys: [false; 16],
+            quirks: Quirks::default(),
+            peripherals: Vec::new(),
+            program_start: ADDR_START_PROGRAM,
+            entry: ADDR_START_PROGRAM,
+            rng: new_rng(),
+        };
+        let font = ADDR_FONT as usize;
+        chip8.memory[font..font + FONT.len()].copy_from_slice(&FONT);
+        chip8
+    }
+
+    /// Restarts RND's sequence from `seed`, for boards with no entropy of
+    /// their own or to replay a run.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = SmallRng::seed_from_u64(seed);
+    }
+
+    /// Copies a ROM into the program area, truncating anything that doesn't fit.
+    pub fn load(&mut self, rom: &[u8]) {
+        self.load_at(rom, ADDR_START_PROGRAM);
+    }
+
+    /// Copies a ROM into memory from `start`, truncating anything that
+    /// doesn't fit, to run from there.
+    pub fn load_at(&mut self, rom: &[u8], start: u16) {
+        let from = start as usize;
+        let len = rom.len().min(self.memory.len() - from);
+        self.memory[from..from + len].copy_from_slice(&rom[..len]);
+        self.program_start = start;
+        self.enter_at(start);
+    }
+
+    /// Runs from `entry` rather than from where the ROM was loaded, for
+    /// programs whose first bytes are data.
+    pub fn enter_at(&mut self, entry: u16) {
+        self.entry = entry;
+        self.pc = entry;
+    }
+
+    /// Latches this frame's keypad state, keeping the previous one for BKY.
+    pub fn set_keys(&mut self, keys: [bool; 16]) {
+        self.last_keys = self.keys;
+        self.keys = keys;
+    }
+
+    /// Decrements the delay and sound timers, called once per 60Hz frame.
+    pub fn tick_timers(&mut self) {
+        if self.delay > 0 {
+            self.delay -= 1;
+        }
+        if self.sound > 0 {
+            self.sound -= 1;
+        }
+        for peripheral in &mut self.peripherals {
+            peripheral.tick();
+        }
+    }
+
+    /// Runs one frame without a frontend: a batch of instructions, then the timers.
+    pub fn run_frame(&mut self, instructions: usize) -> Result<(), Chip8Error> {
+        for _ in 0..instructions {
+            self.step()?;
+        }
+        self.tick_timers();
+        Ok(())
+    }
+
+    /// The rows changed since the last call, one bit each with row 0 lowest.
+    pub fn take_dirty_rows(&mut self) -> u32 {
+        mem::take(&mut self.dirty_rows)
+    }
+
+    /// A stable 64-bit FNV-1a hash of the display, for cheaply comparing frames.
+    pub fn display_hash(&self) -> u64 {
+        let bytes: Vec<u8> = self
+            .display
+            .iter()
+            .flat_map(|row| row.to_be_bytes())
+            .collect();
+        fnv1a(&bytes)
+    }
+
+    /// A hash of everything a program can change: memory, the display, the
+    /// registers, timers and stack, for finding where two runs part ways.
+    pub fn state_hash(&self) -> u64 {
+        let mut bytes = self.memory.clone();
+        bytes.extend(self.display.iter().flat_map(|row| row.to_be_bytes()));
+        bytes.extend(self.stack.iter().flat_map(|addr| addr.to_be_bytes()));
+        bytes.extend(self.pc.to_be_bytes());
+        bytes.extend(self.i.to_be_bytes());
+        bytes.extend(self.v);
+        bytes.extend([self.delay, self.sound]);
+        fnv1a(&bytes)
+    }
+
+    /// Whether the program has ended by jumping to the jump it's on, the usual
+    /// way to stop a CHIP-8 program.
+    pub fn jumps_to_itself(&self) -> bool {
+        let pc = self.pc as usize;
+        self.memory.get(pc..pc + 2).is_some_and(|op| {
+            op[0] >> 4 == 0x1 && ((op[0] as u16 & 0x0F) << 8 | op[1] as u16) == self.pc
+        })
+    }
+
+    /// Whether the instruction at PC is BKY, which does nothing until a key
+    /// is pressed and released.
+    pub fn waiting_for_key(&self) -> bool {
+        let pc = self.pc as usize;
+        self.memory
+            .get(pc..pc + 2)
+            .is_some_and(|op| op[0] >> 4 == 0xF && op[1] == 0x0A)
+    }
+
+    /// Maps a device into memory for every instruction from now on.
+    pub fn attach(&mut self, peripheral: Box<dyn Peripheral>) {
+        self.peripherals.push(peripheral);
+    }
+
+    /// Runs a single instruction, with any attached peripherals supplying the
+    /// bytes it reads and seeing the bytes it writes.
+    pub fn step(&mut self) -> Result<SideEffects, Chip8Error> {
+        let mut effects = if self.peripherals.is_empty() {
+            self.execute()?
+        } else {
+            let op = self.fetch()?;
+            let (reads, writes) = peripheral::accesses(&op, self.i);
+            peripheral::supply(&mut self.peripherals, &mut self.memory, reads);
+            let result = self.execute();
+            peripheral::deliver(&mut self.peripherals, &self.memory, writes);
+            result?
+        };
+        effects.halted = self.jumps_to_itself();
+        Ok(effects)
+    }
+
+    /// The instruction at PC, or an error if it runs past the end of memory.
+    pub fn fetch(&self) -> Result<Opcode, Chip8Error> {
+        let bytes = self.span(self.pc, self.pc, 2)?;
+        Ok(Opcode::from_slice(&self.memory[bytes]))
+    }
+
+    /// The `len` addresses from `start` that the instruction at `pc` uses,
+    /// as long as they're all in memory.
+    fn span(&self, pc: u16, start: u16, len: usize) -> Result<Range<usize>, Chip8Error> {
+        let start_index = start as usize;
+        match start_index + len <= self.memory.len() {
+            true => Ok(start_index..start_index + len),
+            false => Err(Chip8Error::OutOfBounds { pc, addr: start }),
+        }
+    }
+
+    /// Fetches, decodes and executes a single instruction.
+    fn execute(&mut self) -> Result<SideEffects, Chip8Error> {
+        let mut effects = SideEffects::default();
+        // Fetch
+        let addr = self.pc;
+        let op = self.fetch()?;
+        self.pc = self.pc.wrapping_add(2);
+        // Decode and Execute
+        match op {
+            Opcode {
+                n0: 0x0,
+                n1: 0x0,
+                n2: 0xE,
+                n3: 0x0,
+                a: _,
+                v: _,
+            } => {
+                self.display = [0; 32];
+                self.dirty_rows = u32::MAX;
+                effects.display = true;
+            } // CLR
+            Opcode {
+                n0: 0x0,
+                n1: 0x0,
+                n2: 0xE,
+                n3: 0xE,
+                a: _,
+                v: _,
+            } => {
+                self.pc = self
+                    .stack
+                    .pop()
+                    .ok_or(Chip8Error::StackUnderflow { pc: addr })?
+            } // RTN
+            Opcode {
+                n0: 0x1,
+                n1: _,
+                n2: _,
+                n3: _,
+                a: nnn,
+                v: _,
+            } => self.pc = nnn, // JMP
+            Opcode {
+                n0: 0x2,
+                n1: _,
+                n2: _,
+                n3: _,
+                a: nnn,
+                v: _,
+            } => {
+                if self
+                    .quirks
+                    .stack_limit
+                    .is_some_and(|limit| self.stack.len() >= limit)
+                {
+                    return Err(Chip8Error::StackOverflow {
+                        pc: addr,
+                        depth: self.stack.len(),
+                    });
+                }
+                self.stack.push(self.pc);
+                self.pc = nnn;
+            } // CAL
+            Opcode {
+                n0: 0x3,
+                n1: x,
+                n2: _,
+                n3: _,
+                a: _,
+                v: nn,
+            } => {
+                let x = x as usize;
+                if self.v[x] == nn {
+                    self.pc = self.pc.wrapping_add(2)
+                }
+            } // SEQ
+            Opcode {
+                n0: 0x4,
+                n1: x,
+                n2: _,
+                n3: _,
+                a: _,
+                v: nn,
+            } => {
+                let x = x as usize;
+                if self.v[x] != nn {
+                    self.pc = self.pc.wrapping_add(2)
+                }
+            } // SNE
+            Opcode {
+                n0: 0x5,
+                n1: x,
+                n2: y,
+                n3: 0x0,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let y = y as usize;
+                if self.v[x] == self.v[y] {
+                    self.pc = self.pc.wrapping_add(2)
+                }
+            } // SER
+            Opcode {
+                n0: 0x6,
+                n1: x,
+                n2: _,
+                n3: _,
+                a: _,
+                v: nn,
+            } => self.v[x as usize] = nn, // CAN
+            Opcode {
+                n0: 0x7,
+                n1: x,
+                n2: _,
+                n3: _,
+                a: _,
+                v: nn,
+            } => {
+                let x = x as usize;
+                let (value, ..) = self.v[x].overflowing_add(nn);
+                self.v[x] = value;
+            } // CAD
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0x0,
+                a: _,
+                v: _,
+            } => self.v[x as usize] = self.v[y as usize], // ASN
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0x1,
+                a: _,
+                v: _,
+            } => {
+                self.v[x as usize] |= self.v[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.v[0xF] = 0;
+                }
+            } // ORR
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0x2,
+                a: _,
+                v: _,
+            } => {
+                self.v[x as usize] &= self.v[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.v[0xF] = 0;
+                }
+            } // AND
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0x3,
+                a: _,
+                v: _,
+            } => {
+                self.v[x as usize] ^= self.v[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.v[0xF] = 0;
+                }
+            } // XOR
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0x4,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let y = y as usize;
+                let (value, carry) = self.v[x].overflowing_add(self.v[y]);
+                self.v[x] = value;
+                self.v[0xF] = carry as u8;
+            } // ADD
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0x5,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let y = y as usize;
+                let (value, carry) = self.v[x].overflowing_sub(self.v[y]);
+                self.v[x] = value;
+                self.v[0xF] = !carry as u8;
+            } // SXY
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0x6,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let y = if self.quirks.shift_vx { x } else { y as usize };
+                let (value, carry) = self.v[y].overflowing_shr(1);
+                self.v[x] = value;
+                self.v[0xF] = carry as u8;
+            } // RSH
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0x7,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let y = y as usize;
+                let (value, carry) = self.v[y].overflowing_sub(self.v[x]);
+                self.v[x] = value;
+                self.v[0xF] = !carry as u8;
+            } // SYX
+            Opcode {
+                n0: 0x8,
+                n1: x,
+                n2: y,
+                n3: 0xE,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let y = if self.quirks.shift_vx { x } else { y as usize };
+                let (value, carry) = self.v[y].overflowing_shl(1);
+                self.v[x] = value;
+                self.v[0xF] = carry as u8;
+            } // LSH
+            Opcode {
+                n0: 0x9,
+                n1: x,
+                n2: y,
+                n3: 0x0,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let y = y as usize;
+                if self.v[x] != self.v[y] {
+                    self.pc = self.pc.wrapping_add(2)
+                }
+            } // SNR
+            Opcode {
+                n0: 0xA,
+                n1: _,
+                n2: _,
+                n3: _,
+                a: nnn,
+                v: _,
+            } => self.i = nnn, // CAI
+            Opcode {
+                n0: 0xB,
+                n1: x,
+                n2: _,
+                n3: _,
+                a: nnn,
+                v: _,
+            } => {
+                let offset = if self.quirks.jump_vx { x as usize } else { 0 };
+                self.pc = nnn + self.v[offset] as u16;
+            } // J0N
+            Opcode {
+                n0: 0xC,
+                n1: x,
+                n2: _,
+                n3: _,
+                a: _,
+                v: nn,
+            } => self.v[x as usize] = self.rng.gen::<u8>() & nn, // RND
+            Opcode {
+                n0: 0xD,
+                n1: x,
+                n2: y,
+                n3: n,
+                a: _,
+                v: _,
+            } => {
+                // Sprites start wrapped onto the screen but are clipped at its
+                // right and bottom edges
+                let coord_x = self.v[x as usize] % 64;
+                let coord_y = self.v[y as usize] as usize % 32;
+                // Rows clipped at the bottom aren't read
+                let sprite = self.span(addr, self.i, (n as usize).min(32 - coord_y))?;
+                let mut collided = false;
+                for (row, addr) in (coord_y..32).zip(sprite) {
+                    // The byte's first pixel at the display's left edge, then
+                    // moved right; pixels past the right edge fall off the end
+                    let mask = (self.memory[addr] as u64) << 56 >> coord_x;
+                    collided |= mask & self.display[row] != 0;
+                    self.display[row] ^= mask;
+                    if mask != 0 {
+                        self.dirty_rows |= 1 << row;
+                        effects.display = true;
+                    }
+                }
+                self.v[0xF] = collided as u8;
+            } // DRW
+            Opcode {
+                n0: 0xE,
+                n1: x,
+                n2: 0x9,
+                n3: 0xE,
+                a: _,
+                v: _,
+            } => {
+                if self.keys[self.v[x as usize] as usize & 0x0F] {
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            } // KYP
+            Opcode {
+                n0: 0xE,
+                n1: x,
+                n2: 0xA,
+                n3: 0x1,
+                a: _,
+                v: _,
+            } => {
+                if !self.keys[self.v[x as usize] as usize & 0x0F] {
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            } // KYR
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x0,
+                n3: 0x7,
+                a: _,
+                v: _,
+            } => self.v[x as usize] = self.delay, // DLX
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x0,
+                n3: 0xA,
+                a: _,
+                v: _,
+            } => {
+                self.pc = self.pc.wrapping_sub(2);
+                effects.waiting_for_key = true;
+                'char: for k in 0x0..=0xF {
+                    if self.last_keys[k] && (self.last_keys[k] ^ self.keys[k]) {
+                        self.v[x as usize] = k as u8;
+                        self.pc = self.pc.wrapping_add(2);
+                        effects.waiting_for_key = false;
+                        break 'char;
+                    }
+                }
+            } // BKY
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x1,
+                n3: 0x5,
+                a: _,
+                v: _,
+            } => self.delay = self.v[x as usize], // DYS
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x1,
+                n3: 0x8,
+                a: _,
+                v: _,
+            } => {
+                let sound = self.v[x as usize];
+                if (sound > 0) != (self.sound > 0) {
+                    effects.sound = Some(sound > 0);
+                }
+                self.sound = sound;
+            } // SND
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x1,
+                n3: 0xE,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let value = self.i.wrapping_add(self.v[x] as u16);
+                if self.quirks.add_i_sets_vf {
+                    self.v[0xF] = (value & 0xF000 > 0) as u8;
+                }
+                self.i = value;
+            } // ADI
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x2,
+                n3: 0x9,
+                a: _,
+                v: _,
+            } => self.i = FONT_ADDR[self.v[x as usize] as usize & 0x0F], // RCH
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x3,
+                n3: 0x3,
+                a: _,
+                v: _,
+            } => {
+                let value = self.v[x as usize];
+                let digits = self.span(addr, self.i, 3)?;
+                self.memory[digits].copy_from_slice(&[value / 100, value / 10 % 10, value % 10]);
+            } // BCD
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x5,
+                n3: 0x5,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let registers = self.span(addr, self.i, x + 1)?;
+                self.memory[registers].copy_from_slice(&self.v[0..=x]);
+                if self.quirks.load_store_increments_i {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            } // RST
+            Opcode {
+                n0: 0xF,
+                n1: x,
+                n2: 0x6,
+                n3: 0x5,
+                a: _,
+                v: _,
+            } => {
+                let x = x as usize;
+                let registers = self.span(addr, self.i, x + 1)?;
+                self.v[0..=x].copy_from_slice(&self.memory[registers]);
+                if self.quirks.load_store_increments_i {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            } // RLD
+
+            _ => {
+                return Err(Chip8Error::UnknownInstruction {
+                    pc: addr,
+                    opcode: op.a | (op.n0 as u16) << 12,
+                    extension: op.extension(),
+                })
+            }
+        }
+        Ok(effects)
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Chip8 {
+        Chip8::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A machine with `program` loaded and I pointing at `i`.
+    fn machine(memory: usize, program: &[u8], i: u16) -> Chip8 {
+        let mut chip8 = Chip8::with_memory(memory);
+        chip8.load(program);
+        chip8.i = i;
+        chip8
+    }
+
+    #[test]
+    fn runs_the_last_word_of_memory() {
+        let rom = [0x60, 0x00].repeat((0x1000 - 0x200) / 2);
+        let mut chip8 = machine(0x1000, &rom, 0);
+        for _ in 0..rom.len() / 2 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::OutOfBounds {
+                pc: 0x1000,
+                addr: 0x1000
+            })
+        );
+    }
+
+    #[test]
+    fn fetch_past_the_end() {
+        let mut chip8 = machine(0x1000, &[0x1F, 0xFF], 0);
+        chip8.step().unwrap();
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::OutOfBounds {
+                pc: 0xFFF,
+                addr: 0xFFF
+            })
+        );
+    }
+
+    #[test]
+    fn bcd_past_the_end() {
+        let mut chip8 = machine(0x1000, &[0xF0, 0x33], 0xFFE);
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::OutOfBounds {
+                pc: 0x200,
+                addr: 0xFFE
+            })
+        );
+    }
+
+    #[test]
+    fn store_and_load_past_the_end() {
+        for program in [[0xF2, 0x55], [0xF2, 0x65]] {
+            let mut chip8 = machine(0x1000, &program, 0xFFE);
+            assert_eq!(
+                chip8.step(),
+                Err(Chip8Error::OutOfBounds {
+                    pc: 0x200,
+                    addr: 0xFFE
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn draw_past_the_end() {
+        let mut chip8 = machine(0x1000, &[0xD0, 0x05], 0xFFE);
+        assert_eq!(
+            chip8.step(),
+            Err(Chip8Error::OutOfBounds {
+                pc: 0x200,
+                addr: 0xFFE
+            })
+        );
+    }
+
+    #[test]
+    fn store_wraps_i_at_the_top_of_memory() {
+        let mut chip8 = machine(MAX_MEMORY_SIZE, &[0xFF, 0x55], 0xFFF0);
+        chip8.quirks.load_store_increments_i = true;
+        chip8.step().unwrap();
+        assert_eq!(chip8.i, 0);
+    }
+
+    #[test]
+    fn waits_for_a_key_at_the_top_of_memory() {
+        let mut chip8 = machine(MAX_MEMORY_SIZE, &[], 0);
+        chip8.memory[0xFFFE..].copy_from_slice(&[0xF0, 0x0A]);
+        chip8.pc = 0xFFFE;
+        chip8.step().unwrap();
+        assert_eq!(chip8.pc, 0xFFFE);
+    }
+}
@@ -0,0 +1,27 @@
+use crate::multiplexer::Multiplexer;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::Command;
+use std::fmt;
+
+/// Puts text on the clipboard of the terminal fe_o8 is showing in, which is
+/// the one in front of the player even over SSH (OSC 52). Terminals that
+/// don't allow it ignore it. Inside a multiplexer it's also sent wrapped to
+/// pass through, for when the multiplexer won't set the clipboard itself.
+pub struct CopyToClipboard<'a>(pub &'a str, pub Option<Multiplexer>);
+
+impl Command for CopyToClipboard<'_> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(self.0));
+        f.write_str(&sequence)?;
+        match self.1 {
+            Some(multiplexer) => f.write_str(&multiplexer.passthrough(&sequence)),
+            None => Ok(()),
+        }
+    }
+
+    /// Older Windows consoles have no clipboard to reach this way.
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
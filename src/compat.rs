@@ -0,0 +1,104 @@
+use crate::{
+    chip8::{Chip8, ADDR_PROGRAM_END},
+    quirks::{self, Profile, Quirks},
+    read_rom, INSTRUCTIONS_PER_FRAME,
+};
+use std::{error::Error, path::Path, result::Result};
+
+const USAGE: &str = "usage: fe_o8 compat FILE [--frames N]";
+
+/// How a run under one set of quirks went.
+struct Run {
+    name: &'static str,
+    /// The display hash after each frame that ran.
+    hashes: Vec<u64>,
+    outcome: String,
+}
+
+/// Runs a ROM headless under each quirks profile and reports how each run
+/// ended and from which frame their displays differ, to help pick a profile
+/// for an unknown ROM.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let mut path = None;
+    let mut frames: usize = 600;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => frames = args.next().ok_or(USAGE)?.parse()?,
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.ok_or(USAGE)?;
+    let rom = read_rom(Path::new(&path), ADDR_PROGRAM_END as usize)?;
+
+    let mut profiles = vec![("default", Quirks::default())];
+    profiles.extend(Profile::ALL.map(|profile| (profile.name(), profile.quirks())));
+    let runs: Vec<Run> = (profiles.into_iter())
+        .map(|(name, quirks)| run_with(name, quirks, &rom, frames))
+        .collect();
+
+    for run in &runs {
+        let last = run
+            .hashes
+            .last()
+            .map_or("-".into(), |hash| format!("{:016x}", hash));
+        println!("{:<8} {}  {}", run.name, last, run.outcome);
+    }
+    for (index, first) in runs.iter().enumerate() {
+        for second in &runs[index + 1..] {
+            let frame = (first.hashes.iter().zip(&second.hashes)).position(|(a, b)| a != b);
+            match frame {
+                Some(frame) => println!(
+                    "{} and {} first differ at frame {}",
+                    first.name, second.name, frame
+                ),
+                None => println!(
+                    "{} and {} draw the same while both run",
+                    first.name, second.name
+                ),
+            }
+        }
+    }
+    let mut chip8 = Chip8::new();
+    chip8.load(&rom);
+    let detection = quirks::detect(&chip8.memory, chip8.entry);
+    println!(
+        "detected: {} ({})",
+        detection.profile.name(),
+        detection.reasons.join(", ")
+    );
+    Ok(())
+}
+
+fn run_with(name: &'static str, quirks: Quirks, rom: &[u8], frames: usize) -> Run {
+    let mut chip8 = Chip8::new();
+    chip8.quirks = quirks;
+    chip8.load(rom);
+    let mut hashes = vec![];
+    for frame in 0..frames {
+        let outcome = match chip8.run_frame(INSTRUCTIONS_PER_FRAME) {
+            Ok(()) if chip8.jumps_to_itself() => {
+                format!("stopped at {:03X} in frame {}", chip8.pc, frame)
+            }
+            Ok(()) => {
+                hashes.push(chip8.display_hash());
+                continue;
+            }
+            Err(error) => format!("halted in frame {}: {}", frame, error),
+        };
+        hashes.push(chip8.display_hash());
+        return Run {
+            name,
+            hashes,
+            outcome,
+        };
+    }
+    let outcome = match chip8.waiting_for_key() {
+        true => format!("waiting for a key at {:03X}", chip8.pc),
+        false => format!("ran {} frames", frames),
+    };
+    Run {
+        name,
+        hashes,
+        outcome,
+    }
+}
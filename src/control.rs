@@ -0,0 +1,308 @@
+use crate::{chip8::Chip8, debugger::Region, expr::Expr, INSTRUCTIONS_PER_FRAME};
+#[cfg(unix)]
+use std::{
+    error::Error,
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    thread,
+};
+use std::{
+    path::PathBuf,
+    result::Result,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+/// A command sent to a running instance over the control socket.
+#[derive(Debug)]
+pub enum Command {
+    Pause,
+    Resume,
+    /// Execute this many instructions, meant for use while paused.
+    Step(u32),
+    Poke(u16, u8),
+    /// Hold a keypad key down for this many frames.
+    PressKey(usize, u32),
+    Screenshot,
+    /// Report registers, timers and keys as JSON.
+    Registers,
+    /// Put registers, stack, timers and the code around PC on the clipboard
+    /// as JSON, for pasting into a bug report.
+    Copy,
+    /// Work out an expression over registers and memory.
+    Print(Expr),
+    /// Stop at an address, or only when the condition there isn't zero.
+    Break(u16, Option<Expr>),
+    Unbreak(u16),
+    /// Stop when the byte at this address changes.
+    Watch(u16),
+    Unwatch(u16),
+    /// Stop before a sprite is drawn into this part of the display.
+    BreakDraw(Region),
+    UnbreakDraw(Region),
+    /// List breakpoints, watchpoints, labels and notes.
+    Breakpoints,
+    /// Find a byte sequence in memory, or with `narrow` only among the
+    /// addresses the last search found.
+    Search {
+        pattern: Vec<u8>,
+        narrow: bool,
+    },
+    /// Name an address; an empty name removes it.
+    Label(u16, String),
+    Note(u16, String),
+    /// Write breakpoints, labels, notes and the machine to one file.
+    Export(PathBuf),
+    Import(PathBuf),
+}
+
+impl Command {
+    /// Whether anyone who can reach the HTTP server may send it: commands
+    /// that play or only look, stepping no more than a frame's worth of
+    /// instructions at once since they run before the next frame is drawn.
+    /// Changing memory or breakpoints, using the clipboard and reading or
+    /// writing files are left to the socket.
+    pub fn remote(&self) -> bool {
+        match self {
+            Command::Step(count) => *count as usize <= INSTRUCTIONS_PER_FRAME,
+            command => matches!(
+                command,
+                Command::Pause
+                    | Command::Resume
+                    | Command::PressKey(..)
+                    | Command::Screenshot
+                    | Command::Registers
+                    | Command::Print(_)
+                    | Command::Breakpoints
+            ),
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |index: usize, default: Option<u32>| -> Result<u32, String> {
+            match (words.get(index), default) {
+                (Some(word), _) => parse_number(word),
+                (None, Some(default)) => Ok(default),
+                (None, None) => Err(format!("'{}' needs more arguments", words[0])),
+            }
+        };
+        let address = |index: usize| u16::try_from(number(index, None)?).map_err(|e| e.to_string());
+        let side = |index: usize| u8::try_from(number(index, None)?).map_err(|e| e.to_string());
+        let region = || -> Result<Region, String> {
+            Ok(Region {
+                x: side(1)?,
+                y: side(2)?,
+                width: side(3)?,
+                height: side(4)?,
+            })
+        };
+        let rest = |index: usize| words.get(index..).unwrap_or_default().join(" ");
+        let path = || match rest(1) {
+            path if path.is_empty() => Err(format!("'{}' needs a path", words[0])),
+            path => Ok(PathBuf::from(path)),
+        };
+        let command = match words.first().copied() {
+            Some("pause") => Command::Pause,
+            Some("resume") => Command::Resume,
+            Some("step") => Command::Step(number(1, Some(1))?),
+            Some("poke") => Command::Poke(
+                address(1)?,
+                u8::try_from(number(2, None)?).map_err(|e| e.to_string())?,
+            ),
+            Some("press-key") => {
+                let key = number(1, None)? as usize;
+                if key > 0xF {
+                    return Err(format!("no key {:x}", key));
+                }
+                Command::PressKey(key, number(2, Some(5))?)
+            }
+            Some("screenshot") => Command::Screenshot,
+            Some("registers") => Command::Registers,
+            Some("copy") => Command::Copy,
+            Some("print") => Command::Print(Expr::parse(&rest(1))?),
+            Some("break") => {
+                let condition = match words.get(2).copied() {
+                    Some("if") => Some(Expr::parse(&rest(3))?),
+                    Some(other) => return Err(format!("expected 'if', found '{}'", other)),
+                    None => None,
+                };
+                Command::Break(address(1)?, condition)
+            }
+            Some("unbreak") => Command::Unbreak(address(1)?),
+            Some("watch") => Command::Watch(address(1)?),
+            Some("unwatch") => Command::Unwatch(address(1)?),
+            Some("break-draw") => Command::BreakDraw(region()?),
+            Some("unbreak-draw") => Command::UnbreakDraw(region()?),
+            Some("breakpoints") => Command::Breakpoints,
+            Some("label") => Command::Label(address(1)?, rest(2)),
+            Some("note") => Command::Note(address(1)?, rest(2)),
+            Some("export") => Command::Export(path()?),
+            Some("import") => Command::Import(path()?),
+            Some(word @ ("search" | "narrow")) => Command::Search {
+                pattern: parse_pattern(&words[1..])?,
+                narrow: word == "narrow",
+            },
+            Some(other) => return Err(format!("unknown command '{}'", other)),
+            None => return Err("empty command".into()),
+        };
+        Ok(command)
+    }
+}
+
+/// Parses `0x` prefixed hex or plain decimal.
+fn parse_number(word: &str) -> Result<u32, String> {
+    match word.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => word.parse(),
+    }
+    .map_err(|_| format!("invalid number '{}'", word))
+}
+
+/// Either bytes, or `word N` for a 16-bit value stored high byte first.
+fn parse_pattern(words: &[&str]) -> Result<Vec<u8>, String> {
+    let pattern = match words {
+        ["word", value] => u16::try_from(parse_number(value)?)
+            .map_err(|e| e.to_string())?
+            .to_be_bytes()
+            .to_vec(),
+        _ => words
+            .iter()
+            .map(|word| u8::try_from(parse_number(word)?).map_err(|e| e.to_string()))
+            .collect::<Result<_, _>>()?,
+    };
+    if pattern.is_empty() {
+        return Err("nothing to search for".into());
+    }
+    Ok(pattern)
+}
+
+/// A parsed command waiting for the main loop, with the way back to its sender.
+pub struct Request {
+    pub command: Command,
+    reply: Sender<String>,
+}
+
+impl Request {
+    /// Creates a request along with the receiver its reply will arrive on.
+    pub fn new(command: Command) -> (Request, Receiver<String>) {
+        let (reply, answer) = channel();
+        (Request { command, reply }, answer)
+    }
+
+    /// Answers the request with its output, empty if it has none.
+    pub fn reply(self, text: String) {
+        let _ = self.reply.send(text);
+    }
+}
+
+/// Collects requests from every remote interface for the main loop to
+/// handle once per frame.
+pub struct Controls {
+    sender: Sender<Request>,
+    requests: Receiver<Request>,
+}
+
+impl Controls {
+    pub fn new() -> Controls {
+        let (sender, requests) = channel();
+        Controls { sender, requests }
+    }
+
+    pub fn sender(&self) -> Sender<Request> {
+        self.sender.clone()
+    }
+
+    /// Returns the requests that arrived since the last call.
+    pub fn pending(&self) -> impl Iterator<Item = Request> + '_ {
+        self.requests.try_iter()
+    }
+}
+
+/// A Unix domain socket accepting one command per line. Every command is
+/// answered with any output followed by `ok`, or a single `error: ...` line.
+#[cfg(unix)]
+pub struct ControlSocket {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl ControlSocket {
+    pub fn bind(path: &Path, requests: Sender<Request>) -> Result<ControlSocket, Box<dyn Error>> {
+        // A socket left behind by an instance that didn't exit cleanly, and
+        // nothing else: not a live instance's, and not some other file
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(format!("{} exists and isn't a socket", path.display()).into());
+            }
+            if UnixStream::connect(path).is_ok() {
+                return Err(format!("{} is in use by another instance", path.display()).into());
+            }
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let requests = requests.clone();
+                thread::spawn(move || serve(stream, requests));
+            }
+        });
+        Ok(ControlSocket { path: path.into() })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn serve(stream: UnixStream, requests: Sender<Request>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { return };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match Command::parse(&line) {
+            Ok(command) => {
+                let (request, answer) = Request::new(command);
+                if requests.send(request).is_err() {
+                    return;
+                }
+                match answer.recv() {
+                    Ok(text) if text.is_empty() => "ok\n".to_string(),
+                    Ok(text) => format!("{}\nok\n", text.trim_end()),
+                    Err(_) => return,
+                }
+            }
+            Err(error) => format!("error: {}\n", error),
+        };
+        if writer.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+/// The display as text, one line per row with `#` for lit pixels.
+pub fn screenshot(chip8: &Chip8) -> String {
+    chip8
+        .display
+        .iter()
+        .map(|row| {
+            (0..64)
+                .map(|bit| if row >> (63 - bit) & 1 == 1 { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
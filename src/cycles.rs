@@ -0,0 +1,54 @@
+use crate::chip8::Opcode;
+
+/// What each kind of instruction costs out of a frame's budget, which by
+/// default is one apiece, twelve to a frame. Historical machines took far
+/// longer to draw than to add, which weighting `draw` approximates.
+#[derive(Clone, Copy)]
+pub struct Cycles {
+    costs: [usize; Cycles::NAMES.len()],
+}
+
+impl Cycles {
+    /// Every instruction costs one, so a budget is a number of instructions.
+    pub const FLAT: Cycles = Cycles {
+        costs: [1; Cycles::NAMES.len()],
+    };
+
+    /// The classes costs can be set for, in `Class` order.
+    pub const NAMES: [&'static str; 7] =
+        ["flow", "alu", "memory", "draw", "input", "timer", "unknown"];
+
+    /// Sets costs from `CLASS=N,...`, leaving the rest as they were.
+    pub fn parse(&mut self, text: &str) -> Result<(), String> {
+        for setting in text.split(',') {
+            let (name, cost) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("expected CLASS=CYCLES, got {}", setting))?;
+            let class = Cycles::NAMES
+                .iter()
+                .position(|known| *known == name)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown instruction class {}, expected one of {}",
+                        name,
+                        Cycles::NAMES.join(", ")
+                    )
+                })?;
+            self.costs[class] = match cost.parse() {
+                Ok(0) | Err(_) => return Err(format!("invalid cycle count {}", cost)),
+                Ok(cost) => cost,
+            };
+        }
+        Ok(())
+    }
+
+    pub fn cost(&self, op: &Opcode) -> usize {
+        self.costs[op.class() as usize]
+    }
+}
+
+impl Default for Cycles {
+    fn default() -> Cycles {
+        Cycles::FLAT
+    }
+}
@@ -0,0 +1,299 @@
+use crate::{
+    chip8::{Chip8, Chip8Error, Opcode},
+    cycles::Cycles,
+    expr::Expr,
+    profiler::{CallProfile, Counters, Profiler},
+    quirks::Check,
+    savestate::{self, SaveState},
+    storage::data_dir,
+    trace::Trace,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    result::Result,
+};
+
+/// Why execution stopped before running everything it was asked to.
+pub enum Stop {
+    Breakpoint(u16),
+    Watchpoint { addr: u16, old: u8, new: u8 },
+    Draw { pc: u16, region: Region },
+    Halted(Chip8Error),
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stop::Breakpoint(addr) => write!(f, "breakpoint at {:03X}", addr),
+            Stop::Draw { pc, region } => write!(f, "draw at {:03X} into {}", pc, region),
+            Stop::Watchpoint { addr, old, new } => {
+                write!(
+                    f,
+                    "watchpoint {:03X} changed {:02X} -> {:02X}",
+                    addr, old, new
+                )
+            }
+            Stop::Halted(error) => write!(f, "halted: {}", error),
+        }
+    }
+}
+
+/// A rectangle of the display, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Region {
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{} {}x{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl Region {
+    /// Whether the DXYN at PC would draw a lit pixel inside the region.
+    /// Sprites are clipped at the edges, as the interpreter does.
+    fn drawn_by(&self, chip8: &Chip8, op: &Opcode) -> bool {
+        let x = chip8.v[op.n1 as usize] % 64;
+        let top = chip8.v[op.n2 as usize] as usize % 32;
+        if self.width == 0 || self.x >= 64 {
+            return false;
+        }
+        let end = (self.x as u32 + self.width as u32).min(64);
+        let columns = !0u64 >> self.x & !(!0u64).checked_shr(end).unwrap_or(0);
+        let rows = self.y as usize..self.y as usize + self.height as usize;
+        (0..op.n3 as usize)
+            .map(|row| (top + row, chip8.i as usize + row))
+            .take_while(|(y, _)| *y < 32)
+            .filter(|(y, _)| rows.contains(y))
+            .any(|(_, addr)| {
+                let byte = chip8.memory[addr % chip8.memory.len()];
+                (byte as u64) << 56 >> x & columns != 0
+            })
+    }
+}
+
+/// Breakpoints, watchpoints, labels and notes, kept per ROM between sessions.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Debugger {
+    pub breakpoints: BTreeSet<u16>,
+    /// Breakpoints that only stop when their expression isn't zero.
+    #[serde(default)]
+    pub conditions: BTreeMap<u16, Expr>,
+    /// Memory addresses that stop execution when their value changes.
+    pub watchpoints: BTreeSet<u16>,
+    /// Parts of the display that stop execution when a sprite is drawn there.
+    #[serde(default)]
+    pub draw_regions: BTreeSet<Region>,
+    /// Names for addresses, shown in the disassembly.
+    #[serde(default)]
+    pub labels: BTreeMap<u16, String>,
+    #[serde(default)]
+    pub notes: BTreeMap<u16, String>,
+    /// A breakpoint that was just hit, so continuing doesn't hit it again at once.
+    #[serde(skip)]
+    resume_from: Option<u16>,
+    #[serde(skip)]
+    pub profiler: Profiler,
+    #[serde(skip)]
+    pub counters: Counters,
+    #[serde(skip)]
+    pub calls: CallProfile,
+    /// Where the last memory search matched.
+    #[serde(skip)]
+    found: Vec<u16>,
+    /// Watching how the program runs to correct guessed quirks.
+    #[serde(skip)]
+    pub quirk_check: Option<Check>,
+}
+
+impl Debugger {
+    /// The debugger saved for a ROM, or an empty one.
+    pub fn load(rom_hash: &str) -> Debugger {
+        fs::read(path(rom_hash))
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, rom_hash: &str) -> Result<(), Box<dyn Error>> {
+        let path = path(rom_hash);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Writes the debugger and the machine to one file, to pick up later or
+    /// pass to someone else.
+    pub fn export(&self, chip8: &Chip8, rom_hash: &str, path: &Path) -> Result<(), Box<dyn Error>> {
+        let session = Session {
+            debugger: self,
+            state: SaveState::capture(chip8, rom_hash),
+        };
+        fs::write(path, serde_json::to_vec_pretty(&session)?)?;
+        Ok(())
+    }
+
+    /// Reads an exported session into the machine, returning its debugger.
+    pub fn import(
+        chip8: &mut Chip8,
+        rom_hash: &str,
+        path: &Path,
+    ) -> Result<Debugger, Box<dyn Error>> {
+        let session: Session<Debugger> = serde_json::from_slice(&fs::read(path)?)?;
+        if session.state.rom_hash != rom_hash {
+            return Err("exported from a different ROM".into());
+        }
+        session.state.restore(chip8)?;
+        Ok(session.debugger)
+    }
+
+    /// Runs instructions until they've cost `budget` cycles, stopping early at
+    /// a breakpoint, a watched byte changing, or an error.
+    pub fn run(
+        &mut self,
+        chip8: &mut Chip8,
+        budget: usize,
+        cycles: &Cycles,
+        mut trace: Option<&mut Trace>,
+    ) -> Option<Stop> {
+        let mut spent = 0;
+        while spent < budget {
+            let resuming = self.resume_from.take() == Some(chip8.pc);
+            let op = match chip8.fetch() {
+                Ok(op) => op,
+                Err(error) => return Some(Stop::Halted(error)),
+            };
+            if !resuming && self.breakpoints.contains(&chip8.pc) && self.condition_holds(chip8) {
+                self.resume_from = Some(chip8.pc);
+                return Some(Stop::Breakpoint(chip8.pc));
+            }
+            if !resuming && op.mnemonic() == "DRW" {
+                let drawn = self.draw_regions.iter().find(|r| r.drawn_by(chip8, &op));
+                if let Some(&region) = drawn {
+                    self.resume_from = Some(chip8.pc);
+                    return Some(Stop::Draw {
+                        pc: chip8.pc,
+                        region,
+                    });
+                }
+            }
+            if let Some(check) = self.quirk_check.as_mut() {
+                check.before(chip8, &op);
+            }
+            self.profiler.record(chip8.pc, chip8.memory.len());
+            if let Some(trace) = trace.as_mut() {
+                trace.record(chip8);
+            }
+            let watched: Vec<(u16, u8)> = self
+                .watchpoints
+                .iter()
+                .map(|addr| (*addr, chip8.memory[*addr as usize % chip8.memory.len()]))
+                .collect();
+            spent += cycles.cost(&op);
+            if let Err(error) = chip8.step() {
+                return Some(Stop::Halted(error));
+            }
+            self.counters.record(&op, chip8);
+            self.calls.record(&op, chip8);
+            for (addr, old) in watched {
+                let new = chip8.memory[addr as usize % chip8.memory.len()];
+                if new != old {
+                    return Some(Stop::Watchpoint { addr, old, new });
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether the breakpoint at PC should stop. One whose condition can't be
+    /// worked out, dividing by zero say, stops so it's noticed.
+    fn condition_holds(&self, chip8: &Chip8) -> bool {
+        match self.conditions.get(&chip8.pc) {
+            Some(condition) => condition.eval(chip8) != Ok(0),
+            None => true,
+        }
+    }
+
+    /// Finds every address holding `pattern`. Narrowing only looks at the
+    /// addresses the last search found, to home in on a value as it changes.
+    pub fn search(&mut self, memory: &[u8], pattern: &[u8], narrow: bool) -> &[u16] {
+        let matches = |addr: &u16| memory[*addr as usize..].starts_with(pattern);
+        self.found = if narrow {
+            self.found.iter().copied().filter(matches).collect()
+        } else {
+            (0..memory.len())
+                .map(|addr| addr as u16)
+                .filter(matches)
+                .collect()
+        };
+        &self.found
+    }
+
+    /// The breakpoints, watchpoints and watched display regions, one per line.
+    pub fn list(&self) -> String {
+        let breakpoints = self
+            .breakpoints
+            .iter()
+            .map(|addr| match self.conditions.get(addr) {
+                Some(condition) => format!("break {:03X} if {}", addr, condition),
+                None => format!("break {:03X}", addr),
+            });
+        let watchpoints = self
+            .watchpoints
+            .iter()
+            .map(|addr| format!("watch {:03X}", addr));
+        let regions = self.draw_regions.iter().map(|region| {
+            format!(
+                "break-draw {} {} {} {}",
+                region.x, region.y, region.width, region.height
+            )
+        });
+        breakpoints
+            .chain(watchpoints)
+            .chain(regions)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// An exported debugger along with the machine it was debugging.
+#[derive(Serialize, Deserialize)]
+struct Session<D> {
+    debugger: D,
+    #[serde(deserialize_with = "savestate::deserialize")]
+    state: SaveState,
+}
+
+fn path(rom_hash: &str) -> PathBuf {
+    data_dir().join("debug").join(format!("{}.json", rom_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halts_on_the_last_byte_of_memory() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0x1F, 0xFF]);
+        let mut debugger = Debugger::default();
+        let stop = debugger.run(&mut chip8, 2, &Default::default(), None);
+        assert!(matches!(
+            stop,
+            Some(Stop::Halted(Chip8Error::OutOfBounds {
+                pc: 0xFFF,
+                addr: 0xFFF
+            }))
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{
+    analysis::branch_targets,
+    chip8::{Opcode, ADDR_START_PROGRAM},
+    load_rom,
+    options::{parse_address, parse_syntax},
+    syntax::Syntax,
+    ui::class_color,
+};
+use crossterm::style::Stylize;
+use std::{
+    error::Error,
+    io::{self, IsTerminal, Write},
+    path::Path,
+    result::Result,
+};
+
+const USAGE: &str =
+    "usage: fe_o8 disasm [--syntax fe|chipper|octo] [--load-addr ADDR] [--entry ADDR] FILE";
+
+/// Lists a ROM one instruction per line, marking where jumps and calls land
+/// and coloring instructions by class when printing to a terminal.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let mut syntax = Syntax::default();
+    let (mut start, mut entry) = (ADDR_START_PROGRAM, None);
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--syntax" => syntax = parse_syntax(&args.next().ok_or(USAGE)?)?,
+            "--load-addr" => start = parse_address(&args.next().ok_or(USAGE)?)?,
+            "--entry" => entry = Some(parse_address(&args.next().ok_or(USAGE)?)?),
+            _ if arg.starts_with("--") => return Err(USAGE.into()),
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.ok_or(USAGE)?;
+    let (chip8, rom) = load_rom(Path::new(&path), start, entry)?;
+    let targets = branch_targets(&chip8.memory, chip8.entry);
+    let color = io::stdout().is_terminal();
+
+    let mut out = io::stdout().lock();
+    let end = start + rom.len() as u16;
+    for addr in (start..end).step_by(2) {
+        let bytes = &chip8.memory[addr as usize..];
+        let op = Opcode::from_slice(bytes);
+        let target = if targets.contains(&addr) { '▸' } else { ' ' };
+        write!(
+            out,
+            "{}{:03X}  {:02X}{:02X}  ",
+            target, addr, bytes[0], bytes[1]
+        )?;
+        let instruction = syntax.format(&op);
+        if color {
+            writeln!(out, "{}", instruction.with(class_color(op.class()).into()))?;
+        } else {
+            writeln!(out, "{}", instruction)?;
+        }
+    }
+    Ok(())
+}
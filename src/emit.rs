@@ -0,0 +1,78 @@
+use crate::chip8::Chip8;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Streams one JSON object per frame describing the machine state, for
+/// scripts and visualizers that want more than the TUI shows.
+pub struct StateEmitter {
+    out: BufWriter<File>,
+    frame: u64,
+}
+
+impl StateEmitter {
+    /// Creates the file at `path`. A descriptor the parent opened is reached
+    /// through its path, e.g. `/dev/fd/3` for `3>state.ndjson`.
+    pub fn open(path: &Path) -> io::Result<StateEmitter> {
+        Ok(StateEmitter {
+            out: BufWriter::new(File::create(path)?),
+            frame: 0,
+        })
+    }
+
+    /// Writes the record for the current frame and flushes it, so readers see
+    /// it as it happens.
+    pub fn emit(&mut self, chip8: &Chip8) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "{{\"frame\":{},{}}}",
+            self.frame,
+            state_fields(chip8)
+        )?;
+        self.out.flush()?;
+        self.frame += 1;
+        Ok(())
+    }
+}
+
+/// The machine state as a single JSON object.
+pub fn state_json(chip8: &Chip8) -> String {
+    format!("{{{}}}", state_fields(chip8))
+}
+
+/// How many bytes either side of PC go into `report_json`.
+const REPORT_CONTEXT: usize = 16;
+
+/// The machine state and the bytes around PC, in hex, as a single JSON
+/// object, to ask for help with.
+pub fn report_json(chip8: &Chip8) -> String {
+    let pc = chip8.pc as usize;
+    let from = pc.saturating_sub(REPORT_CONTEXT);
+    let to = (pc + REPORT_CONTEXT).min(chip8.memory.len());
+    let bytes: String = (chip8.memory.get(from..to).unwrap_or_default().iter())
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    format!(
+        "{{{},\"memory_from\":{},\"memory\":\"{}\"}}",
+        state_fields(chip8),
+        from,
+        bytes
+    )
+}
+
+fn state_fields(chip8: &Chip8) -> String {
+    let join = |values: &mut dyn Iterator<Item = String>| values.collect::<Vec<_>>().join(",");
+    format!(
+        "\"pc\":{},\"i\":{},\"v\":[{}],\"stack\":[{}],\"delay\":{},\"sound\":{},\"display_hash\":\"{:016x}\",\"keys\":[{}]",
+        chip8.pc,
+        chip8.i,
+        join(&mut chip8.v.iter().map(u8::to_string)),
+        join(&mut chip8.stack.iter().map(u16::to_string)),
+        chip8.delay,
+        chip8.sound,
+        chip8.display_hash(),
+        join(&mut (0..16).filter(|k| chip8.keys[*k]).map(|k| k.to_string())),
+    )
+}
@@ -0,0 +1,172 @@
+use crate::{
+    chip8::{Chip8, Opcode, ADDR_FONT},
+    syntax::Syntax,
+};
+
+/// The instruction at PC and what it's about to do with the registers as
+/// they are, e.g. "DRW V2 V3 5 — draw a 5 byte sprite from I=21E at (12, 20),
+/// collision → VF".
+pub fn explain(chip8: &Chip8, syntax: Syntax) -> String {
+    let pc = chip8.pc as usize;
+    if pc + 2 >= chip8.memory.len() {
+        return format!("PC {:03X} is past the end of memory", pc);
+    }
+    let op = Opcode::from_slice(&chip8.memory[pc..]);
+    format!("{} — {}", syntax.format(&op), effect(&op, chip8))
+}
+
+fn effect(op: &Opcode, chip8: &Chip8) -> String {
+    let (x, y, n) = (op.n1 as usize, op.n2 as usize, op.n3);
+    let (vx, vy) = (chip8.v[x], chip8.v[y]);
+    let (nnn, kk) = (op.a, op.v);
+    let skip = |taken: bool| if taken { "skips" } else { "doesn't skip" };
+    let shifted = if chip8.quirks.shift_vx { vx } else { vy };
+    match (op.n0, op.n1, op.n2, op.n3) {
+        (0x0, 0x0, 0xE, 0x0) => "clear the screen".into(),
+        (0x0, 0x0, 0xE, 0xE) => match chip8.stack.last() {
+            Some(addr) => format!("return to {:03X}", addr),
+            None => "return, with nothing on the stack".into(),
+        },
+        (0x1, ..) => format!("jump to {:03X}", nnn),
+        (0x2, ..) => format!(
+            "call {:03X}, returning to {:03X} (depth {})",
+            nnn,
+            chip8.pc + 2,
+            chip8.stack.len() + 1
+        ),
+        (0x3, ..) => format!("V{:X}={:02X} == {:02X}? {}", x, vx, kk, skip(vx == kk)),
+        (0x4, ..) => format!("V{:X}={:02X} != {:02X}? {}", x, vx, kk, skip(vx != kk)),
+        (0x5, _, _, 0x0) => format!(
+            "V{:X}={:02X} == V{:X}={:02X}? {}",
+            x,
+            vx,
+            y,
+            vy,
+            skip(vx == vy)
+        ),
+        (0x6, ..) => format!("V{:X} = {:02X}", x, kk),
+        (0x7, ..) => format!(
+            "V{:X} = {:02X} + {:02X} = {:02X}, VF untouched",
+            x,
+            vx,
+            kk,
+            vx.wrapping_add(kk)
+        ),
+        (0x8, _, _, 0x0) => format!("V{:X} = V{:X} = {:02X}", x, y, vy),
+        (0x8, _, _, 0x1) => format!("V{:X} = {:02X} | {:02X} = {:02X}", x, vx, vy, vx | vy),
+        (0x8, _, _, 0x2) => format!("V{:X} = {:02X} & {:02X} = {:02X}", x, vx, vy, vx & vy),
+        (0x8, _, _, 0x3) => format!("V{:X} = {:02X} ^ {:02X} = {:02X}", x, vx, vy, vx ^ vy),
+        (0x8, _, _, 0x4) => {
+            let (sum, carry) = vx.overflowing_add(vy);
+            format!(
+                "V{:X} = {:02X} + {:02X} = {:02X}, carry {} → VF",
+                x, vx, vy, sum, carry as u8
+            )
+        }
+        (0x8, _, _, 0x5) => {
+            let (difference, borrow) = vx.overflowing_sub(vy);
+            format!(
+                "V{:X} = {:02X} - {:02X} = {:02X}, no borrow {} → VF",
+                x, vx, vy, difference, !borrow as u8
+            )
+        }
+        (0x8, _, _, 0x6) => format!(
+            "V{:X} = {:02X} >> 1 = {:02X}, bit shifted out {} → VF",
+            x,
+            shifted,
+            shifted >> 1,
+            shifted & 1
+        ),
+        (0x8, _, _, 0x7) => {
+            let (difference, borrow) = vy.overflowing_sub(vx);
+            format!(
+                "V{:X} = {:02X} - {:02X} = {:02X}, no borrow {} → VF",
+                x, vy, vx, difference, !borrow as u8
+            )
+        }
+        (0x8, _, _, 0xE) => format!(
+            "V{:X} = {:02X} << 1 = {:02X}, bit shifted out {} → VF",
+            x,
+            shifted,
+            shifted << 1,
+            shifted >> 7
+        ),
+        (0x9, _, _, 0x0) => format!(
+            "V{:X}={:02X} != V{:X}={:02X}? {}",
+            x,
+            vx,
+            y,
+            vy,
+            skip(vx != vy)
+        ),
+        (0xA, ..) => format!("I = {:03X}", nnn),
+        (0xB, ..) if chip8.quirks.jump_vx => format!(
+            "jump to {:03X} + V{:X}={:02X} = {:03X}",
+            nnn,
+            x,
+            vx,
+            nnn + vx as u16
+        ),
+        (0xB, ..) => format!(
+            "jump to {:03X} + V0={:02X} = {:03X}",
+            nnn,
+            chip8.v[0],
+            nnn + chip8.v[0] as u16
+        ),
+        (0xC, ..) => format!("V{:X} = a random byte & {:02X}", x, kk),
+        (0xD, ..) => format!(
+            "draw a {} byte sprite from I={:03X} at ({}, {}), collision → VF",
+            n,
+            chip8.i,
+            vx % 64,
+            vy % 32
+        ),
+        (0xE, _, 0x9, 0xE) => format!(
+            "key {:X} pressed? {}",
+            vx & 0xF,
+            skip(chip8.keys[vx as usize & 0xF])
+        ),
+        (0xE, _, 0xA, 0x1) => format!(
+            "key {:X} not pressed? {}",
+            vx & 0xF,
+            skip(!chip8.keys[vx as usize & 0xF])
+        ),
+        (0xF, _, 0x0, 0x7) => format!("V{:X} = delay timer = {:02X}", x, chip8.delay),
+        (0xF, _, 0x0, 0xA) => format!("wait for a key and put it in V{:X}", x),
+        (0xF, _, 0x1, 0x5) => format!("delay timer = V{:X} = {:02X}", x, vx),
+        (0xF, _, 0x1, 0x8) => format!("sound timer = V{:X} = {:02X}", x, vx),
+        (0xF, _, 0x1, 0xE) => format!(
+            "I = {:03X} + {:02X} = {:03X}",
+            chip8.i,
+            vx,
+            chip8.i.wrapping_add(vx as u16)
+        ),
+        (0xF, _, 0x2, 0x9) => format!(
+            "I = the font sprite for {:X}, at {:03X}",
+            vx & 0xF,
+            ADDR_FONT + 5 * (vx & 0xF) as u16
+        ),
+        (0xF, _, 0x3, 0x3) => format!(
+            "write {} {} {}, the digits of V{:X}={}, to {:03X}",
+            vx / 100,
+            vx / 10 % 10,
+            vx % 10,
+            x,
+            vx,
+            chip8.i
+        ),
+        (0xF, _, 0x5, 0x5) => format!(
+            "store V0..V{:X} at {:03X}..{:03X}",
+            x,
+            chip8.i,
+            chip8.i as usize + x
+        ),
+        (0xF, _, 0x6, 0x5) => format!(
+            "load V0..V{:X} from {:03X}..{:03X}",
+            x,
+            chip8.i,
+            chip8.i as usize + x
+        ),
+        _ => "not an instruction this interpreter knows".into(),
+    }
+}
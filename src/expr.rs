@@ -0,0 +1,289 @@
+use crate::chip8::Chip8;
+use serde::{Deserialize, Serialize};
+use std::{fmt, result::Result};
+
+/// Operators by how tightly they bind, loosest first, as in C.
+const LEVELS: [&[&str]; 10] = [
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+/// Longer symbols first, so `<<` isn't read as two `<`.
+const SYMBOLS: [&str; 24] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/",
+    "%", "!", "~", "(", ")", "[", "]",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Symbol(&'static str),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Register {
+    I,
+    Pc,
+    Delay,
+    Sound,
+    /// How deep the stack is.
+    Sp,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    V(Box<Node>),
+    Mem(Box<Node>),
+    Unary(&'static str, Box<Node>),
+    Binary(&'static str, Box<Node>, Box<Node>),
+}
+
+/// An expression over the registers and memory, such as
+/// `V[3] * 2 + mem[I+1]`, kept with the text it was written as.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expr {
+    source: String,
+    node: Node,
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+        };
+        let node = parser.binary(0)?;
+        if let Some(token) = parser.tokens.get(parser.next) {
+            return Err(format!("unexpected {} in expression", describe(token)));
+        }
+        Ok(Expr {
+            source: source.trim().into(),
+            node,
+        })
+    }
+
+    pub fn eval(&self, chip8: &Chip8) -> Result<i64, String> {
+        eval(&self.node, chip8)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl TryFrom<String> for Expr {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Expr, String> {
+        Expr::parse(&source)
+    }
+}
+
+impl From<Expr> for String {
+    fn from(expr: Expr) -> String {
+        expr.source
+    }
+}
+
+/// A value in hex, decimal and binary.
+pub fn format(value: i64) -> String {
+    format!("{:#X}  {}  {:#b}", value, value, value)
+}
+
+/// Numbers are decimal, or hex and binary with `0x` and `0b`.
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let number = match (word.get(..2), word.get(2..)) {
+                (Some("0x" | "0X"), Some(hex)) => Some(i64::from_str_radix(hex, 16)),
+                (Some("0b" | "0B"), Some(bin)) => Some(i64::from_str_radix(bin, 2)),
+                _ if c.is_ascii_digit() => Some(word.parse()),
+                _ => None,
+            };
+            tokens.push(match number {
+                Some(Ok(value)) => Token::Number(value),
+                Some(Err(_)) => return Err(format!("invalid number '{}'", word)),
+                None => Token::Name(word.to_ascii_uppercase()),
+            });
+            rest = &rest[end..];
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or(format!("unexpected '{}' in expression", c))?;
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format!("number {}", value),
+        Token::Name(name) => format!("'{}'", name),
+        Token::Symbol(symbol) => format!("'{}'", symbol),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn take(&mut self) -> Result<Token, String> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token.ok_or("expression ends too soon".into())
+    }
+
+    /// Takes the next token if it's one of these symbols.
+    fn symbol(&mut self, symbols: &[&str]) -> Option<&'static str> {
+        match self.tokens.get(self.next) {
+            Some(Token::Symbol(symbol)) if symbols.contains(symbol) => {
+                self.next += 1;
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        match self.take()? {
+            Token::Symbol(found) if found == symbol => Ok(()),
+            token => Err(format!("expected '{}', found {}", symbol, describe(&token))),
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Node, String> {
+        let Some(operators) = LEVELS.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.symbol(operators) {
+            let right = self.binary(level + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.symbol(&["-", "!", "~"]) {
+            Some(op) => Ok(Node::Unary(op, Box::new(self.unary()?))),
+            None => self.operand(),
+        }
+    }
+
+    /// Inside brackets, as in `mem[I+1]`.
+    fn index(&mut self) -> Result<Box<Node>, String> {
+        self.expect("[")?;
+        let index = self.binary(0)?;
+        self.expect("]")?;
+        Ok(Box::new(index))
+    }
+
+    fn operand(&mut self) -> Result<Node, String> {
+        let name = match self.take()? {
+            Token::Number(value) => return Ok(Node::Number(value)),
+            Token::Symbol("(") => {
+                let inner = self.binary(0)?;
+                self.expect(")")?;
+                return Ok(inner);
+            }
+            Token::Symbol(symbol) => return Err(format!("unexpected '{}' in expression", symbol)),
+            Token::Name(name) => name,
+        };
+        let register = match name.as_str() {
+            "MEM" => return Ok(Node::Mem(self.index()?)),
+            "V" => return Ok(Node::V(self.index()?)),
+            "I" => Register::I,
+            "PC" => Register::Pc,
+            "DT" => Register::Delay,
+            "ST" => Register::Sound,
+            "SP" => Register::Sp,
+            name => {
+                let x = name
+                    .strip_prefix('V')
+                    .filter(|x| x.len() == 1)
+                    .and_then(|x| i64::from_str_radix(x, 16).ok())
+                    .ok_or(format!("unknown name '{}'", name))?;
+                return Ok(Node::V(Box::new(Node::Number(x))));
+            }
+        };
+        Ok(Node::Register(register))
+    }
+}
+
+fn eval(node: &Node, chip8: &Chip8) -> Result<i64, String> {
+    let value = match node {
+        Node::Number(value) => *value,
+        Node::Register(Register::I) => chip8.i as i64,
+        Node::Register(Register::Pc) => chip8.pc as i64,
+        Node::Register(Register::Delay) => chip8.delay as i64,
+        Node::Register(Register::Sound) => chip8.sound as i64,
+        Node::Register(Register::Sp) => chip8.stack.len() as i64,
+        Node::V(x) => match eval(x, chip8)? {
+            x @ 0..=0xF => chip8.v[x as usize] as i64,
+            x => return Err(format!("no register V[{}]", x)),
+        },
+        Node::Mem(addr) => {
+            let addr = eval(addr, chip8)?;
+            chip8.memory[addr.rem_euclid(chip8.memory.len() as i64) as usize] as i64
+        }
+        Node::Unary(op, operand) => {
+            let operand = eval(operand, chip8)?;
+            match *op {
+                "-" => operand.wrapping_neg(),
+                "!" => (operand == 0) as i64,
+                _ => !operand,
+            }
+        }
+        Node::Binary(op, left, right) => {
+            let (a, b) = (eval(left, chip8)?, eval(right, chip8)?);
+            match *op {
+                "/" | "%" if b == 0 => return Err("division by zero".into()),
+                "+" => a.wrapping_add(b),
+                "-" => a.wrapping_sub(b),
+                "*" => a.wrapping_mul(b),
+                "/" => a.wrapping_div(b),
+                "%" => a.wrapping_rem(b),
+                "<<" => a.wrapping_shl(b as u32),
+                ">>" => a.wrapping_shr(b as u32),
+                "&" => a & b,
+                "^" => a ^ b,
+                "|" => a | b,
+                "==" => (a == b) as i64,
+                "!=" => (a != b) as i64,
+                "<" => (a < b) as i64,
+                "<=" => (a <= b) as i64,
+                ">" => (a > b) as i64,
+                ">=" => (a >= b) as i64,
+                "&&" => (a != 0 && b != 0) as i64,
+                _ => (a != 0 || b != 0) as i64,
+            }
+        }
+    };
+    Ok(value)
+}
@@ -0,0 +1,125 @@
+use crate::storage::cache_dir;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListState, Paragraph},
+    DefaultTerminal,
+};
+use serde::Deserialize;
+use std::{collections::BTreeMap, error::Error, fs, io::Read, path::PathBuf, result::Result};
+
+/// The community archive, with an index of every program and one ROM file each.
+const ARCHIVE: &str = "https://raw.githubusercontent.com/JohnEarnest/chip8Archive/master";
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    platform: String,
+}
+
+/// Lets the user pick a program from the archive and returns the downloaded
+/// ROM, or `None` if they backed out.
+pub fn choose() -> Result<Option<PathBuf>, Box<dyn Error>> {
+    eprintln!("fetching the chip8Archive index...");
+    let json = ureq::get(&format!("{}/programs.json", ARCHIVE))
+        .call()?
+        .into_string()?;
+    let index: BTreeMap<String, Program> = serde_json::from_str(&json)?;
+    let mut programs: Vec<(String, Program)> = index.into_iter().collect();
+    programs.sort_by_cached_key(|(_, program)| program.title.to_lowercase());
+
+    let mut terminal = ratatui::init();
+    let chosen = browse(&mut terminal, &programs);
+    ratatui::restore();
+    match chosen? {
+        Some(key) => download(&key).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The list, narrowed by typing part of a title, author or platform.
+fn browse(
+    terminal: &mut DefaultTerminal,
+    programs: &[(String, Program)],
+) -> Result<Option<String>, Box<dyn Error>> {
+    let mut filter = String::new();
+    let mut state = ListState::default().with_selected(Some(0));
+    loop {
+        let needle = filter.to_lowercase();
+        let shown: Vec<&(String, Program)> = programs
+            .iter()
+            .filter(|(_, program)| {
+                program.title.to_lowercase().contains(&needle)
+                    || program.platform.to_lowercase().contains(&needle)
+                    || (program.authors.iter()).any(|a| a.to_lowercase().contains(&needle))
+            })
+            .collect();
+        terminal.draw(|frame| {
+            let [search, list] =
+                Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(frame.area());
+            frame.render_widget(
+                Paragraph::new(format!("{}▏", filter)).block(
+                    Block::bordered()
+                        .title(" filter by title, author or platform (Enter runs, Esc quits) "),
+                ),
+                search,
+            );
+            let items = shown.iter().map(|(_, program)| {
+                Line::from(vec![
+                    Span::raw(format!("{:<40} ", program.title)),
+                    format!("{:<8} ", program.platform).cyan(),
+                    program.authors.join(", ").dark_gray(),
+                ])
+            });
+            let list_widget = List::new(items)
+                .block(Block::bordered().title(format!(" {} programs ", shown.len())))
+                .highlight_style(Style::new().reversed());
+            frame.render_stateful_widget(list_widget, list, &mut state);
+        })?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let selected = (state.selected().unwrap_or(0)).min(shown.len().saturating_sub(1));
+        match key.code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Enter => return Ok(shown.get(selected).map(|(key, _)| key.clone())),
+            KeyCode::Up => state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => state.select(Some(selected + 1)),
+            KeyCode::PageUp => state.select(Some(selected.saturating_sub(20))),
+            KeyCode::PageDown => state.select(Some(selected + 20)),
+            KeyCode::Backspace => {
+                filter.pop();
+                state.select(Some(0));
+            }
+            KeyCode::Char(c) => {
+                filter.push(c);
+                state.select(Some(0));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Fetches a program's ROM into the cache, unless it's already there.
+fn download(key: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let path = cache_dir().join("gallery").join(format!("{}.ch8", key));
+    if !path.exists() {
+        let mut rom = Vec::new();
+        ureq::get(&format!("{}/roms/{}.ch8", ARCHIVE, key))
+            .call()?
+            .into_reader()
+            .read_to_end(&mut rom)?;
+        fs::create_dir_all(path.parent().ok_or("no cache directory")?)?;
+        fs::write(&path, rom)?;
+    }
+    Ok(path)
+}
@@ -0,0 +1,54 @@
+use crate::chip8::Chip8;
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+/// Draws the whole display onto `target` with its top left at `origin`, each
+/// CHIP-8 pixel as a `scale` by `scale` square: scale 2 fills a 128x64
+/// SSD1306, 3 most of a 320x240 ILI9341.
+pub fn draw<D: DrawTarget>(
+    chip8: &Chip8,
+    target: &mut D,
+    origin: Point,
+    scale: u32,
+    on: D::Color,
+    off: D::Color,
+) -> Result<(), D::Error> {
+    draw_rows(chip8, target, origin, scale, on, off, u32::MAX)
+}
+
+/// Draws only the rows set in `rows`, as returned by `Chip8::take_dirty_rows`,
+/// for displays slow enough that sending the rest matters.
+pub fn draw_rows<D: DrawTarget>(
+    chip8: &Chip8,
+    target: &mut D,
+    origin: Point,
+    scale: u32,
+    on: D::Color,
+    off: D::Color,
+    rows: u32,
+) -> Result<(), D::Error> {
+    for (y, row) in chip8.display.iter().enumerate() {
+        if rows & 1 << y == 0 {
+            continue;
+        }
+        let area = Rectangle::new(
+            origin + Point::new(0, (y as u32 * scale) as i32),
+            Size::new(64 * scale, scale),
+        );
+        // Row major over the scaled row: each pixel repeated `scale` times
+        // across, the whole line repeated `scale` times down
+        let line = (0..64 * scale).map(|x| {
+            let bit = 63 - x / scale;
+            if row >> bit & 1 == 1 {
+                on
+            } else {
+                off
+            }
+        });
+        target.fill_contiguous(&area, (0..scale).flat_map(|_| line.clone()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use crate::{
+    chip8::{Chip8, ADDR_PROGRAM_END},
+    peripheral::{self, Spec},
+    read_rom, INSTRUCTIONS_PER_FRAME,
+};
+use std::{
+    error::Error,
+    io::{stdout, BufWriter, Write},
+    path::Path,
+    result::Result,
+};
+
+const USAGE: &str =
+    "usage: fe_o8 hashes FILE [--frames N] [--seed N] [--peripheral NAME[@ADDR]] [--audit]";
+
+/// Runs a ROM headless and prints the display hash after every frame, one
+/// per line, so runs can be diffed across versions without storing frames.
+/// With `--audit` it instead runs the ROM twice and checks that the whole
+/// machine is the same after every frame both times, which it can only be
+/// if nothing but the seed and the pinned peripherals decide what happens.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let mut path = None;
+    let mut frames: u64 = 600;
+    let mut seed = None;
+    let mut peripherals = vec![];
+    let mut audit = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => frames = args.next().ok_or(USAGE)?.parse()?,
+            "--seed" => seed = Some(args.next().ok_or(USAGE)?.parse()?),
+            "--peripheral" => peripherals.push(Spec::parse(&args.next().ok_or(USAGE)?)?),
+            "--audit" => audit = true,
+            _ => path = Some(arg),
+        }
+    }
+    let rom = read_rom(Path::new(&path.ok_or(USAGE)?), ADDR_PROGRAM_END as usize)?;
+
+    if audit {
+        let seed = seed.unwrap_or(0);
+        let mut runs = vec![];
+        for _ in 0..2 {
+            let mut chip8 = machine(&rom, &peripherals, Some(seed))?;
+            let mut states = vec![];
+            for _ in 0..frames {
+                chip8.run_frame(INSTRUCTIONS_PER_FRAME)?;
+                states.push(chip8.state_hash());
+            }
+            runs.push(states);
+        }
+        return match (runs[0].iter().zip(&runs[1])).position(|(a, b)| a != b) {
+            Some(frame) => Err(format!(
+                "not deterministic: the runs differ after frame {} ({:016x} and {:016x})",
+                frame, runs[0][frame], runs[1][frame]
+            )
+            .into()),
+            None => {
+                println!("deterministic: both runs the same for {} frames", frames);
+                Ok(())
+            }
+        };
+    }
+
+    let mut chip8 = machine(&rom, &peripherals, seed)?;
+    let mut out = BufWriter::new(stdout().lock());
+    for frame in 0..frames {
+        chip8.run_frame(INSTRUCTIONS_PER_FRAME)?;
+        writeln!(out, "{} {:016x}", frame, chip8.display_hash())?;
+    }
+    Ok(())
+}
+
+/// A machine with `rom` loaded and its peripherals pinned, so runs only
+/// differ by what RND returns, and not that either if it's seeded.
+fn machine(rom: &[u8], peripherals: &[Spec], seed: Option<u64>) -> Result<Chip8, String> {
+    let mut chip8 = Chip8::new();
+    if let Some(seed) = seed {
+        chip8.seed(seed);
+    }
+    chip8.load(rom);
+    for spec in peripherals {
+        let mut peripheral = peripheral::create(spec)?;
+        peripheral.pin();
+        chip8.attach(peripheral);
+    }
+    Ok(chip8)
+}
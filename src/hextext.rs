@@ -0,0 +1,46 @@
+/// Whether a ROM file is hex written out as text rather than the bytes
+/// themselves: no control characters but line breaks and tabs, which any real
+/// program has plenty of.
+pub fn is_text(file: &[u8]) -> bool {
+    std::str::from_utf8(file).is_ok_and(|text| {
+        text.chars()
+            .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+    })
+}
+
+/// The bytes of a program listed as hex, as tutorials and forum posts do:
+/// `00E0 A22A 600C ...`, in any grouping, optionally with `0x` in front and
+/// commas between. Comments after `#`, `;` or `//` and addresses ending in
+/// `:` are skipped.
+pub fn parse(text: &str) -> Result<Vec<u8>, String> {
+    let mut rom = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let code = ["#", ";", "//"]
+            .iter()
+            .filter_map(|marker| line.find(marker))
+            .min()
+            .map_or(line, |comment| &line[..comment]);
+        for word in code.split(|c: char| c.is_whitespace() || c == ',') {
+            if word.is_empty() || word.ends_with(':') {
+                continue;
+            }
+            let digits = word
+                .strip_prefix("0x")
+                .or_else(|| word.strip_prefix("0X"))
+                .unwrap_or(word);
+            if digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "line {}: {} isn't whole bytes of hex",
+                    number + 1,
+                    word
+                ));
+            }
+            for pair in digits.as_bytes().chunks(2) {
+                // Checked as hex digits above
+                let pair = std::str::from_utf8(pair).unwrap();
+                rom.push(u8::from_str_radix(pair, 16).unwrap());
+            }
+        }
+    }
+    Ok(rom)
+}
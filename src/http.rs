@@ -0,0 +1,189 @@
+use crate::control::{Command, Request};
+use std::{error::Error, result::Result, sync::mpsc::Sender, thread};
+use tiny_http::{Header, Method, ReadWrite, Response, Server, StatusCode};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+/// A touch keypad for phones, which holds keys down through `/keypad/socket`.
+const KEYPAD_PAGE: &str = include_str!("keypad.html");
+
+/// Frames a key stays down while it's touched, which is as good as for ever.
+const TOUCHED: u32 = u32::MAX;
+
+/// Serves a small remote-control API over HTTP:
+///
+/// - `GET /display.png[?scale=N]` the display as a PNG, scaled 8x by default
+/// - `GET /registers` registers, timers and keys as JSON
+/// - `POST /keys/K[?frames=N]` presses keypad key K (hex) for N frames
+/// - `POST /command` runs the control command line in the request body,
+///   if it's one that only plays or looks
+/// - `GET /keypad` a page with a keypad to play with from a phone
+/// - `GET /keypad/socket` a WebSocket taking `dK` and `uK` for key K going
+///   down and up
+pub fn serve(addr: &str, requests: Sender<Request>) -> Result<(), Box<dyn Error>> {
+    let server = Server::http(addr).map_err(|e| format!("can't serve HTTP on {}: {}", addr, e))?;
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            if request.url() == "/keypad/socket" {
+                upgrade(request, &requests);
+                continue;
+            }
+            let response = handle(&mut request, &requests)
+                .unwrap_or_else(|error| text(400, format!("error: {}\n", error)));
+            let _ = request.respond(response);
+        }
+    });
+    Ok(())
+}
+
+type HttpResponse = Response<std::io::Cursor<Vec<u8>>>;
+
+fn handle(
+    request: &mut tiny_http::Request,
+    requests: &Sender<Request>,
+) -> Result<HttpResponse, String> {
+    let url = request.url().to_string();
+    let (route, query) = url.split_once('?').unwrap_or((&url, ""));
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid {}", name))
+            })
+            .transpose()
+    };
+    match (request.method(), route) {
+        (Method::Get, "/display.png") => {
+            let screenshot = run(requests, Command::Screenshot)?;
+            let png = encode_png(&screenshot, param("scale")?.unwrap_or(8).clamp(1, 32))?;
+            Ok(Response::from_data(png).with_header(header("Content-Type", "image/png")))
+        }
+        (Method::Get, "/registers") => Ok(Response::from_data(run(requests, Command::Registers)?)
+            .with_header(header("Content-Type", "application/json"))),
+        (Method::Post, route) if route.starts_with("/keys/") => {
+            let key = usize::from_str_radix(&route["/keys/".len()..], 16)
+                .ok()
+                .filter(|key| *key <= 0xF)
+                .ok_or("no such key")?;
+            run(
+                requests,
+                Command::PressKey(key, param("frames")?.unwrap_or(5)),
+            )?;
+            Ok(text(200, "ok\n".into()))
+        }
+        (Method::Get, "/keypad") => Ok(Response::from_string(KEYPAD_PAGE)
+            .with_header(header("Content-Type", "text/html; charset=utf-8"))),
+        (Method::Post, "/command") => {
+            let mut line = String::new();
+            request
+                .as_reader()
+                .read_to_string(&mut line)
+                .map_err(|e| e.to_string())?;
+            let command = Command::parse(line.trim())?;
+            // The server takes requests from anyone who can reach it
+            if !command.remote() {
+                return Ok(text(403, "not allowed over HTTP\n".into()));
+            }
+            let output = run(requests, command)?;
+            Ok(text(200, format!("{}\n", output)))
+        }
+        _ => Ok(text(404, "not found\n".into())),
+    }
+}
+
+/// Takes a request over as a WebSocket for the keypad page, each on a
+/// thread of its own as it stays open.
+fn upgrade(request: tiny_http::Request, requests: &Sender<Request>) {
+    let key = (request.headers().iter())
+        .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+        .map(|header| derive_accept_key(header.value.as_bytes()));
+    let Some(accept) = key else {
+        let _ = request.respond(text(400, "expected a WebSocket\n".into()));
+        return;
+    };
+    let response =
+        Response::empty(StatusCode(101)).with_header(header("Sec-WebSocket-Accept", &accept));
+    let stream = request.upgrade("websocket", response);
+    let requests = requests.clone();
+    thread::spawn(move || keypad(stream, &requests));
+}
+
+/// Holds keys down from when they're touched on the keypad page until
+/// they're let go, or until the page goes away.
+fn keypad(stream: Box<dyn ReadWrite + Send>, requests: &Sender<Request>) {
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    let mut touched = [false; 16];
+    while let Ok(message) = socket.read() {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let (down, key) = match text.split_at_checked(1) {
+            Some(("d", key)) => (true, key),
+            Some(("u", key)) => (false, key),
+            _ => continue,
+        };
+        let Some(key) = usize::from_str_radix(key, 16)
+            .ok()
+            .filter(|key| *key <= 0xF)
+        else {
+            continue;
+        };
+        touched[key] = down;
+        // Letting go leaves the key down a frame more, so a tap quicker
+        // than a frame still reaches the game
+        let frames = if down { TOUCHED } else { 1 };
+        if run(requests, Command::PressKey(key, frames)).is_err() {
+            return;
+        }
+    }
+    for key in (0..16).filter(|key| touched[*key]) {
+        let _ = run(requests, Command::PressKey(key, 0));
+    }
+}
+
+/// Hands a command to the main loop and waits for its reply.
+fn run(requests: &Sender<Request>, command: Command) -> Result<String, String> {
+    let (request, answer) = Request::new(command);
+    requests.send(request).map_err(|_| "emulator has exited")?;
+    answer.recv().map_err(|_| "emulator has exited".to_string())
+}
+
+fn text(status: u16, body: String) -> HttpResponse {
+    Response::from_string(body).with_status_code(status)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+/// Converts a text screenshot (`#` lit, `.` unlit) into a grayscale PNG.
+fn encode_png(screenshot: &str, scale: u32) -> Result<Vec<u8>, String> {
+    let rows: Vec<&str> = screenshot.lines().collect();
+    let width = rows.first().map_or(0, |row| row.len()) as u32;
+    let height = rows.len() as u32;
+    let mut pixels = Vec::with_capacity((width * height * scale * scale) as usize);
+    for row in &rows {
+        let line: Vec<u8> = row
+            .bytes()
+            .flat_map(|c| {
+                let value = if c == b'#' { 0xFF } else { 0x00 };
+                std::iter::repeat_n(value, scale as usize)
+            })
+            .collect();
+        for _ in 0..scale {
+            pixels.extend_from_slice(&line);
+        }
+    }
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width * scale, height * scale);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
@@ -0,0 +1,475 @@
+use keyboard_query::DeviceState;
+
+// Linux kernel key codes, which is what keyboard_query reports
+pub const KEY_F1: u16 = 0x3B;
+pub const KEY_F2: u16 = 0x3C;
+pub const KEY_F3: u16 = 0x3D;
+pub const KEY_F4: u16 = 0x3E;
+pub const KEY_F5: u16 = 0x3F;
+pub const KEY_F6: u16 = 0x40;
+pub const KEY_F7: u16 = 0x41;
+pub const KEY_F8: u16 = 0x42;
+pub const KEY_F9: u16 = 0x43;
+pub const KEY_F10: u16 = 0x44;
+pub const KEY_F11: u16 = 0x57;
+pub const KEY_F12: u16 = 0x58;
+pub const KEY_ESC: u16 = 0x01;
+pub const KEY_MINUS: u16 = 0x0C;
+pub const KEY_EQUAL: u16 = 0x0D;
+pub const KEY_TAB: u16 = 0x0F;
+pub const KEY_BACKSPACE: u16 = 0x0E;
+pub const KEY_ENTER: u16 = 0x1C;
+pub const KEY_HOME: u16 = 0x66;
+pub const KEY_PAGEUP: u16 = 0x68;
+pub const KEY_INSERT: u16 = 0x6E;
+pub const KEY_DELETE: u16 = 0x6F;
+pub const KEY_SPACE: u16 = 0x39;
+pub const KEY_LEFT: u16 = 0x69;
+pub const KEY_RIGHT: u16 = 0x6A;
+pub const KEY_END: u16 = 0x6B;
+pub const KEY_PAGEDOWN: u16 = 0x6D;
+pub const KEY_LEFTSHIFT: u16 = 0x2A;
+pub const KEY_RIGHTSHIFT: u16 = 0x36;
+pub const KEY_PAUSE: u16 = 0x77;
+pub const KEY_PRINT: u16 = 0x63;
+pub const KEY_B: u16 = 0x30;
+pub const KEY_M: u16 = 0x32;
+pub const KEY_LEFTBRACE: u16 = 0x1A;
+pub const KEY_RIGHTBRACE: u16 = 0x1B;
+/// Never reported as held, for hotkeys that are turned off.
+pub const KEY_RESERVED: u16 = 0x00;
+
+/// Physical keys for CHIP-8 keys 0 through F, laid out as
+/// ```text
+/// 1 2 3 4      1 2 3 C
+/// q w e r  ->  4 5 6 D
+/// a s d f      7 8 9 E
+/// z x c v      A 0 B F
+/// ```
+pub const KEYPAD: [u16; 16] = [
+    0x2D, // x
+    0x02, // 1
+    0x03, // 2
+    0x04, // 3
+    0x10, // q
+    0x11, // w
+    0x12, // e
+    0x1E, // a
+    0x1F, // s
+    0x20, // d
+    0x2C, // z
+    0x2E, // c
+    0x05, // 4
+    0x13, // r
+    0x21, // f
+    0x2F, // v
+];
+
+/// What's printed on the keys in `KEYPAD`, for showing the mapping.
+pub const KEYPAD_LABELS: [char; 16] = [
+    'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+];
+
+/// The keyboard as of the last poll, remembering the poll before it so
+/// hotkeys can act once per press rather than every frame they are held.
+pub struct Keyboard {
+    device: DeviceState,
+    keymap: Vec<u16>,
+    last_keymap: Vec<u16>,
+    /// Physical keys for CHIP-8 keys 0 through F.
+    keypad: [u16; 16],
+}
+
+impl Keyboard {
+    pub fn new() -> Keyboard {
+        Keyboard {
+            device: DeviceState::new(),
+            keymap: vec![],
+            last_keymap: vec![],
+            keypad: KEYPAD,
+        }
+    }
+
+    /// Reads the keyboard, once per frame.
+    pub fn poll(&mut self) {
+        self.last_keymap = std::mem::replace(&mut self.keymap, self.device.query_keymap());
+    }
+
+    pub fn held(&self, key: u16) -> bool {
+        self.keymap.contains(&key)
+    }
+
+    /// Whether the key went down since the last poll.
+    pub fn pressed(&self, key: u16) -> bool {
+        self.held(key) && !self.last_keymap.contains(&key)
+    }
+
+    pub fn shift(&self) -> bool {
+        self.held(KEY_LEFTSHIFT) || self.held(KEY_RIGHTSHIFT)
+    }
+
+    /// The state of the CHIP-8 keypad.
+    pub fn keypad(&self) -> [bool; 16] {
+        self.keypad.map(|key| self.held(key))
+    }
+
+    /// Plays the keypad on other keys, given by what's printed on them, as
+    /// a ROM may ask, or on the usual ones given nothing.
+    pub fn set_keypad(&mut self, labels: Option<&[char; 16]>) -> Result<(), String> {
+        self.keypad = KEYPAD;
+        if let Some(labels) = labels {
+            for (key, label) in self.keypad.iter_mut().zip(labels) {
+                *key = key_code(&label.to_string())
+                    .ok_or(format!("no key {} to map the keypad to", label))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Names for the keys hotkeys can be bound to, as written on the command
+/// line, and those the keypad can be moved to.
+const KEY_NAMES: &[(&str, u16)] = &[
+    ("esc", KEY_ESC),
+    ("tab", KEY_TAB),
+    ("backspace", KEY_BACKSPACE),
+    ("enter", KEY_ENTER),
+    ("space", KEY_SPACE),
+    ("1", 0x02),
+    ("2", 0x03),
+    ("3", 0x04),
+    ("4", 0x05),
+    ("5", 0x06),
+    ("6", 0x07),
+    ("7", 0x08),
+    ("8", 0x09),
+    ("9", 0x0A),
+    ("0", 0x0B),
+    ("q", 0x10),
+    ("w", 0x11),
+    ("e", 0x12),
+    ("r", 0x13),
+    ("pause", KEY_PAUSE),
+    ("print", KEY_PRINT),
+    ("pageup", KEY_PAGEUP),
+    ("pagedown", KEY_PAGEDOWN),
+    ("home", KEY_HOME),
+    ("end", KEY_END),
+    ("up", 0x67),
+    ("down", 0x6C),
+    ("left", KEY_LEFT),
+    ("right", KEY_RIGHT),
+    ("insert", KEY_INSERT),
+    ("delete", KEY_DELETE),
+    ("f1", KEY_F1),
+    ("f2", KEY_F2),
+    ("f3", KEY_F3),
+    ("f4", KEY_F4),
+    ("f5", KEY_F5),
+    ("f6", KEY_F6),
+    ("f7", KEY_F7),
+    ("f8", KEY_F8),
+    ("f9", KEY_F9),
+    ("f10", KEY_F10),
+    ("f11", KEY_F11),
+    ("f12", KEY_F12),
+    ("1", 0x02),
+    ("2", 0x03),
+    ("3", 0x04),
+    ("4", 0x05),
+    ("5", 0x06),
+    ("6", 0x07),
+    ("7", 0x08),
+    ("8", 0x09),
+    ("9", 0x0A),
+    ("0", 0x0B),
+    ("-", KEY_MINUS),
+    ("=", KEY_EQUAL),
+    ("q", 0x10),
+    ("w", 0x11),
+    ("e", 0x12),
+    ("r", 0x13),
+    ("t", 0x14),
+    ("y", 0x15),
+    ("u", 0x16),
+    ("i", 0x17),
+    ("o", 0x18),
+    ("p", 0x19),
+    ("a", 0x1E),
+    ("s", 0x1F),
+    ("d", 0x20),
+    ("f", 0x21),
+    ("g", 0x22),
+    ("h", 0x23),
+    ("j", 0x24),
+    ("k", 0x25),
+    ("l", 0x26),
+    ("z", 0x2C),
+    ("x", 0x2D),
+    ("c", 0x2E),
+    ("v", 0x2F),
+    ("b", KEY_B),
+    ("n", 0x31),
+    ("m", KEY_M),
+    ("[", KEY_LEFTBRACE),
+    ("]", KEY_RIGHTBRACE),
+];
+
+/// What a key name on the command line stands for.
+fn key_code(name: &str) -> Option<u16> {
+    let name = name.to_ascii_lowercase();
+    KEY_NAMES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, code)| *code)
+}
+
+/// Keys to be held together, named and joined with `+` like `esc+f1+f12`.
+pub fn parse_chord(spec: &str) -> Result<Vec<u16>, String> {
+    spec.split('+')
+        .map(|name| key_code(name).ok_or_else(|| format!("unknown key {}", name)))
+        .collect()
+}
+
+/// The name of a key, for messages.
+pub fn key_name(code: u16) -> String {
+    KEY_NAMES
+        .iter()
+        .find(|(_, known)| *known == code)
+        .map_or_else(
+            || format!("key {:#04x}", code),
+            |(name, _)| name.to_string(),
+        )
+}
+
+/// Something the emulator does when a key is pressed, rather than the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    /// Load a save slot, or save it with shift held.
+    Slot1,
+    Slot2,
+    Slot3,
+    Slot4,
+    Status,
+    Keypad,
+    Registers,
+    Timers,
+    Memory,
+    Disassembly,
+    Log,
+    Counters,
+    /// The sound timer and a trace of the beeper over the game.
+    Sound,
+    Legend,
+    /// Swap lit and unlit pixels' colors.
+    Invert,
+    /// Copy the display, as text, to the clipboard.
+    Screenshot,
+    Workspace,
+    Fullscreen,
+    /// Run more or fewer instructions each frame.
+    Faster,
+    Slower,
+    Rewind,
+    /// While rewinding: scrub back and forward, resume from there, or go back.
+    ScrubBack,
+    ScrubForward,
+    Resume,
+    Cancel,
+    NextRom,
+    PreviousRom,
+    /// Start the ROM over, and the speedrun timer with it.
+    Reset,
+    /// Mark a split on the speedrun timer.
+    Split,
+    /// Play the recorded keypad macro, or record one with shift held.
+    Macro,
+    /// Make the beeper quieter or louder.
+    VolumeDown,
+    VolumeUp,
+}
+
+impl Action {
+    pub const ALL: [Action; 33] = [
+        Action::Quit,
+        Action::Slot1,
+        Action::Slot2,
+        Action::Slot3,
+        Action::Slot4,
+        Action::Status,
+        Action::Keypad,
+        Action::Registers,
+        Action::Timers,
+        Action::Memory,
+        Action::Disassembly,
+        Action::Log,
+        Action::Counters,
+        Action::Sound,
+        Action::Legend,
+        Action::Invert,
+        Action::Screenshot,
+        Action::Workspace,
+        Action::Fullscreen,
+        Action::Faster,
+        Action::Slower,
+        Action::Rewind,
+        Action::ScrubBack,
+        Action::ScrubForward,
+        Action::Resume,
+        Action::Cancel,
+        Action::NextRom,
+        Action::PreviousRom,
+        Action::Reset,
+        Action::Split,
+        Action::Macro,
+        Action::VolumeDown,
+        Action::VolumeUp,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Slot1 => "slot1",
+            Action::Slot2 => "slot2",
+            Action::Slot3 => "slot3",
+            Action::Slot4 => "slot4",
+            Action::Status => "status",
+            Action::Keypad => "keypad",
+            Action::Registers => "registers",
+            Action::Timers => "timers",
+            Action::Memory => "memory",
+            Action::Disassembly => "disassembly",
+            Action::Log => "log",
+            Action::Counters => "counters",
+            Action::Sound => "sound",
+            Action::Legend => "legend",
+            Action::Invert => "invert",
+            Action::Screenshot => "screenshot",
+            Action::Workspace => "workspace",
+            Action::Fullscreen => "fullscreen",
+            Action::Faster => "faster",
+            Action::Slower => "slower",
+            Action::Rewind => "rewind",
+            Action::ScrubBack => "scrub-back",
+            Action::ScrubForward => "scrub-forward",
+            Action::Resume => "resume",
+            Action::Cancel => "cancel",
+            Action::NextRom => "next-rom",
+            Action::PreviousRom => "previous-rom",
+            Action::Reset => "reset",
+            Action::Split => "split",
+            Action::Macro => "macro",
+            Action::VolumeDown => "volume-down",
+            Action::VolumeUp => "volume-up",
+        }
+    }
+
+    fn default_key(self) -> u16 {
+        match self {
+            Action::Quit => KEY_PAUSE,
+            Action::Slot1 => KEY_F1,
+            Action::Slot2 => KEY_F2,
+            Action::Slot3 => KEY_F3,
+            Action::Slot4 => KEY_F4,
+            Action::Status => KEY_HOME,
+            Action::Keypad => KEY_F5,
+            Action::Registers => KEY_F6,
+            Action::Timers => KEY_END,
+            Action::Memory => KEY_F7,
+            Action::Disassembly => KEY_F8,
+            Action::Log => KEY_F9,
+            Action::Counters => KEY_F12,
+            Action::Sound => KEY_B,
+            Action::Legend => KEY_TAB,
+            Action::Invert => KEY_INSERT,
+            Action::Screenshot => KEY_PRINT,
+            Action::Workspace => KEY_F10,
+            Action::Fullscreen => KEY_F11,
+            Action::Faster => KEY_EQUAL,
+            Action::Slower => KEY_MINUS,
+            Action::Rewind => KEY_BACKSPACE,
+            Action::ScrubBack => KEY_LEFT,
+            Action::ScrubForward => KEY_RIGHT,
+            Action::Resume => KEY_ENTER,
+            Action::Cancel => KEY_ESC,
+            Action::NextRom => KEY_PAGEDOWN,
+            Action::PreviousRom => KEY_PAGEUP,
+            Action::Reset => KEY_DELETE,
+            Action::Split => KEY_SPACE,
+            Action::Macro => KEY_M,
+            Action::VolumeDown => KEY_LEFTBRACE,
+            Action::VolumeUp => KEY_RIGHTBRACE,
+        }
+    }
+}
+
+/// Which key does what, the defaults unless rebound with `--bind`.
+#[derive(Clone)]
+pub struct Hotkeys {
+    keys: [u16; Action::ALL.len()],
+}
+
+impl Default for Hotkeys {
+    fn default() -> Hotkeys {
+        Hotkeys {
+            keys: Action::ALL.map(Action::default_key),
+        }
+    }
+}
+
+impl Hotkeys {
+    /// None of the emulator's own keys, for when only the game should see
+    /// the keyboard.
+    pub fn none() -> Hotkeys {
+        Hotkeys {
+            keys: [KEY_RESERVED; Action::ALL.len()],
+        }
+    }
+
+    pub fn key(&self, action: Action) -> u16 {
+        self.keys[action as usize]
+    }
+
+    /// Rebinds from `ACTION=KEY,...`, refusing keys the game's keypad needs
+    /// and keys another action already has.
+    pub fn bind(&mut self, spec: &str) -> Result<(), String> {
+        for binding in spec.split(',') {
+            let (name, key) = binding
+                .split_once('=')
+                .ok_or_else(|| format!("expected ACTION=KEY, got {}", binding))?;
+            let action = Action::ALL
+                .into_iter()
+                .find(|action| action.name() == name)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = Action::ALL.map(Action::name).to_vec();
+                    format!(
+                        "unknown action {}, expected one of {}",
+                        name,
+                        names.join(", ")
+                    )
+                })?;
+            let code = key_code(key).ok_or_else(|| format!("unknown key {}", key))?;
+            if let Some(pad) = KEYPAD.iter().position(|&pad| pad == code) {
+                return Err(format!(
+                    "{} is keypad key {:X} and can't also be {}",
+                    key, pad, name
+                ));
+            }
+            self.keys[action as usize] = code;
+        }
+        for (n, a) in Action::ALL.into_iter().enumerate() {
+            for b in Action::ALL.into_iter().skip(n + 1) {
+                if self.key(a) == self.key(b) {
+                    return Err(format!(
+                        "{} and {} are both bound to {}",
+                        a.name(),
+                        b.name(),
+                        key_name(self.key(a))
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
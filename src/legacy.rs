@@ -0,0 +1,212 @@
+use crossterm::{
+    cursor::MoveTo,
+    style::{Print, SetBackgroundColor, SetForegroundColor},
+    QueueableCommand,
+};
+use ratatui::{
+    buffer::Cell,
+    style::{Color, Modifier},
+};
+use std::io::{self, Write};
+
+/// The sixteen colors an older console has, as ratatui names them in
+/// order, with the RGB the console shows them as.
+const COLORS: [(Color, [u8; 3]); 16] = [
+    (Color::Black, [0x00, 0x00, 0x00]),
+    (Color::Red, [0x80, 0x00, 0x00]),
+    (Color::Green, [0x00, 0x80, 0x00]),
+    (Color::Yellow, [0x80, 0x80, 0x00]),
+    (Color::Blue, [0x00, 0x00, 0x80]),
+    (Color::Magenta, [0x80, 0x00, 0x80]),
+    (Color::Cyan, [0x00, 0x80, 0x80]),
+    (Color::Gray, [0xC0, 0xC0, 0xC0]),
+    (Color::DarkGray, [0x80, 0x80, 0x80]),
+    (Color::LightRed, [0xFF, 0x00, 0x00]),
+    (Color::LightGreen, [0x00, 0xFF, 0x00]),
+    (Color::LightYellow, [0xFF, 0xFF, 0x00]),
+    (Color::LightBlue, [0x00, 0x00, 0xFF]),
+    (Color::LightMagenta, [0xFF, 0x00, 0xFF]),
+    (Color::LightCyan, [0x00, 0xFF, 0xFF]),
+    (Color::White, [0xFF, 0xFF, 0xFF]),
+];
+
+/// The original PC's code page, whose line drawing and blocks older
+/// consoles' raster fonts have.
+const CP437: u32 = 437;
+
+/// An older Windows console: conhost before Windows 10, or with escape
+/// sequences turned off. It's written to through crossterm's commands,
+/// which fall back to the console API there, in its sixteen colors and
+/// with the characters of its code page.
+#[derive(Clone, Copy)]
+pub struct Legacy {
+    /// The code page is 437, so line drawing and blocks show. Under any
+    /// other, everything is written in ASCII.
+    cp437: bool,
+}
+
+impl Legacy {
+    /// The console if it's an older one, or as if it were if asked for,
+    /// for seeing how the emulator looks on one.
+    pub fn detect(forced: bool) -> Option<Legacy> {
+        match code_page() {
+            Some(code_page) => Some(Legacy {
+                cp437: code_page == CP437,
+            }),
+            None if forced => Some(Legacy { cp437: true }),
+            None => None,
+        }
+    }
+
+    /// Writes the cells out, each at its position.
+    pub fn write<'c>(
+        self,
+        out: &mut impl Write,
+        cells: impl Iterator<Item = (u16, u16, &'c Cell)>,
+    ) -> io::Result<()> {
+        let (mut fg, mut bg) = (None, None);
+        let mut last = None;
+        for (x, y, cell) in cells {
+            if !matches!(last, Some((last_x, last_y)) if x == last_x + 1 && y == last_y) {
+                out.queue(MoveTo(x, y))?;
+            }
+            last = Some((x, y));
+            let (symbol, colors) = self.glyph(cell);
+            if fg != Some(colors.0) {
+                out.queue(SetForegroundColor(colors.0.into()))?;
+                fg = Some(colors.0);
+            }
+            if bg != Some(colors.1) {
+                out.queue(SetBackgroundColor(colors.1.into()))?;
+                bg = Some(colors.1);
+            }
+            out.queue(Print(symbol))?;
+        }
+        if last.is_some() {
+            out.queue(SetForegroundColor(Color::Reset.into()))?
+                .queue(SetBackgroundColor(Color::Reset.into()))?;
+        }
+        out.flush()
+    }
+
+    /// What the console shows for the cell: a character it has and two
+    /// of its colors. With no text attributes, reversed swaps the colors
+    /// and bold brightens the text.
+    fn glyph(self, cell: &Cell) -> (&str, (Color, Color)) {
+        let (mut fg, mut bg) = (nearest(cell.fg), nearest(cell.bg));
+        if cell.modifier.contains(Modifier::BOLD) {
+            fg = bright(fg);
+        }
+        if cell.modifier.contains(Modifier::REVERSED) {
+            let default = |color, or| if color == Color::Reset { or } else { color };
+            (fg, bg) = (default(bg, Color::Black), default(fg, Color::Gray));
+        }
+        let symbol = cell.symbol();
+        let Some(c) = symbol.chars().next().filter(|c| !c.is_ascii()) else {
+            return (ascii(symbol), (fg, bg));
+        };
+        match (self.cp437, c) {
+            // A solid block is a space in the block's color, which needs no font
+            (false, '█' | '▉' | '▊' | '▋') => (" ", (fg, fg)),
+            (true, _) => (cp437(symbol, c), (fg, bg)),
+            (false, _) => (fallback(c), (fg, bg)),
+        }
+    }
+}
+
+/// The console's output code page if it's an older one.
+#[cfg(windows)]
+fn code_page() -> Option<u32> {
+    if crossterm::ansi_support::supports_ansi() {
+        return None;
+    }
+    // SAFETY: takes nothing and only reads the console's state
+    Some(unsafe { winapi::um::consoleapi::GetConsoleOutputCP() })
+}
+
+#[cfg(not(windows))]
+fn code_page() -> Option<u32> {
+    None
+}
+
+/// ASCII text as it is, and anything else as a space.
+fn ascii(symbol: &str) -> &str {
+    match symbol.is_ascii() {
+        true => symbol,
+        false => " ",
+    }
+}
+
+/// The character in code page 437 that's closest.
+fn cp437(symbol: &str, c: char) -> &str {
+    match c {
+        '━' | '┄' => "═",
+        '╋' => "╬",
+        '▁' | '▂' => "_",
+        '▃' | '▅' => "▄",
+        '▆' | '▇' | '▉' | '▊' | '▋' => "█",
+        '▏' | '▎' | '▍' => "▌",
+        '▸' | '→' => "»",
+        '←' => "«",
+        '●' => "■",
+        // The console puts the rest of its line drawing and blocks into the code page itself
+        '─' | '│' | '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' | '═' | '╫' | '░' | '▒'
+        | '▓' | '█' | '▀' | '▄' | '▌' | '·' => symbol,
+        _ => fallback(c),
+    }
+}
+
+/// The ASCII character that's closest.
+fn fallback(c: char) -> &'static str {
+    match c {
+        '─' | '━' | '═' | '┄' | '—' => "-",
+        '│' | '▏' | '▎' | '▍' | '▌' => "|",
+        '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' | '╋' | '╫' => {
+            "+"
+        }
+        '█' | '▉' | '▊' | '▋' | '▓' => "#",
+        '▒' => ":",
+        '▀' => "\"",
+        '▁' | '▂' | '▃' | '▄' | '▅' | '▆' | '▇' => "_",
+        '·' => ".",
+        '●' | '♪' => "*",
+        '▸' | '→' => ">",
+        '←' => "<",
+        '↑' => "^",
+        '↓' => "v",
+        _ => " ",
+    }
+}
+
+/// The console color that's closest.
+fn nearest(color: Color) -> Color {
+    let rgb = match color {
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::Indexed(index @ 0..=15) => return COLORS[index as usize].0,
+        Color::Indexed(index @ 16..=231) => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + 40 * n };
+            let index = index - 16;
+            [level(index / 36), level(index / 6 % 6), level(index % 6)]
+        }
+        Color::Indexed(index) => [8 + 10 * (index - 232); 3],
+        named => return named,
+    };
+    let distance = |[r, g, b]: [u8; 3]| {
+        [(r, rgb[0]), (g, rgb[1]), (b, rgb[2])]
+            .iter()
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+            .sum::<i32>()
+    };
+    let closest = COLORS.iter().min_by_key(|(_, rgb)| distance(*rgb));
+    closest.map_or(Color::Reset, |(color, _)| *color)
+}
+
+/// The bright version of a dark color, as bold text shows in.
+fn bright(color: Color) -> Color {
+    match COLORS.iter().position(|(named, _)| *named == color) {
+        Some(index @ 1..=6) => COLORS[index + 8].0,
+        Some(0) => Color::DarkGray,
+        Some(7) => Color::White,
+        _ => color,
+    }
+}
@@ -0,0 +1,17 @@
+//! The interpreter core: the machine, its quirks and the peripherals that can
+//! be mapped into it. It needs only `core` and `alloc`, so without the default
+//! `std` feature it builds for microcontrollers; the terminal frontend in
+//! `main.rs` is what needs an operating system. With the `embedded-graphics`
+//! feature, `graphics` draws the display onto anything embedded-graphics can.
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod analysis;
+pub mod chip8;
+#[cfg(feature = "embedded-graphics")]
+pub mod graphics;
+pub mod peripheral;
+pub mod quirks;
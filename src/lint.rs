@@ -0,0 +1,58 @@
+use crate::{
+    analysis::reachable,
+    chip8::{Opcode, ADDR_START_PROGRAM},
+    load_rom,
+    options::{parse_address, parse_syntax},
+    quirks::{dependence, Quirks},
+    syntax::Syntax,
+};
+use std::{error::Error, path::Path, result::Result};
+
+const USAGE: &str =
+    "usage: fe_o8 lint [--syntax fe|chipper|octo] [--load-addr ADDR] [--entry ADDR] FILE";
+
+/// Lists the reachable instructions that behave differently from one quirk
+/// profile to another, so a ROM's author knows where it isn't portable.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let mut syntax = Syntax::default();
+    let (mut start, mut entry) = (ADDR_START_PROGRAM, None);
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--syntax" => syntax = parse_syntax(&args.next().ok_or(USAGE)?)?,
+            "--load-addr" => start = parse_address(&args.next().ok_or(USAGE)?)?,
+            "--entry" => entry = Some(parse_address(&args.next().ok_or(USAGE)?)?),
+            _ if arg.starts_with("--") => return Err(USAGE.into()),
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.ok_or(USAGE)?;
+    let (chip8, _) = load_rom(Path::new(&path), start, entry)?;
+
+    let mut counts = [0; Quirks::NAMES.len()];
+    for addr in reachable(&chip8.memory, chip8.entry) {
+        let op = Opcode::from_slice(&chip8.memory[addr as usize..]);
+        if let Some((quirk, how)) = dependence(&op) {
+            println!(
+                "{:03X}  {:<14} {:<12}  {}",
+                addr,
+                syntax.format(&op),
+                quirk,
+                how
+            );
+            counts[Quirks::NAMES
+                .iter()
+                .position(|&name| name == quirk)
+                .unwrap()] += 1;
+        }
+    }
+    let found: Vec<String> = (Quirks::NAMES.iter().zip(counts))
+        .filter(|(_, count)| *count > 0)
+        .map(|(name, count)| format!("{} {}", count, name))
+        .collect();
+    match found.is_empty() {
+        true => println!("{}: runs the same under every profile", path),
+        false => println!("{}: depends on {}", path, found.join(", ")),
+    }
+    Ok(())
+}
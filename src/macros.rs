@@ -0,0 +1,88 @@
+use crate::storage::data_dir;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::PathBuf, result::Result};
+
+/// Ten seconds; longer than menu inputs need.
+const MAX_FRAMES: usize = 600;
+
+/// A short run of keypad input, kept frame by frame so it plays back with
+/// the timing it was entered with, and saved per ROM.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Macro {
+    frames: Vec<[bool; 16]>,
+    #[serde(skip)]
+    recording: Option<Vec<[bool; 16]>>,
+    /// The frame being played back.
+    #[serde(skip)]
+    playing: Option<usize>,
+}
+
+impl Macro {
+    pub fn load(rom_hash: &str) -> Macro {
+        fs::read(path(rom_hash))
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, rom_hash: &str) -> Result<(), Box<dyn Error>> {
+        let path = path(rom_hash);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Starts recording, or stops and keeps what was recorded.
+    pub fn record(&mut self, rom_hash: &str) -> String {
+        let Some(mut frames) = self.recording.take() else {
+            self.recording = Some(vec![]);
+            self.playing = None;
+            return "recording a macro".into();
+        };
+        // Playback starts with the first key, not the wait before it
+        let first = frames.iter().position(|keys| keys.contains(&true));
+        frames.drain(..first.unwrap_or(frames.len()));
+        let last = frames.iter().rposition(|keys| keys.contains(&true));
+        frames.truncate(last.map_or(0, |last| last + 2));
+        if frames.is_empty() {
+            return "macro not recorded: no keys pressed".into();
+        }
+        self.frames = frames;
+        match self.save(rom_hash) {
+            Ok(()) => format!("macro recorded, {} frames", self.frames.len()),
+            Err(e) => format!("macro recorded but not saved: {}", e),
+        }
+    }
+
+    pub fn play(&mut self) -> String {
+        if self.recording.is_some() {
+            return "macro still recording".into();
+        }
+        if self.frames.is_empty() {
+            return "no macro recorded".into();
+        }
+        self.playing = Some(0);
+        "playing the macro".into()
+    }
+
+    /// Presses what's played back this frame, or records what's pressed.
+    /// Returns whether the recording is full and should be stopped.
+    pub fn frame(&mut self, keys: &mut [bool; 16]) -> bool {
+        if let Some(frame) = self.playing {
+            for (key, played) in keys.iter_mut().zip(self.frames[frame]) {
+                *key |= played;
+            }
+            self.playing = Some(frame + 1).filter(|&next| next < self.frames.len());
+        }
+        self.recording.as_mut().is_some_and(|recording| {
+            recording.push(*keys);
+            recording.len() >= MAX_FRAMES
+        })
+    }
+}
+
+fn path(rom_hash: &str) -> PathBuf {
+    data_dir().join("macros").join(format!("{}.json", rom_hash))
+}
@@ -1,726 +1,1215 @@
+mod audio;
+mod background;
+mod bench;
+mod c8b;
+mod check;
+mod clipboard;
+mod compat;
+mod control;
+mod cycles;
+mod debugger;
+mod disasm;
+mod emit;
+mod explain;
+mod expr;
+mod gallery;
+mod hashes;
+mod hextext;
+mod http;
+mod input;
+mod legacy;
+mod lint;
+mod macros;
+mod midi;
+mod multiplexer;
+mod octocart;
+mod options;
+mod profiler;
+mod render;
+mod rewind;
+mod savestate;
+mod speedrun;
+mod storage;
+mod syntax;
+mod telnet;
+mod timing;
+mod trace;
+mod tracediff;
+mod tutorial;
+mod ui;
+mod versus;
+mod video;
+mod watch;
+mod watchdog;
+
+use fe_o8::{analysis, chip8, peripheral, quirks};
+
+use audio::Beeper;
+use background::Background;
+use c8b::Bundle;
+use chip8::{Chip8, Chip8Error, ADDR_PROGRAM_END, ADDR_START_PROGRAM};
+use clipboard::CopyToClipboard;
+#[cfg(unix)]
+use control::ControlSocket;
+use control::{Command, Controls};
 use crossterm::{
-    cursor, queue,
-    style::{Color, Print, PrintStyledContent, StyledContent, Stylize},
-    terminal::{self, Clear, ClearType, EnterAlternateScreen},
+    cursor,
+    style::Print,
+    terminal::{
+        self, BeginSynchronizedUpdate, EndSynchronizedUpdate, EnterAlternateScreen, SetTitle,
+    },
     ExecutableCommand, QueueableCommand,
 };
-use keyboard_query;
-use rand::random;
-use rodio::{
-    source::{SineWave, Source},
-    OutputStream, Sink,
-};
+use cycles::Cycles;
+use debugger::{Debugger, Stop};
+use emit::StateEmitter;
+use input::{Action, Hotkeys, Keyboard, KEYPAD_LABELS};
+use legacy::Legacy;
+use macros::Macro;
+use midi::MidiKeypad;
+use multiplexer::Multiplexer;
+use octocart::Cart;
+use options::Options;
+use quirks::{Profile, Quirks};
+use ratatui::layout::Rect;
+use render::Renderer;
+use rewind::Rewind;
+#[cfg(unix)]
+use signal_hook::consts::{SIGHUP, SIGUSR1};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use speedrun::Speedrun;
 use std::{
+    collections::BTreeMap,
     env,
     error::Error,
-    fs::File,
-    io::{prelude::*, stdout, Stdout},
-    path::Path,
+    fs::{self, File},
+    io::{self, prelude::*, stdout},
+    mem,
+    path::{Path, PathBuf},
     result::Result,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     thread::sleep,
-    time::Instant,
+    time::{Duration, Instant},
 };
+use telnet::TelnetServer;
+use timing::{FrameTiming, Pacing, TimingReport};
+use trace::Trace;
+use ui::{Console, InputHistory, Log, Palette, PanelData, Panels, SoundHistory, View, Workspace};
+use video::Video;
+use watch::FileWatcher;
+use watchdog::Watchdog;
 
-struct Chip8 {
-    memory: [u8; 4096],
-    display: [u64; 32],
-    pc: u16,
-    stack: Vec<u16>,
-    delay: u8,
-    sound: u8,
-    v: [u8; 16],
-    i: u16,
+/// Run when no ROM is given, so there's something to see: the name of the emulator.
+const DEFAULT_ROM: &[u8] = include_bytes!("../roms/fe_o8.ch8");
+
+/// How many times a second the delay and sound timers count down, whatever
+/// rate the screen is drawn at.
+pub const TIMER_RATE: u32 = 60;
+/// How many instructions are executed per 60Hz frame.
+pub const INSTRUCTIONS_PER_FRAME: usize = 12;
+/// How much the speed keys change that by, a quarter of normal speed.
+const SPEED_STEP: usize = INSTRUCTIONS_PER_FRAME / 4;
+/// The fastest the speed keys go, sixteen times normal speed.
+const MAX_INSTRUCTIONS_PER_FRAME: usize = INSTRUCTIONS_PER_FRAME * 16;
+
+/// Saves the debugger after a change, replying with any error since the change itself stands.
+fn save_debugger(debugger: &Debugger, rom_hash: &str) -> String {
+    match debugger.save(rom_hash) {
+        Ok(()) => String::new(),
+        Err(e) => format!("not saved: {}", e),
+    }
 }
 
-#[derive(Debug)]
-struct Opcode {
-    n0: u8,
-    n1: u8,
-    n2: u8,
-    n3: u8,
-    a: u16,
-    v: u8,
+/// The terminal in raw mode on the alternate screen, put back however `main`
+/// ends, errors included.
+struct RawTerminal {
+    /// Whether to keep the terminal's title on its title stack. Older Windows
+    /// consoles would print the escape sequences, and tmux and screen have none.
+    title_stack: bool,
 }
-impl Opcode {
-    fn from_slice(slice: &[u8]) -> Opcode {
-        assert!(slice.len() > 2);
-        Opcode {
-            n0: (slice[0] & 0xF0) >> 4,
-            n1: slice[0] & 0x0F,
-            n2: (slice[1] & 0xF0) >> 4,
-            n3: slice[1] & 0x0F,
-            a: (slice[0] as u16 & 0x0F) << 8 | slice[1] as u16,
-            v: slice[1],
+
+/// Asks the terminal to remember its title, and to go back to it, as there's
+/// no asking what it is (xterm's title stack, which most terminals have).
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+impl RawTerminal {
+    fn enter(title_stack: bool) -> Result<RawTerminal, Box<dyn Error>> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = stdout();
+        if title_stack {
+            stdout.execute(Print(PUSH_TITLE))?;
         }
+        stdout
+            .execute(EnterAlternateScreen)?
+            .execute(cursor::Hide)?
+            .execute(cursor::DisableBlinking)?;
+        Ok(RawTerminal { title_stack })
     }
 }
 
-fn style_number(number: u8, keys: [bool; 16]) -> StyledContent<String> {
-    let color = if keys[number as usize] {
-        Color::Black
-    } else {
-        Color::White
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = stdout()
+            .execute(terminal::LeaveAlternateScreen)
+            .and_then(|out| out.execute(cursor::Show))
+            .and_then(|out| match self.title_stack {
+                true => out.execute(Print(POP_TITLE)),
+                false => Ok(out),
+            });
+    }
+}
+
+/// How long attract mode shows the name of a game once it starts.
+const TITLE_TIME: Duration = Duration::from_secs(3);
+
+/// How long kiosk mode leaves a game that stopped on screen before
+/// starting it again, so its ending can be seen.
+const KIOSK_RESTART: Duration = Duration::from_secs(5);
+
+/// How long a frame lasts when nothing can happen until a key is pressed:
+/// paused, halted, or in BKY or a jump to itself with the timers stopped.
+/// Long enough to leave the CPU alone, short enough not to miss a tap.
+const IDLE_FRAME: Duration = Duration::from_millis(50);
+
+/// How often the screen is drawn even when nothing but the frame rate changed.
+const STATUS_REFRESH: Duration = Duration::from_secs(1);
+
+/// The name of a ROM file without its extension.
+fn rom_title(path: Option<&PathBuf>) -> String {
+    match path {
+        Some(path) => path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |stem| stem.to_string_lossy().into(),
+        ),
+        None => "fe_o8".into(),
+    }
+}
+
+/// A ROM loaded into a fresh machine, along with what's worked out from it.
+struct Loaded {
+    chip8: Chip8,
+    rom_hash: String,
+    rom_size: usize,
+    /// Which quirks are in use, for the registers panel.
+    profile_label: String,
+    /// Settings that came with the ROM, from an Octo cartridge or a c8b container.
+    palette: Option<Palette>,
+    instructions_per_frame: Option<usize>,
+    /// Keyboard keys for keypad keys 0 through F.
+    keymap: Option<[char; 16]>,
+}
+
+/// Watchers for the segment files, when `--watch` asks for them.
+fn watch_segments(options: &Options) -> Result<Vec<FileWatcher>, Box<dyn Error>> {
+    match options.watch {
+        true => (options.segments.iter())
+            .map(|(path, _)| FileWatcher::new(path))
+            .collect(),
+        false => Ok(vec![]),
+    }
+}
+
+/// Watching the program run to correct the quirks, when they were guessed,
+/// but not those asked for by name.
+fn quirk_check(options: &Options) -> Option<quirks::Check> {
+    options.detect_quirks.then(|| {
+        let mut check = quirks::Check::default();
+        for (name, _) in &options.quirk_overrides {
+            check.settle(name);
+        }
+        check
+    })
+}
+
+/// Reads a ROM, or the built in one without a path, into a new machine and picks its quirks.
+fn load(
+    path: Option<&PathBuf>,
+    options: &Options,
+    log: &mut Log,
+) -> Result<Loaded, Box<dyn Error>> {
+    let rom = match path {
+        Some(path) => read_rom_at(path, options.load_address, options.memory_size)?,
+        // The segments are the whole program
+        None if !options.segments.is_empty() => vec![],
+        None => DEFAULT_ROM.to_vec(),
+    };
+    let mut chip8 = Chip8::with_memory(options.memory_size);
+    chip8.load_at(&rom, options.load_address);
+    for (segment, addr) in &options.segments {
+        let bytes = read_rom_at(segment, *addr, options.memory_size)?;
+        let start = *addr as usize;
+        chip8.memory[start..start + bytes.len()].copy_from_slice(&bytes);
+    }
+    // Patched or put together from parts, it's a different program than the ROM alone
+    let rom_hash = match options.segments.is_empty() {
+        true => storage::rom_hash(&rom),
+        false => storage::rom_hash(&chip8.memory),
     };
-    let background = if keys[number as usize] {
-        Color::White
+    if let Some(entry) = options.entry {
+        chip8.enter_at(entry);
+    }
+    let file = match path {
+        Some(path) => Some(fs::read(path)?),
+        None => None,
+    };
+    let file = file.as_deref();
+    let cart = (file.filter(|file| octocart::is_cart(file)))
+        .map(Cart::decode)
+        .transpose()?;
+    let bundle = (file.filter(|file| c8b::is_c8b(file)))
+        .map(Bundle::decode)
+        .transpose()?;
+    if let Some(name) = bundle.as_ref().and_then(|bundle| bundle.name.as_ref()) {
+        match bundle.as_ref().and_then(|bundle| bundle.designer.as_ref()) {
+            Some(designer) => log.push(format!("{} by {}", name, designer)),
+            None => log.push(name.clone()),
+        }
+    }
+
+    let mut detected = None;
+    let profile = if options.detect_quirks {
+        let detection = quirks::detect(&chip8.memory, chip8.entry);
+        log.push(format!(
+            "quirks: {} ({})",
+            detection.profile.name(),
+            detection.reasons.join(", ")
+        ));
+        detected = Some(detection.quirks);
+        Some(detection.profile)
     } else {
-        Color::Black
+        options.profile
+    };
+    let mut profile_label = match (profile, options.detect_quirks) {
+        (Some(profile), true) => format!("quirks {} (auto)", profile.name()),
+        (Some(profile), false) => format!("quirks {}", profile.name()),
+        (None, _) => "quirks default".to_string(),
     };
-    return format!("{:x}", number).with(color).on(background);
+    let mut quirks = profile.map_or_else(Quirks::default, Profile::quirks);
+    // Some quirks of the guessed profile may have been guessed otherwise
+    let adjusted = detected.is_some_and(|detected| detected != quirks);
+    quirks = detected.unwrap_or(quirks);
+    // What the ROM comes with, unless a profile was asked for
+    if options.profile.is_none() && !options.detect_quirks {
+        if let Some(cart) = &cart {
+            cart.apply(&mut quirks);
+            profile_label = "quirks from cart".to_string();
+        }
+        let bundled = bundle
+            .as_ref()
+            .and_then(|bundle| (bundle.quirks).or(bundle.platform.map(Profile::quirks)));
+        if let Some(bundled) = bundled {
+            quirks = bundled;
+            profile_label = "quirks from c8b".to_string();
+        }
+    }
+    for (name, on) in &options.quirk_overrides {
+        quirks.set(name, *on)?;
+    }
+    if let Some(limit) = options.stack_limit {
+        quirks.stack_limit = limit;
+    }
+    if adjusted || !options.quirk_overrides.is_empty() {
+        profile_label.push('*');
+    }
+    // Asking for SUPER-CHIP doesn't make its instructions run
+    let note = match quirks == Profile::Schip.quirks() {
+        true => " (the schip profile only changes quirks)",
+        false => "",
+    };
+    for (extension, (count, first)) in analysis::extensions(&chip8.memory, chip8.entry) {
+        log.push(format!(
+            "warning: {} {} instructions, the first at {:03X}, which fe_o8 can't run{}",
+            count, extension, first, note
+        ));
+    }
+    chip8.quirks = quirks;
+    for spec in &options.peripherals {
+        chip8.attach(peripheral::create(spec)?);
+    }
+
+    Ok(Loaded {
+        chip8,
+        rom_hash,
+        rom_size: rom.len(),
+        profile_label,
+        palette: (cart.as_ref().and_then(Cart::palette))
+            .or(bundle.as_ref().and_then(Bundle::palette)),
+        instructions_per_frame: (cart.and_then(|cart| cart.options.tickrate))
+            .or(bundle.as_ref().and_then(|bundle| bundle.tickrate)),
+        keymap: bundle.and_then(|bundle| bundle.keymap),
+    })
 }
 
-fn color_from_index(index: usize) -> Color {
-    match index {
-        0 => Color::AnsiValue(21),
-        1 => Color::AnsiValue(20),
-        2 => Color::AnsiValue(19),
-        3 => Color::AnsiValue(18),
-        _ => Color::AnsiValue(17),
+/// Sets or, given nothing, removes the label or note at an address.
+fn set_text(texts: &mut BTreeMap<u16, String>, addr: u16, text: &str) {
+    if text.is_empty() {
+        texts.remove(&addr);
+    } else {
+        texts.insert(addr, text.into());
     }
 }
 
-fn print_memory<'std>(
-    c8: &Chip8,
-    stdout: &'std mut Stdout,
-) -> Result<&'std mut Stdout, Box<dyn Error>> {
-    for i in (0..4096).step_by(32) {
-        let rng = i..(i + 32);
-        let slice = &c8.memory[i as usize..i as usize + 32];
-        let mut color: Color;
-        let character = if rng.contains(&c8.pc) {
-            '╫'
-        } else if rng.contains(&c8.i) {
-            '┼'
-        } else if slice.iter().all(|n| *n == 0) {
-            ' '
-        } else if slice.iter().filter(|n| **n == 1).count() > 8 {
-            '─'
-        } else if slice.iter().filter(|n| **n == 1).count() > 16 {
-            '━'
-        } else if slice.iter().filter(|n| **n == 1).count() > 24 {
-            '═'
-        } else {
-            '┄'
-        };
-        if i < 0x200 {
-            color = Color::Black;
-        } else {
-            color = Color::Reset;
-        }
+/// Reads a ROM, either the bytes themselves, hex written out as text, an
+/// Octo cartridge or a c8b container, and checks it fits the program area
+/// of a machine with the given amount of memory.
+pub fn read_rom(path: &Path, memory_size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    read_rom_at(path, ADDR_START_PROGRAM, memory_size)
+}
 
-        for (j, addr) in c8.stack.iter().rev().enumerate() {
-            if rng.contains(&addr) {
-                color = color_from_index(j);
-            }
-        }
-        stdout.queue(PrintStyledContent(format!("{}", character).on(color)))?;
+/// Loads a ROM for the subcommands that look at it without running it, at
+/// `start` and entered at `entry` as `--load-addr` and `--entry` do.
+pub fn load_rom(
+    path: &Path,
+    start: u16,
+    entry: Option<u16>,
+) -> Result<(Chip8, Vec<u8>), Box<dyn Error>> {
+    let rom = read_rom_at(path, start, ADDR_PROGRAM_END as usize)?;
+    let mut chip8 = Chip8::new();
+    chip8.load_at(&rom, start);
+    if let Some(entry) = entry {
+        chip8.enter_at(entry);
     }
-    Ok(stdout)
+    Ok((chip8, rom))
+}
+
+/// Reads a ROM as `read_rom` does, checking it fits between `start` and
+/// the end of memory.
+fn read_rom_at(path: &Path, start: u16, memory_size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut rom = fs::read(path)?;
+    if octocart::is_cart(&rom) {
+        rom = Cart::decode(&rom)?
+            .rom()
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+    } else if c8b::is_c8b(&rom) {
+        rom = Bundle::decode(&rom)
+            .map_err(|error| format!("{}: {}", path.display(), error))?
+            .code;
+    } else if hextext::is_text(&rom) {
+        rom = hextext::parse(&String::from_utf8(rom)?)
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+    }
+    let room = memory_size.saturating_sub(start as usize);
+    if rom.is_empty() {
+        return Err(format!("{}: the ROM is empty", path.display()).into());
+    }
+    if rom.len() > room {
+        return Err(format!(
+            "{}: the ROM is {} bytes, {} more than fit between {:03X} and {:03X}",
+            path.display(),
+            rom.len(),
+            rom.len() - room,
+            start,
+            memory_size
+        )
+        .into());
+    }
+    Ok(rom)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
-    let path = Path::new(&args[1]);
+    let mut args = env::args().skip(1).peekable();
+    let command_line: Vec<String> = match args.peek().map(String::as_str) {
+        Some("bench") => return bench::run(args.skip(1)),
+        Some("hashes") => return hashes::run(args.skip(1)),
+        Some("disasm") => return disasm::run(args.skip(1)),
+        Some("check") => return check::run(args.skip(1)),
+        Some("lint") => return lint::run(args.skip(1)),
+        Some("compat") => return compat::run(args.skip(1)),
+        Some("versus") => return versus::run(args.skip(1)),
+        Some("tracediff") => return tracediff::run(args.skip(1)),
+        Some("tutorial") => return tutorial::run(),
+        Some("cart") => return octocart::run(args.skip(1)),
+        Some("c8b") => return c8b::run(args.skip(1)),
+        Some("gallery") => match gallery::choose()? {
+            // Any options given after the subcommand apply to the chosen ROM
+            Some(rom) => args.skip(1).chain([rom.display().to_string()]).collect(),
+            None => return Ok(()),
+        },
+        _ => args.collect(),
+    };
+    // The config file's options come first so the command line can override them
+    let parse = || {
+        Options::parse(
+            options::config_args()?
+                .into_iter()
+                .chain(command_line.clone()),
+        )
+    };
+    let mut options = parse()?;
+    if options.list_audio {
+        for name in audio::devices()? {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+    if options.list_midi {
+        for name in midi::ports()? {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+    // Which of the ROMs given is running, and since when
+    let mut current = 0;
+    let mut started = Instant::now();
+    let default_palette = Palette::default();
     //let path = Path::new("/home/qwert/Downloads/IBM Logo.ch8");
     //let path = Path::new("/home/qwert/Downloads/test_opcode.ch8");
-    let mut file = File::open(path)?;
+    let mut log = Log::default();
+    let Loaded {
+        mut chip8,
+        mut rom_hash,
+        mut rom_size,
+        mut profile_label,
+        palette: mut rom_palette,
+        instructions_per_frame: mut rom_speed,
+        mut keymap,
+    } = load(options.paths.get(current), &options, &mut log)?;
+    let mut watcher = match options.paths.get(current) {
+        Some(path) if options.watch => Some(FileWatcher::new(path)?),
+        _ => None,
+    };
+    let mut segment_watchers = watch_segments(&options)?;
+
+    let mut emitter = match &options.emit_state {
+        Some(target) => Some(StateEmitter::open(target)?),
+        None => None,
+    };
+    let controls = Controls::new();
+    #[cfg(unix)]
+    let _socket = match &options.control {
+        Some(socket) => Some(ControlSocket::bind(socket, controls.sender())?),
+        None => None,
+    };
+    if let Some(addr) = &options.http {
+        http::serve(addr, controls.sender())?;
+    }
+    let telnet = match &options.telnet {
+        Some(addr) => Some(TelnetServer::bind(addr)?),
+        None => None,
+    };
+
+    // Nobody is there to answer in kiosk mode
+    if options.autosave && !options.kiosk {
+        if let Ok(state) = savestate::read(&rom_hash, &savestate::autosave_path(&rom_hash)) {
+            for line in &state.thumbnail {
+                println!("{}", line);
+            }
+            print!("Resume from the autosave made {}? [Y/n] ", state.age());
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            if !answer.trim().eq_ignore_ascii_case("n") {
+                state.restore(&mut chip8)?;
+            }
+        }
+    }
 
     let mut stdout = stdout();
-    let keyboard = keyboard_query::DeviceState::new();
-
-    terminal::enable_raw_mode()?;
-    stdout
-        .execute(EnterAlternateScreen)?
-        .execute(Clear(ClearType::All))?
-        .execute(cursor::Hide)?
-        .execute(cursor::DisableBlinking)?;
-
-    //Initialize main memory
-    let mut chip8 = Chip8 {
-        memory: [0; 4096],
-        display: [0; 32],
-        pc: 0x200,
-        stack: vec![],
-        delay: 0x0,
-        sound: 0x0,
-        v: [0; 16],
-        i: 0x0,
+    let mut keyboard = Keyboard::new();
+    keyboard.set_keypad(keymap.as_ref())?;
+    let midi = match &options.midi {
+        Some(name) => Some(MidiKeypad::open(name, &options.midi_map)?),
+        None => None,
+    };
+    let hotkeys = match options.kiosk {
+        true => Hotkeys::none(),
+        false => options.hotkeys.clone(),
     };
-    let font_arr = [
-        0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-        0x20, 0x60, 0x20, 0x20, 0x70, // 1
-        0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-        0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-        0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-        0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-        0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-        0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-        0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-        0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-        0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-        0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-        0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-        0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-        0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-        0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-    ];
-    let font_addr: [u16; 16] = [
-        0x050, // 0
-        0x055, // 1
-        0x05A, // 2
-        0x05F, // 3
-        0x064, // 4
-        0x069, // 5
-        0x06E, // 6
-        0x073, // 7
-        0x078, // 8
-        0x07D, // 9
-        0x082, // A
-        0x087, // B
-        0x08C, // C
-        0x091, // D
-        0x096, // E
-        0x09A, // F
-    ];
-    chip8.memory[0x050..0x0A0].copy_from_slice(&font_arr);
-
-    file.read(&mut chip8.memory[0x200..])?;
+    let hotkey = |action| hotkeys.key(action);
+
+    let legacy = Legacy::detect(options.legacy_console);
+    let multiplexer = options.multiplexer.unwrap_or_else(Multiplexer::detect);
+    let raw_terminal = RawTerminal::enter(legacy.is_none() && multiplexer.is_none())?;
+    // Older consoles can't be asked, and are black behind the text anyway
+    let background = match (options.background, legacy) {
+        (Some(background), _) => Some(background),
+        (None, None) => Background::query(multiplexer),
+        (None, Some(_)) => None,
+    };
+    let background = background.unwrap_or(Background::Dark);
+    // Each frame is rendered into a buffer first, so it can also be sent to telnet viewers
+    let indexed = multiplexer.is_some() && !Multiplexer::truecolor();
+    let mut renderer = Renderer::new(legacy, indexed, options.interlace);
+    if legacy.is_some() && telnet.is_some() {
+        log.push("older console: frames aren't sent to telnet viewers".into());
+    }
+    renderer.clear()?;
+    let mut workspace = Workspace::Debug;
+    let mut panels = Panels::from(workspace).hide(&options.hidden);
+    let mut fullscreen = options.fullscreen || options.kiosk;
+    let mut legend = false;
+    let mut inputs = InputHistory::default();
+    let mut sounds = SoundHistory::default();
+    let mut panel_data = PanelData::default();
+    let mut speedrun = (options.speedrun).then(|| Speedrun::new(options.split_on, &chip8));
+    let mut inverted = false;
+    let mut instructions_per_frame = (options.speed)
+        .or(rom_speed)
+        .unwrap_or(INSTRUCTIONS_PER_FRAME);
+    // Watched for settings that can change while the game runs
+    let config = options::config_path();
+    let config_watcher = match config.exists() {
+        true => Some(FileWatcher::new(&config)?),
+        false => None,
+    };
+    // SIGUSR1 stops the game and brings up the debugger, for when it hangs unexpectedly
+    let attach = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    signal_hook::flag::register(SIGUSR1, attach.clone())?;
+    // Being told to stop leaves through the same way as the pause key, putting the
+    // terminal back and autosaving. Raw mode means Ctrl-C arrives as a key, not SIGINT.
+    let quit = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register(signal, quit.clone())?;
+    }
+    #[cfg(unix)]
+    signal_hook::flag::register(SIGHUP, quit.clone())?;
 
     //Set up sound
-    let (_stream, stream_handle) = OutputStream::try_default()?;
-    let sink = Sink::try_new(&stream_handle)?;
-    let beep = SineWave::new(440).amplify(0.20);
-    sink.append(beep);
-    sink.pause();
+    let beeper = Beeper::open(options.audio_device.as_deref(), options.audio_buffer)?;
+    let mut latency_reported = false;
 
     let mut last_time = Instant::now();
-    let mut keys = [false; 16];
+    let mut pacing = Pacing::new(options.refresh);
+    let mut paused = false;
+    // Set when the program does something it can't continue from
+    let mut halted: Option<Chip8Error> = None;
+    // When the game stopped, for kiosk mode to start it again
+    let mut stopped_since: Option<Instant> = None;
+    let mut watchdog = Watchdog::new(options.stall);
+    let mut debugger = Debugger::load(&rom_hash);
+    debugger.quirk_check = quirk_check(&options);
+    let mut keypad_macro = Macro::load(&rom_hash);
+    let mut trace = match &options.trace {
+        Some(path) => Some(Trace::create(path, options.syntax)?),
+        None => None,
+    };
+    let mut video = match &options.record_video {
+        Some(path) => Some(Video::start(path)?),
+        None => None,
+    };
+    let mut rewind = Rewind::default();
+    // Frames left on keys pressed through the control socket
+    let mut held_keys = [0u32; 16];
+    let mut timing_report = TimingReport::default();
+    let mut console = Console::default();
+    // What was on screen when it was last drawn, and when that was
+    let mut drawn = None;
+    let mut last_drawn = Instant::now();
+    let mut console_log = match &options.console_log {
+        Some(path) => Some(File::create(path)?),
+        None => None,
+    };
+    let mut slept = Duration::ZERO;
+    let mut idle = false;
+    // Which ROM the terminal's title names, and when its frame rate was updated
+    let mut titled = None;
+    let mut title_refreshed = Instant::now();
 
     'exit: loop {
-        if last_time.elapsed().as_secs_f32() * 60.0 < 1.0 {
-            sleep(Instant::now() - last_time);
+        let frame = if idle { IDLE_FRAME } else { pacing.frame() };
+        let elapsed = last_time.elapsed();
+        if elapsed < frame {
+            let asleep = Instant::now();
+            // Not past the end of the frame, which idle ones would otherwise overshoot
+            sleep(elapsed.min(frame - elapsed));
+            slept += asleep.elapsed();
         } else {
-            stdout.queue(cursor::MoveTo(0, 0))?.queue(Print(format!(
-                "{:.1}fps {:.4}fpf",
-                1.0 / last_time.elapsed().as_secs_f32(),
-                last_time.elapsed().as_secs_f32() * 60.0
-            )))?;
+            let frame_time = last_time.elapsed();
             last_time = Instant::now();
-            let last_keys = keys;
-            keys = [false; 16];
-
-            for key in keyboard.query_keymap() {
-                match key {
-                    0x77 => break 'exit,      // Pause/Break
-                    0x2D => keys[0x0] = true, // 1
-                    0x02 => keys[0x1] = true, // 2
-                    0x03 => keys[0x2] = true, // 3
-                    0x04 => keys[0x3] = true, // 4
-                    0x10 => keys[0x4] = true, // q
-                    0x11 => keys[0x5] = true, // w
-                    0x12 => keys[0x6] = true, // e
-                    0x1E => keys[0x7] = true, // r
-                    0x1F => keys[0x8] = true, // a
-                    0x20 => keys[0x9] = true, // s
-                    0x2C => keys[0xA] = true, // d
-                    0x2E => keys[0xB] = true, // f
-                    0x05 => keys[0xC] = true, // z
-                    0x13 => keys[0xD] = true, // x
-                    0x21 => keys[0xE] = true, // c
-                    0x2F => keys[0xF] = true, // v
-                    _ => (),
+            let mut timing = FrameTiming {
+                frame: frame_time,
+                sleep: mem::take(&mut slept),
+                ..FrameTiming::default()
+            };
+
+            let mut keys = [false; 16];
+            for (key, frames) in keys.iter_mut().zip(held_keys.iter_mut()) {
+                if *frames > 0 {
+                    *key = true;
+                    *frames -= 1;
                 }
             }
 
-            queue!(
-                stdout,
-                cursor::MoveTo(70 + 64, 5),
-                PrintStyledContent(style_number(0x1, keys)),
-                PrintStyledContent(style_number(0x2, keys)),
-                PrintStyledContent(style_number(0x3, keys)),
-                PrintStyledContent(style_number(0xC, keys)),
-                cursor::MoveTo(70 + 64, 6),
-                PrintStyledContent(style_number(0x4, keys)),
-                PrintStyledContent(style_number(0x5, keys)),
-                PrintStyledContent(style_number(0x6, keys)),
-                PrintStyledContent(style_number(0xD, keys)),
-                cursor::MoveTo(70 + 64, 7),
-                PrintStyledContent(style_number(0x7, keys)),
-                PrintStyledContent(style_number(0x8, keys)),
-                PrintStyledContent(style_number(0x9, keys)),
-                PrintStyledContent(style_number(0xE, keys)),
-                cursor::MoveTo(70 + 64, 8),
-                PrintStyledContent(style_number(0xA, keys)),
-                PrintStyledContent(style_number(0x0, keys)),
-                PrintStyledContent(style_number(0xB, keys)),
-                PrintStyledContent(style_number(0xF, keys)),
-            )?;
-
-            if chip8.delay > 0 {
-                chip8.delay -= 1;
-            };
-            if chip8.sound > 0 {
-                if sink.is_paused() {
-                    sink.play();
+            keyboard.poll();
+            let exit_chord =
+                options.kiosk && (options.kiosk_exit.iter()).all(|&key| keyboard.held(key));
+            if keyboard.held(hotkey(Action::Quit)) || exit_chord || quit.load(Ordering::Relaxed) {
+                break 'exit;
+            }
+            let mut switch_to = None;
+            // Every watcher is asked, so none are left with changes for next frame
+            let segments_changed = (segment_watchers.iter())
+                .filter(|watcher| watcher.changed())
+                .count();
+            if watcher.as_ref().is_some_and(FileWatcher::changed) || segments_changed > 0 {
+                switch_to = Some(current);
+            }
+            if config_watcher.as_ref().is_some_and(FileWatcher::changed) {
+                match parse() {
+                    // Only what can change without disturbing the game
+                    Ok(reloaded) => {
+                        if reloaded.speed != options.speed {
+                            instructions_per_frame = (reloaded.speed)
+                                .or(rom_speed)
+                                .unwrap_or(INSTRUCTIONS_PER_FRAME);
+                        }
+                        options.palette = reloaded.palette;
+                        options.hidden = reloaded.hidden;
+                        options.memory_map = reloaded.memory_map;
+                        options.speed = reloaded.speed;
+                        if reloaded.refresh != options.refresh {
+                            pacing = Pacing::new(reloaded.refresh);
+                        }
+                        options.refresh = reloaded.refresh;
+                        // New segments make a different program, so it starts over
+                        if reloaded.segments != options.segments {
+                            options.segments = reloaded.segments;
+                            segment_watchers = watch_segments(&options)?;
+                            switch_to = Some(current);
+                        }
+                        panels = Panels::from(workspace).hide(&options.hidden);
+                        log.push("options reloaded".into());
+                    }
+                    Err(e) => log.push(format!("options not reloaded: {}", e)),
                 }
-                chip8.sound -= 1;
-            } else {
-                if !sink.is_paused() {
-                    sink.pause();
+            }
+            let playlist = options.paths.len();
+            let game_over = halted.is_some() || chip8.jumps_to_itself();
+            let stopped = match options.kiosk && (game_over || paused) {
+                true => stopped_since.get_or_insert_with(Instant::now).elapsed(),
+                false => {
+                    stopped_since = None;
+                    Duration::ZERO
                 }
+            };
+            if stopped >= KIOSK_RESTART {
+                switch_to = Some(current);
             }
-            //stdout.execute(Clear(terminal::ClearType::All))?;
-            stdout
-                .queue(cursor::MoveTo(0, 2))?
-                .queue(Print(format!("╔{:═<128}╗", "")))?;
-
-            for line in chip8.display {
-                let output: String = format!("{:064b}", line)
-                    .chars()
-                    .map(|c| match c {
-                        '1' => "██",
-                        '0' => "░░",
-                        _ => "  ",
-                    })
-                    .collect();
-                stdout
-                    .queue(cursor::MoveToNextLine(1))?
-                    .queue(Print::<String>(format!("║{}║", output)))?;
-            }
-            stdout
-                .queue(cursor::MoveToNextLine(1))?
-                .queue(Print(format!("╠{:═<128}╣", "")))?;
-
-            stdout.queue(cursor::MoveToNextLine(1))?.queue(Print("╙"))?;
-            print_memory(&chip8, &mut stdout)?
-                .queue(Print("╜"))?
-                .flush()?;
-
-            for _ in 0..12 {
-                // Fetch
-                let op = Opcode::from_slice(&chip8.memory[chip8.pc as usize..]);
-                // stdout.execute(cursor::MoveTo(0, 0))?;
-                // stdout.execute(terminal::Clear(ClearType::CurrentLine))?;
-                // stdout.execute(Print(format!(
-                //     "{:02X}{:02X}",
-                //     chip8.memory[chip8.pc as usize],
-                //     chip8.memory[chip8.pc as usize + 1]
-                // )))?;
-                chip8.pc += 2;
-                // Decode and Execute
-                match op {
-                    Opcode {
-                        n0: 0x0,
-                        n1: 0x0,
-                        n2: 0xE,
-                        n3: 0x0,
-                        a: _,
-                        v: _,
-                    } => chip8.display = [0; 32], // CLR
-                    Opcode {
-                        n0: 0x0,
-                        n1: 0x0,
-                        n2: 0xE,
-                        n3: 0xE,
-                        a: _,
-                        v: _,
-                    } => chip8.pc = chip8.stack.pop().unwrap(), // RTN
-                    Opcode {
-                        n0: 0x1,
-                        n1: _,
-                        n2: _,
-                        n3: _,
-                        a: nnn,
-                        v: _,
-                    } => chip8.pc = nnn, // JMP
-                    Opcode {
-                        n0: 0x2,
-                        n1: _,
-                        n2: _,
-                        n3: _,
-                        a: nnn,
-                        v: _,
-                    } => {
-                        chip8.stack.push(chip8.pc);
-                        chip8.pc = nnn;
-                    } // CAL
-                    Opcode {
-                        n0: 0x3,
-                        n1: x,
-                        n2: _,
-                        n3: _,
-                        a: _,
-                        v: nn,
-                    } => {
-                        let x = x as usize;
-                        if chip8.v[x] == nn {
-                            chip8.pc += 2
+            let attract_done = options
+                .attract
+                .is_some_and(|time| started.elapsed() >= time || game_over);
+            if playlist > 1 && (keyboard.pressed(hotkey(Action::NextRom)) || attract_done) {
+                switch_to = Some((current + 1) % playlist);
+            }
+            if playlist > 1 && keyboard.pressed(hotkey(Action::PreviousRom)) {
+                switch_to = Some((current + playlist - 1) % playlist);
+            }
+            if keyboard.pressed(hotkey(Action::Reset)) {
+                switch_to = Some(current);
+            }
+            if let Some(next) = switch_to {
+                let path = options.paths.get(next);
+                // Otherwise the same ROM is being reloaded after a change or a reset
+                let switching = next != current;
+                match load(path, &options, &mut log) {
+                    Ok(loaded) => {
+                        if options.autosave && switching {
+                            savestate::save(
+                                &chip8,
+                                &rom_hash,
+                                &savestate::autosave_path(&rom_hash),
+                            )?;
                         }
-                    } // SEQ
-                    Opcode {
-                        n0: 0x4,
-                        n1: x,
-                        n2: _,
-                        n3: _,
-                        a: _,
-                        v: nn,
-                    } => {
-                        let x = x as usize;
-                        if chip8.v[x] != nn {
-                            chip8.pc += 2
+                        Loaded {
+                            chip8,
+                            rom_hash,
+                            rom_size,
+                            profile_label,
+                            palette: rom_palette,
+                            instructions_per_frame: rom_speed,
+                            keymap,
+                        } = loaded;
+                        if let Err(e) = keyboard.set_keypad(keymap.as_ref()) {
+                            log.push(e);
                         }
-                    } // SNE
-                    Opcode {
-                        n0: 0x5,
-                        n1: x,
-                        n2: y,
-                        n3: 0x0,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let y = y as usize;
-                        if chip8.v[x] == chip8.v[y] {
-                            chip8.pc += 2
+                        if options.autosave && switching {
+                            let autosave = savestate::autosave_path(&rom_hash);
+                            if let Ok(state) = savestate::read(&rom_hash, &autosave) {
+                                state.restore(&mut chip8)?;
+                            }
                         }
-                    } // SER
-                    Opcode {
-                        n0: 0x6,
-                        n1: x,
-                        n2: _,
-                        n3: _,
-                        a: _,
-                        v: nn,
-                    } => chip8.v[x as usize] = nn, // CAN
-                    Opcode {
-                        n0: 0x7,
-                        n1: x,
-                        n2: _,
-                        n3: _,
-                        a: _,
-                        v: nn,
-                    } => {
-                        let x = x as usize;
-                        let (value, ..) = chip8.v[x].overflowing_add(nn);
-                        chip8.v[x] = value;
-                    } // CAD
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0x0,
-                        a: _,
-                        v: _,
-                    } => chip8.v[x as usize] = chip8.v[y as usize], // ASN
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0x1,
-                        a: _,
-                        v: _,
-                    } => chip8.v[x as usize] |= chip8.v[y as usize], // ORR
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0x2,
-                        a: _,
-                        v: _,
-                    } => chip8.v[x as usize] &= chip8.v[y as usize], // AND
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0x3,
-                        a: _,
-                        v: _,
-                    } => chip8.v[x as usize] ^= chip8.v[y as usize], // XOR
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0x4,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let y = y as usize;
-                        let (value, carry) = chip8.v[x].overflowing_add(chip8.v[y]);
-                        chip8.v[x] = value;
-                        chip8.v[0xF] = carry as u8;
-                    } // ADD
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0x5,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let y = y as usize;
-                        let (value, carry) = chip8.v[x].overflowing_sub(chip8.v[y]);
-                        chip8.v[x] = value;
-                        chip8.v[0xF] = !carry as u8;
-                    } // SXY
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0x6,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let y = y as usize;
-                        let (value, carry) = chip8.v[y].overflowing_shr(1);
-                        chip8.v[x] = value;
-                        chip8.v[0xF] = carry as u8;
-                    } // RSH
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0x7,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let y = y as usize;
-                        let (value, carry) = chip8.v[y].overflowing_sub(chip8.v[x]);
-                        chip8.v[x] = value;
-                        chip8.v[0xF] = !carry as u8;
-                    } // SYX
-                    Opcode {
-                        n0: 0x8,
-                        n1: x,
-                        n2: y,
-                        n3: 0xE,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let y = y as usize;
-                        let (value, carry) = chip8.v[y].overflowing_shl(1);
-                        chip8.v[x] = value;
-                        chip8.v[0xF] = carry as u8;
-                    } // LSH
-                    Opcode {
-                        n0: 0x9,
-                        n1: x,
-                        n2: y,
-                        n3: 0x0,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let y = y as usize;
-                        if chip8.v[x] != chip8.v[y] {
-                            chip8.pc += 2
+                        if let Some(speedrun) = &mut speedrun {
+                            speedrun.reset(&chip8);
                         }
-                    } // SNR
-                    Opcode {
-                        n0: 0xA,
-                        n1: _,
-                        n2: _,
-                        n3: _,
-                        a: nnn,
-                        v: _,
-                    } => chip8.i = nnn, // CAI
-                    Opcode {
-                        n0: 0xB,
-                        n1: _,
-                        n2: _,
-                        n3: _,
-                        a: nnn,
-                        v: _,
-                    } => chip8.pc = nnn + chip8.v[0] as u16, // J0N
-                    Opcode {
-                        n0: 0xC,
-                        n1: x,
-                        n2: _,
-                        n3: _,
-                        a: _,
-                        v: nn,
-                    } => chip8.v[x as usize] = random::<u8>() & nn, // RND
-                    Opcode {
-                        n0: 0xD,
-                        n1: x,
-                        n2: y,
-                        n3: n,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let y = y as usize;
-                        let coord_x = chip8.v[x] % 64;
-                        let mut coord_y = chip8.v[y] as usize % 32;
-                        chip8.v[0xF] = 0;
-                        let mut i = chip8.i as usize;
-                        let imax = i + n as u16 as usize;
-                        while coord_y < 32 && i < imax {
-                            // Operate on a u128, with 32 bits of padding to avoid overlfow
-
-                            // First, put the sprite at coord 0 (bit 32) by lshifting it 32 (pad) + 64 (screen width) - 8 (byte width)
-                            // 00000000000000000000000000000000|SSSSSSSS00000000000000000000000000000000000000000000000000000000|00000000000000000000000000000000
-                            let sprite = (chip8.memory[i] as u128) << 32 + 64 - 8;
-
-                            // Then rshift it to it's proper x position
-                            // 00000000000000000000000000000000|000SSSSSSSS00000000000000000000000000000000000000000000000000000|00000000000000000000000000000000
-                            //                                 |x-|
-                            let sprite = sprite >> coord_x;
-
-                            // Then do an overflow aware rshift of 32 to squish the display 64 into the lower 64
-                            //0000000000000000000000000000000000000000000000000000000000000000|000SSSSSSSS00000000000000000000000000000000000000000000000000000
-                            let (mask, _) = sprite.overflowing_shr(32);
-
-                            //Then grab only the 64 bits we care about
-                            //000SSSSSSSS00000000000000000000000000000000000000000000000000000
-                            let mask = (mask & 0xFFFF_FFFF__FFFF_FFFF) as u64;
-
-                            chip8.v[0xF] = if mask & chip8.display[coord_y] > 0 {
-                                0x1
-                            } else {
-                                0x0
-                            };
-                            chip8.display[coord_y] ^= mask;
-
-                            coord_y += 1;
-                            i += 1;
+                        if let (true, Some(path)) = (switching, path) {
+                            started = Instant::now();
+                            instructions_per_frame = (options.speed)
+                                .or(rom_speed)
+                                .unwrap_or(INSTRUCTIONS_PER_FRAME);
+                            log.push(format!("{} ({}/{})", path.display(), next + 1, playlist));
+                            if options.watch {
+                                watcher = Some(FileWatcher::new(path)?);
+                            }
                         }
-                    } // DRW
-                    Opcode {
-                        n0: 0xE,
-                        n1: x,
-                        n2: 0x9,
-                        n3: 0xE,
-                        a: _,
-                        v: _,
-                    } => {
-                        if keys[chip8.v[x as usize] as usize & 0x0F] {
-                            chip8.pc += 2;
+                        current = next;
+                        halted = None;
+                        stopped_since = None;
+                        // Stopped at a breakpoint, it would stay paused after starting again
+                        if options.kiosk {
+                            paused = false;
                         }
-                    } // KYP
-                    Opcode {
-                        n0: 0xE,
-                        n1: x,
-                        n2: 0xA,
-                        n3: 0x1,
-                        a: _,
-                        v: _,
-                    } => {
-                        if !keys[chip8.v[x as usize] as usize & 0x0F] {
-                            chip8.pc += 2;
+                        debugger = Debugger::load(&rom_hash);
+                        debugger.quirk_check = quirk_check(&options);
+                        keypad_macro = Macro::load(&rom_hash);
+                        rewind.clear();
+                    }
+                    Err(e) if switching => log.push(format!("{}: {}", rom_title(path), e)),
+                    // A half-written file fails to read; the write that finishes it reloads
+                    Err(_) => (),
+                }
+            }
+            for (key, held) in keys.iter_mut().zip(keyboard.keypad()) {
+                *key |= held;
+            }
+            if let Some(midi) = &midi {
+                for (key, held) in keys.iter_mut().zip(midi.keys()) {
+                    *key |= held;
+                }
+            }
+            for (slot, key) in [Action::Slot1, Action::Slot2, Action::Slot3, Action::Slot4]
+                .into_iter()
+                .enumerate()
+            {
+                let slot = slot + 1;
+                if !keyboard.pressed(hotkey(key)) {
+                    continue;
+                }
+                let message = if keyboard.shift() {
+                    savestate::save_slot(&chip8, &rom_hash, slot)
+                        .map(|_| format!("saved slot {}", slot))
+                } else {
+                    savestate::load_slot(&mut chip8, &rom_hash, slot).map(|state| {
+                        halted = None;
+                        format!("loaded slot {}, saved {}", slot, state.age())
+                    })
+                };
+                log.push(message.unwrap_or_else(|e| format!("slot {}: {}", slot, e)));
+            }
+            let toggles = [
+                (Action::Status, &mut panels.status),
+                (Action::Keypad, &mut panels.keypad),
+                (Action::Registers, &mut panels.registers),
+                (Action::Timers, &mut panels.timers),
+                (Action::Memory, &mut panels.memory),
+                (Action::Disassembly, &mut panels.disassembly),
+                (Action::Log, &mut panels.log),
+                (Action::Counters, &mut panels.counters),
+                (Action::Sound, &mut panels.sound),
+            ];
+            for (action, shown) in toggles {
+                if keyboard.pressed(hotkey(action)) {
+                    *shown = !*shown;
+                }
+            }
+            for (action, steps) in [(Action::VolumeDown, -1), (Action::VolumeUp, 1)] {
+                if keyboard.pressed(hotkey(action)) {
+                    let level = beeper.change_volume(steps);
+                    log.push(format!("volume {}/{}", level, audio::VOLUME_STEPS));
+                }
+            }
+            if keyboard.pressed(hotkey(Action::Fullscreen)) {
+                fullscreen = !fullscreen;
+            }
+            let speed = instructions_per_frame;
+            if keyboard.pressed(hotkey(Action::Faster)) {
+                // A cart may already run faster than the keys go
+                instructions_per_frame =
+                    (speed + SPEED_STEP).min(MAX_INSTRUCTIONS_PER_FRAME.max(speed));
+            }
+            if keyboard.pressed(hotkey(Action::Slower)) {
+                instructions_per_frame = speed.saturating_sub(SPEED_STEP).max(SPEED_STEP);
+            }
+            if instructions_per_frame != speed {
+                log.push(format!(
+                    "speed {:.2}x, {} instructions a frame",
+                    instructions_per_frame as f32 / INSTRUCTIONS_PER_FRAME as f32,
+                    instructions_per_frame
+                ));
+            }
+            if keyboard.pressed(hotkey(Action::Legend)) {
+                legend = !legend;
+                // The legend is drawn on the keypad, so show it to be seen
+                panels.keypad |= legend;
+            }
+            if keyboard.pressed(hotkey(Action::Invert)) {
+                inverted = !inverted;
+            }
+            if let (true, Some(speedrun)) = (keyboard.pressed(hotkey(Action::Split)), &mut speedrun)
+            {
+                speedrun.split();
+            }
+            if keyboard.pressed(hotkey(Action::Screenshot)) {
+                let screenshot = control::screenshot(&chip8);
+                stdout.execute(CopyToClipboard(&screenshot, multiplexer))?;
+                log.push("screenshot copied to the clipboard".into());
+            }
+            if attach.swap(false, Ordering::Relaxed) {
+                paused = true;
+                fullscreen = false;
+                workspace = Workspace::Debug;
+                panels = Panels::from(workspace).hide(&options.hidden);
+                log.push(format!("attached at {:03X}", chip8.pc));
+            }
+            if keyboard.pressed(hotkey(Action::Workspace)) {
+                workspace = workspace.next();
+                panels = Panels::from(workspace).hide(&options.hidden);
+                log.push(format!("workspace {}", workspace.name()));
+            }
+            if rewind.scrubbing() {
+                // Holding an arrow scrubs through the history at normal speed
+                if keyboard.held(hotkey(Action::ScrubBack)) {
+                    rewind.scrub(&mut chip8, -1)?;
+                }
+                if keyboard.held(hotkey(Action::ScrubForward)) {
+                    rewind.scrub(&mut chip8, 1)?;
+                }
+                if keyboard.pressed(hotkey(Action::Resume)) {
+                    rewind.resume();
+                    halted = None;
+                }
+                if keyboard.pressed(hotkey(Action::Cancel)) {
+                    rewind.cancel(&mut chip8)?;
+                }
+            } else if keyboard.pressed(hotkey(Action::Rewind)) {
+                rewind.start(&chip8);
+            }
+            if keyboard.pressed(hotkey(Action::Macro)) {
+                log.push(match keyboard.shift() {
+                    true => keypad_macro.record(&rom_hash),
+                    false => keypad_macro.play(),
+                });
+            }
+            if !paused && !rewind.scrubbing() && keypad_macro.frame(&mut keys) {
+                log.push(keypad_macro.record(&rom_hash));
+            }
+            chip8.set_keys(keys);
+
+            for key in telnet.iter().flat_map(TelnetServer::pressed_keys) {
+                held_keys[key] = 5;
+            }
+
+            for request in controls.pending() {
+                let reply = match request.command {
+                    Command::Pause => {
+                        paused = true;
+                        String::new()
+                    }
+                    Command::Resume => {
+                        paused = false;
+                        halted = None;
+                        String::new()
+                    }
+                    Command::Step(count) => {
+                        match debugger.run(
+                            &mut chip8,
+                            count as usize,
+                            &Cycles::FLAT,
+                            trace.as_mut(),
+                        ) {
+                            Some(Stop::Halted(error)) => {
+                                let reply = format!("halted: {}", error);
+                                halted = Some(error);
+                                reply
+                            }
+                            Some(stop) => format!("{}, pc {:03X}", stop, chip8.pc),
+                            None => format!(
+                                "pc {:03X}, next {}",
+                                chip8.pc,
+                                explain::explain(&chip8, options.syntax)
+                            ),
                         }
-                    } // KYR
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x0,
-                        n3: 0x7,
-                        a: _,
-                        v: _,
-                    } => chip8.v[x as usize] = chip8.delay, // DLX
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x0,
-                        n3: 0xA,
-                        a: _,
-                        v: _,
-                    } => {
-                        chip8.pc -= 2;
-                        'char: for k in 0x0..=0xF {
-                            if last_keys[k] && (last_keys[k] ^ keys[k]) {
-                                chip8.v[x as usize] = k as u8;
-                                chip8.pc += 2;
-                                break 'char;
+                    }
+                    Command::Poke(addr, value) => {
+                        let len = chip8.memory.len();
+                        chip8.memory[addr as usize % len] = value;
+                        String::new()
+                    }
+                    Command::PressKey(key, frames) => {
+                        held_keys[key] = frames;
+                        String::new()
+                    }
+                    Command::Screenshot => control::screenshot(&chip8),
+                    Command::Registers => emit::state_json(&chip8),
+                    Command::Copy => {
+                        let report = emit::report_json(&chip8);
+                        stdout.execute(CopyToClipboard(&report, multiplexer))?;
+                        log.push("machine state copied to the clipboard".into());
+                        report
+                    }
+                    Command::Print(ref expr) => match expr.eval(&chip8) {
+                        Ok(value) => expr::format(value),
+                        Err(e) => format!("can't print: {}", e),
+                    },
+                    Command::Break(addr, ref condition) => {
+                        debugger.breakpoints.insert(addr);
+                        match condition {
+                            Some(condition) => debugger.conditions.insert(addr, condition.clone()),
+                            None => debugger.conditions.remove(&addr),
+                        };
+                        save_debugger(&debugger, &rom_hash)
+                    }
+                    Command::Unbreak(addr) => {
+                        debugger.breakpoints.remove(&addr);
+                        debugger.conditions.remove(&addr);
+                        save_debugger(&debugger, &rom_hash)
+                    }
+                    Command::Watch(addr) => {
+                        debugger.watchpoints.insert(addr);
+                        save_debugger(&debugger, &rom_hash)
+                    }
+                    Command::Unwatch(addr) => {
+                        debugger.watchpoints.remove(&addr);
+                        save_debugger(&debugger, &rom_hash)
+                    }
+                    Command::BreakDraw(region) => {
+                        debugger.draw_regions.insert(region);
+                        save_debugger(&debugger, &rom_hash)
+                    }
+                    Command::UnbreakDraw(region) => {
+                        debugger.draw_regions.remove(&region);
+                        save_debugger(&debugger, &rom_hash)
+                    }
+                    Command::Breakpoints => debugger.list(),
+                    Command::Label(addr, ref name) => {
+                        set_text(&mut debugger.labels, addr, name);
+                        save_debugger(&debugger, &rom_hash)
+                    }
+                    Command::Note(addr, ref text) => {
+                        set_text(&mut debugger.notes, addr, text);
+                        save_debugger(&debugger, &rom_hash)
+                    }
+                    Command::Export(ref path) => match debugger.export(&chip8, &rom_hash, path) {
+                        Ok(()) => String::new(),
+                        Err(e) => format!("not exported: {}", e),
+                    },
+                    Command::Import(ref path) => {
+                        match Debugger::import(&mut chip8, &rom_hash, path) {
+                            Ok(imported) => {
+                                debugger = imported;
+                                debugger.quirk_check = quirk_check(&options);
+                                halted = None;
+                                save_debugger(&debugger, &rom_hash)
                             }
+                            Err(e) => format!("not imported: {}", e),
                         }
-                    } // BKY
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x1,
-                        n3: 0x5,
-                        a: _,
-                        v: _,
-                    } => chip8.delay = chip8.v[x as usize], // DYS
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x1,
-                        n3: 0x8,
-                        a: _,
-                        v: _,
-                    } => chip8.sound = chip8.v[x as usize], // SND
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x1,
-                        n3: 0xE,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let value = chip8.i + chip8.v[x] as u16;
-                        chip8.v[0xF] = (value & 0xF000 > 0) as u8;
-                        chip8.i = value;
-                    } // ADI
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x2,
-                        n3: 0x9,
-                        a: _,
-                        v: _,
-                    } => chip8.i = font_addr[chip8.v[x as usize] as usize & 0x0F], // RCH
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x3,
-                        n3: 0x3,
-                        a: _,
-                        v: _,
+                    }
+                    Command::Search {
+                        ref pattern,
+                        narrow,
                     } => {
-                        let x = x as usize;
-                        let i = chip8.i as usize;
-                        chip8.memory[i + 0] = chip8.v[x] / 100;
-                        chip8.memory[i + 1] = (chip8.v[x] % 100) / 10;
-                        chip8.memory[i + 2] = chip8.v[x] % 10;
-                    } // BCD
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x5,
-                        n3: 0x5,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let i = chip8.i as usize;
-                        chip8.memory[i..=i + x].copy_from_slice(&chip8.v[0..=x])
-                    } // RST
-                    Opcode {
-                        n0: 0xF,
-                        n1: x,
-                        n2: 0x6,
-                        n3: 0x5,
-                        a: _,
-                        v: _,
-                    } => {
-                        let x = x as usize;
-                        let i = chip8.i as usize;
-                        chip8.v[0..=x].copy_from_slice(&chip8.memory[i..=i + x])
-                    } // RLD
+                        let found = debugger.search(&chip8.memory, pattern, narrow);
+                        let shown: Vec<String> = found
+                            .iter()
+                            .take(32)
+                            .map(|a| format!("{:03X}", a))
+                            .collect();
+                        format!("{} found: {}", found.len(), shown.join(" "))
+                    }
+                };
+                request.reply(reply);
+            }
 
-                    _ => panic!("Unknown operand! {0:?}", op),
+            let running = !paused && !rewind.scrubbing();
+            // Above 60Hz some frames are due no ticks and only redraw
+            let ticks = if running { pacing.ticks() } else { 0 };
+            let beeping = chip8.sound > 0 && running;
+            beeper.set(beeping);
+            if let (false, Some(latency)) = (latency_reported, beeper.latency()) {
+                log.push(format!("audio latency {} ms", latency.as_millis()));
+                latency_reported = true;
+            }
+            for error in beeper.errors() {
+                log.push(format!("audio output failed: {}", error));
+            }
+            if ticks > 0 {
+                sounds.record(chip8.sound);
+                chip8.tick_timers();
+            }
+            if let Some(emitter) = emitter.as_mut() {
+                emitter.emit(&chip8)?;
+            }
+            for peripheral in &mut chip8.peripherals {
+                let output = peripheral.output();
+                if let Some(file) = console_log.as_mut() {
+                    file.write_all(&output)?;
+                }
+                console.write(&output);
+            }
+            let rendering = Instant::now();
+            if titled != Some(current) || title_refreshed.elapsed() >= STATUS_REFRESH {
+                let fps = 1.0 / frame_time.as_secs_f32();
+                let title = match options.paths.get(current) {
+                    Some(path) => format!("fe_o8 — {} ({:.0}fps)", rom_title(Some(path)), fps),
+                    None => format!("fe_o8 ({:.0}fps)", fps),
                 };
+                stdout.execute(SetTitle(title))?;
+                titled = Some(current);
+                title_refreshed = Instant::now();
             }
+            // Attract mode names each game as it starts
+            let title = (options.attract.is_some() && started.elapsed() < TITLE_TIME)
+                .then(|| rom_title(options.paths.get(current)));
+            let dirty_rows = chip8.take_dirty_rows();
+            // Kiosk mode starts a stopped game again instead of saying so
+            let stuck = match paused || halted.is_some() || options.kiosk {
+                true => {
+                    watchdog.reset();
+                    None
+                }
+                false => watchdog.check(&chip8, dirty_rows != 0),
+            };
+            // What's asked for on the command line, then what the ROM came with
+            let palette = (options.palette.as_ref())
+                .or(rom_palette.as_ref())
+                .unwrap_or(&default_palette);
+            let palette = match inverted {
+                true => palette.inverted(),
+                false => palette.clone(),
+            };
+            panel_data.update(&chip8, &debugger, &panels);
+            let view = View {
+                chip8: &chip8,
+                debugger: &debugger,
+                log: &log,
+                console: &console,
+                inputs: &inputs,
+                sounds: &sounds,
+                data: &panel_data,
+                palette: &palette,
+                panels: &panels,
+                fullscreen,
+                monochrome: options.monochrome,
+                background,
+                legend: legend.then(|| keymap.as_ref().unwrap_or(&KEYPAD_LABELS)),
+                title: title.as_deref(),
+                frame_time,
+                refresh: pacing.refresh(),
+                instructions_per_frame,
+                paused,
+                beeping,
+                // Kiosk mode starts it again instead
+                halted: halted.as_ref().filter(|_| !options.kiosk),
+                profile_label: &profile_label,
+                syntax: options.syntax,
+                memory_map: options.memory_map,
+                rom_size,
+                rewind: rewind.position(),
+                speedrun: speedrun.as_ref(),
+                stuck,
+                hotkeys: &hotkeys,
+            };
+            // Idle games leave the screen alone for many frames at a time
+            let (width, height) = terminal::size()?;
+            renderer.resize(Rect::new(0, 0, width, height));
+            let fingerprint = (view.fingerprint(), width, height);
+            let joined = telnet.as_ref().is_some_and(TelnetServer::joined);
+            if joined
+                || dirty_rows != 0
+                || renderer.pending()
+                || drawn != Some(fingerprint)
+                || last_drawn.elapsed() >= STATUS_REFRESH
+            {
+                // Terminals that know DEC mode 2026 show the frame all at
+                // once instead of as it arrives; the rest ignore it, all but
+                // older consoles, which would print it
+                if legacy.is_none() {
+                    renderer.output().queue(BeginSynchronizedUpdate)?;
+                }
+                if joined {
+                    // Newcomers need the whole screen, not just what changed
+                    renderer.clear()?;
+                }
+                renderer.draw(|area, buf| view.render(area, buf))?;
+                if legacy.is_none() {
+                    renderer.output().queue(EndSynchronizedUpdate)?;
+                }
+                drawn = Some(fingerprint);
+                last_drawn = Instant::now();
+                debugger.counters.frames += 1;
+                let output = renderer.output();
+                stdout.write_all(output)?;
+                stdout.flush()?;
+                if let Some(telnet) = &telnet {
+                    telnet.broadcast(output);
+                }
+                output.clear();
+            }
+            timing.render = rendering.elapsed();
+
+            for tick in 0..ticks {
+                // The first tick's timers counted down before drawing
+                if tick > 0 {
+                    sounds.record(chip8.sound);
+                    chip8.tick_timers();
+                }
+                if paused {
+                    break;
+                }
+                if halted.is_some() {
+                    continue;
+                }
+                rewind.record(&chip8);
+                inputs.record(chip8.keys);
+                let emulating = Instant::now();
+                let stop = debugger.run(
+                    &mut chip8,
+                    instructions_per_frame,
+                    &options.cycles,
+                    trace.as_mut(),
+                );
+                timing.emulate += emulating.elapsed();
+                debugger.calls.end_frame();
+                if let Some(speedrun) = &mut speedrun {
+                    speedrun.tick(&chip8);
+                }
+                if let Some(video) = &mut video {
+                    video.record(&chip8, &palette)?;
+                }
+                match stop {
+                    Some(Stop::Halted(error)) => {
+                        log.push(format!("halted: {}", error));
+                        halted = Some(error);
+                    }
+                    Some(stop) => {
+                        paused = true;
+                        log.push(stop.to_string());
+                    }
+                    None => {}
+                }
+            }
+            if options.timing_report.is_some() {
+                timing_report.push(timing);
+            }
+            if let Some(check) = debugger.quirk_check.as_mut() {
+                for change in check.changes() {
+                    log.push(format!("quirks: {}", change));
+                    if !profile_label.ends_with('*') {
+                        profile_label.push('*');
+                    }
+                }
+            }
+            // A program that jumps to itself won't do anything else either
+            let blocked = (chip8.waiting_for_key() || chip8.jumps_to_itself())
+                && chip8.delay == 0
+                && chip8.sound == 0;
+            idle = (paused || halted.is_some() || blocked) && !rewind.scrubbing();
         }
     }
-    terminal::disable_raw_mode()?;
-    stdout.execute(terminal::LeaveAlternateScreen)?;
+    drop(raw_terminal);
+    if let Some(video) = video {
+        video.finish()?;
+    }
+    if let Some(path) = &options.timing_report {
+        timing_report.write(path)?;
+    }
+    if options.autosave {
+        savestate::save(&chip8, &rom_hash, &savestate::autosave_path(&rom_hash))?;
+    }
     Ok(())
 }
@@ -0,0 +1,154 @@
+use midir::{MidiInput, MidiInputConnection};
+use std::{
+    error::Error,
+    result::Result,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc,
+    },
+};
+
+/// The keypad's keys, row by row from the top, as pads are laid out.
+const LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Which keypad key each MIDI note presses, if any.
+#[derive(Clone)]
+pub struct MidiMap {
+    keys: [Option<usize>; 128],
+}
+
+impl MidiMap {
+    /// Parses a preset name (`pads`, `launchpad`) or comma separated
+    /// `NOTE=KEY` pairs, the note in decimal and the key in hex.
+    pub fn parse(spec: &str) -> Result<MidiMap, String> {
+        match spec {
+            "pads" => return Ok(MidiMap::default()),
+            // Programmer mode numbers the grid by row and column from the
+            // bottom left, 11 to 88; the keypad is its top left corner
+            "launchpad" => return Ok(MidiMap::grid(|row, column| (8 - row) * 10 + column + 1)),
+            _ => (),
+        }
+        let mut map = MidiMap { keys: [None; 128] };
+        for pair in spec.split(',') {
+            let (note, key) = pair
+                .split_once('=')
+                .ok_or(format!("expected NOTE=KEY, found '{}'", pair))?;
+            let note = (note.trim().parse::<usize>().ok())
+                .filter(|note| *note < map.keys.len())
+                .ok_or(format!("invalid MIDI note '{}'", note))?;
+            let key = (usize::from_str_radix(key.trim(), 16).ok())
+                .filter(|key| *key <= 0xF)
+                .ok_or(format!("no key '{}'", key))?;
+            map.keys[note] = Some(key);
+        }
+        Ok(map)
+    }
+
+    /// The keypad laid over a grid of pads, with the note of the pad at
+    /// each row and column, counted from the top left.
+    fn grid(note: impl Fn(usize, usize) -> usize) -> MidiMap {
+        let mut map = MidiMap { keys: [None; 128] };
+        for (row, keys) in LAYOUT.iter().enumerate() {
+            for (column, &key) in keys.iter().enumerate() {
+                map.keys[note(row, column)] = Some(key);
+            }
+        }
+        map
+    }
+}
+
+impl Default for MidiMap {
+    /// Drum pad controllers' 4x4 banks, notes 36 to 51 from the bottom left.
+    fn default() -> MidiMap {
+        MidiMap::grid(|row, column| 36 + (3 - row) * 4 + column)
+    }
+}
+
+/// Keys held down on a MIDI controller, read alongside the keyboard.
+pub struct MidiKeypad {
+    _connection: MidiInputConnection<()>,
+    /// A bit for each key whose note is on.
+    held: Arc<AtomicU16>,
+    /// A bit for each key struck since the keys were last read, so that a
+    /// hit let go of within a frame still presses the key.
+    struck: Arc<AtomicU16>,
+}
+
+impl MidiKeypad {
+    /// Listens on the first input port whose name contains `name`.
+    pub fn open(name: &str, map: &MidiMap) -> Result<MidiKeypad, Box<dyn Error>> {
+        let input = input()?;
+        let port = (input.ports().into_iter())
+            .find(|port| {
+                input
+                    .port_name(port)
+                    .is_ok_and(|found| found.contains(name))
+            })
+            .ok_or(format!(
+                "no MIDI input named {} (--list-midi shows them)",
+                name
+            ))?;
+        let held = Arc::new(AtomicU16::new(0));
+        let struck = Arc::new(AtomicU16::new(0));
+        let (holding, striking, map) = (held.clone(), struck.clone(), map.clone());
+        let connection = input
+            .connect(
+                &port,
+                "fe_o8 keypad",
+                move |_, message, _| {
+                    let &[status, note, velocity] = message else {
+                        return;
+                    };
+                    let Some(key) = map.keys.get(note as usize).copied().flatten() else {
+                        return;
+                    };
+                    // A note on with no velocity is how most controllers send a note off
+                    match (status & 0xF0, velocity) {
+                        (0x90, 1..) => {
+                            holding.fetch_or(1 << key, Ordering::Relaxed);
+                            striking.fetch_or(1 << key, Ordering::Relaxed);
+                        }
+                        (0x80 | 0x90, _) => {
+                            holding.fetch_and(!(1 << key), Ordering::Relaxed);
+                        }
+                        _ => (),
+                    }
+                },
+                (),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(MidiKeypad {
+            _connection: connection,
+            held,
+            struck,
+        })
+    }
+
+    /// The keys held now or struck since last time.
+    pub fn keys(&self) -> [bool; 16] {
+        let bits = self.held.load(Ordering::Relaxed) | self.struck.swap(0, Ordering::Relaxed);
+        std::array::from_fn(|key| bits & (1 << key) != 0)
+    }
+}
+
+/// The system's MIDI inputs, ALSA's sequencer on Linux.
+fn input() -> Result<MidiInput, String> {
+    MidiInput::new("fe_o8").map_err(|e| format!("can't use MIDI: {}", e))
+}
+
+/// The names of the MIDI inputs, to pick one with `--midi`.
+pub fn ports() -> Result<Vec<String>, Box<dyn Error>> {
+    let input = input()?;
+    let names: Vec<String> = (input.ports().iter())
+        .filter_map(|port| input.port_name(port).ok())
+        .collect();
+    if names.is_empty() {
+        return Err("no MIDI inputs".into());
+    }
+    Ok(names)
+}
@@ -0,0 +1,62 @@
+use std::env;
+
+/// Wrapped escape sequences screen passes on at most this much at a time.
+const SCREEN_CHUNK: usize = 76;
+
+/// A terminal multiplexer fe_o8 is running inside, between it and the real
+/// terminal, which understands less than most terminals do.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Multiplexer {
+    Tmux,
+    /// GNU screen.
+    Screen,
+}
+
+impl Multiplexer {
+    pub const NAMES: [&'static str; 3] = ["tmux", "screen", "none"];
+
+    /// The one named, `none` for none, as for `--multiplexer`.
+    pub fn parse(name: &str) -> Option<Option<Multiplexer>> {
+        match name {
+            "tmux" => Some(Some(Multiplexer::Tmux)),
+            "screen" => Some(Some(Multiplexer::Screen)),
+            "none" => Some(None),
+            _ => None,
+        }
+    }
+
+    /// From what each sets in the environment of what runs inside it.
+    pub fn detect() -> Option<Multiplexer> {
+        if env::var_os("TMUX").is_some() {
+            Some(Multiplexer::Tmux)
+        } else if env::var_os("STY").is_some() {
+            Some(Multiplexer::Screen)
+        } else {
+            None
+        }
+    }
+
+    /// Whether RGB colors get through to the terminal. Neither passes them
+    /// on unless set up to, and COLORTERM is how that's usually told.
+    pub fn truecolor() -> bool {
+        matches!(env::var("COLORTERM").as_deref(), Ok("truecolor" | "24bit"))
+    }
+
+    /// An escape sequence, which is ASCII, wrapped for the multiplexer to
+    /// hand on to the terminal untouched, for those it would otherwise drop.
+    /// tmux only does with `allow-passthrough` on.
+    pub fn passthrough(self, sequence: &str) -> String {
+        match self {
+            Multiplexer::Tmux => {
+                format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+            }
+            Multiplexer::Screen => {
+                let chunks: Vec<&str> = (0..sequence.len())
+                    .step_by(SCREEN_CHUNK)
+                    .map(|start| &sequence[start..(start + SCREEN_CHUNK).min(sequence.len())])
+                    .collect();
+                format!("\x1bP{}\x1b\\", chunks.join("\x1b\\\x1bP"))
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{hextext, quirks::Quirks, ui::Palette};
+use serde::Deserialize;
+use std::{error::Error, fs, result::Result};
+
+const USAGE: &str = "usage: fe_o8 cart FILE.gif";
+
+/// Whether a ROM file is a GIF, which is what Octo saves cartridges as.
+pub fn is_cart(file: &[u8]) -> bool {
+    file.starts_with(b"GIF8")
+}
+
+/// The settings Octo saves with a program, those fe_o8 has a use for.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CartOptions {
+    /// Instructions per frame.
+    pub tickrate: Option<usize>,
+    pub shift_quirks: Option<bool>,
+    pub load_store_quirks: Option<bool>,
+    pub jump_quirks: Option<bool>,
+    pub logic_quirks: Option<bool>,
+    pub background_color: Option<String>,
+    pub fill_color: Option<String>,
+    pub fill_color2: Option<String>,
+    pub blend_color: Option<String>,
+}
+
+/// An OctoCart: a GIF with a label on it and, in the low two bits of each
+/// pixel, a four byte length and that much JSON holding the program's source
+/// and Octo's options for it.
+#[derive(Deserialize)]
+pub struct Cart {
+    pub program: String,
+    #[serde(default)]
+    pub options: CartOptions,
+}
+
+impl Cart {
+    pub fn decode(file: &[u8]) -> Result<Cart, Box<dyn Error>> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let not_cart = |error| format!("not an Octo cartridge: {}", error);
+        let mut decoder = options.read_info(file).map_err(not_cart)?;
+        let mut bytes = vec![];
+        let (mut byte, mut bits) = (0u8, 0);
+        while let Some(frame) = decoder.read_next_frame().map_err(not_cart)? {
+            for pixel in frame.buffer.iter() {
+                byte = byte << 2 | pixel & 3;
+                bits += 2;
+                if bits == 8 {
+                    bytes.push(byte);
+                    bits = 0;
+                }
+            }
+        }
+        let length = match bytes.get(..4) {
+            Some(&[a, b, c, d]) => u32::from_be_bytes([a, b, c, d]) as usize,
+            _ => return Err("not an Octo cartridge: no data in the image".into()),
+        };
+        let json = bytes
+            .get(4..4 + length)
+            .ok_or("not an Octo cartridge: data shorter than it says")?;
+        Ok(serde_json::from_slice(json)?)
+    }
+
+    /// The program's bytes, if its source is nothing but byte literals, as
+    /// carts made from ROMs are. Anything more needs Octo to assemble it.
+    pub fn rom(&self) -> Result<Vec<u8>, String> {
+        let source = self.program.trim_start();
+        let source = source
+            .strip_prefix(':')
+            .and_then(|rest| rest.trim_start().strip_prefix("main"))
+            .unwrap_or(source);
+        hextext::parse(source).map_err(|_| {
+            "this cartridge holds Octo source, which fe_o8 can't assemble; \
+             fe_o8 cart FILE.gif prints it, with its options as flags, for Octo"
+                .into()
+        })
+    }
+
+    /// Sets the quirks the cart asks for, leaving the rest as they are.
+    pub fn apply(&self, quirks: &mut Quirks) {
+        let options = &self.options;
+        // Octo never sets VF on FX1E
+        quirks.add_i_sets_vf = false;
+        if let Some(on) = options.shift_quirks {
+            quirks.shift_vx = on;
+        }
+        if let Some(on) = options.load_store_quirks {
+            quirks.load_store_increments_i = !on;
+        }
+        if let Some(on) = options.jump_quirks {
+            quirks.jump_vx = on;
+        }
+        if let Some(on) = options.logic_quirks {
+            quirks.logic_resets_vf = on;
+        }
+    }
+
+    /// The cart's colors as a `--palette` value, if it has all four.
+    fn palette_spec(&self) -> Option<String> {
+        let options = &self.options;
+        let colors = [
+            options.background_color.as_deref()?,
+            options.fill_color.as_deref()?,
+            options.fill_color2.as_deref()?,
+            options.blend_color.as_deref()?,
+        ];
+        Some(colors.join(","))
+    }
+
+    pub fn palette(&self) -> Option<Palette> {
+        Palette::parse(&self.palette_spec()?).ok()
+    }
+
+    /// The fe_o8 flags that run the program as the cart has it set up.
+    fn flags(&self) -> Vec<String> {
+        let options = &self.options;
+        let quirks = [
+            ("shift-vx", options.shift_quirks),
+            ("load-store-i", options.load_store_quirks.map(|on| !on)),
+            ("jump-vx", options.jump_quirks),
+            ("vf-reset", options.logic_quirks),
+        ];
+        let mut flags = vec!["--quirk fx1e-vf=off".to_string()];
+        for (name, on) in quirks {
+            if let Some(on) = on {
+                flags.push(format!(
+                    "--quirk {}={}",
+                    name,
+                    if on { "on" } else { "off" }
+                ));
+            }
+        }
+        if let Some(palette) = self.palette_spec() {
+            flags.push(format!("--palette {}", palette));
+        }
+        flags
+    }
+}
+
+/// Prints a cartridge's source, headed by comments giving its options as
+/// flags, ready for Octo to assemble.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let path = args.next().ok_or(USAGE)?;
+    let cart = Cart::decode(&fs::read(&path)?)?;
+    println!("# fe_o8 {}", cart.flags().join(" "));
+    if let Some(tickrate) = cart.options.tickrate {
+        println!("# {} instructions a frame", tickrate);
+    }
+    print!("{}", cart.program);
+    Ok(())
+}
@@ -0,0 +1,471 @@
+use crate::{
+    background::Background,
+    chip8::{ADDR_PROGRAM_END, ADDR_START_PROGRAM, MAX_MEMORY_SIZE},
+    cycles::Cycles,
+    input::{self, Hotkeys, KEY_ESC, KEY_F1, KEY_F12},
+    midi::MidiMap,
+    multiplexer::Multiplexer,
+    peripheral::Spec,
+    quirks::Profile,
+    storage::config_dir,
+    syntax::Syntax,
+    ui::{MemoryMap, Palette, Panels},
+    TIMER_RATE,
+};
+use std::{
+    env,
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+    result::Result,
+    time::Duration,
+};
+
+const USAGE: &str = "usage: fe_o8 [--palette octo|#RRGGBB,...] [--watch] [--emit-state PATH] [--control SOCKET] [--http ADDR:PORT] [--telnet ADDR:PORT] [--autosave] [--fullscreen] [--hide PANEL,...] [--memory-map BYTES[xROWS]] [--bind ACTION=KEY,...] [--attract SECONDS] [--kiosk] [--kiosk-exit KEY+KEY...] [--stall SECONDS] [--cycles CLASS=N,...] [--syntax fe|chipper|octo] [--trace PATH] [--audio-buffer FRAMES] [--audio-device NAME] [--list-audio] [--midi NAME] [--midi-map pads|launchpad|NOTE=KEY,...] [--list-midi] [--timing-report PATH] [--peripheral NAME[@ADDR]] [--console-log PATH] [--quirks auto|vip|schip] [--quirk NAME=on|off] [--memory 4K|64K|BYTES] [--load-addr ADDR] [--entry ADDR] [--stack-limit N|unlimited] [--speedrun] [--split-on ADDR] [--record-video PATH] [--speed N] [--refresh HZ] [--legacy-console] [--multiplexer tmux|screen|none] [--monochrome] [--background auto|dark|light] [--interlace] [FILE|DIRECTORY...] [FILE@ADDR...]";
+
+/// The fastest --refresh goes, beyond what any screen shows.
+const MAX_REFRESH: u32 = 240;
+
+/// Command line options for running a ROM interactively.
+pub struct Options {
+    /// ROMs to cycle through, with directories expanded to the files in them.
+    /// Empty runs the built in ROM, unless there are segments.
+    pub paths: Vec<PathBuf>,
+    /// Files written into memory at their addresses, in order, over each
+    /// ROM as it loads, such as graphics kept apart from the code.
+    pub segments: Vec<(PathBuf, u16)>,
+    /// Colors asked for; without, a cartridge's or the terminal's own.
+    pub palette: Option<Palette>,
+    pub watch: bool,
+    pub emit_state: Option<PathBuf>,
+    #[cfg(unix)]
+    pub control: Option<PathBuf>,
+    pub http: Option<String>,
+    pub telnet: Option<String>,
+    /// Save the machine on exit and offer to resume it next time.
+    pub autosave: bool,
+    /// Start with only the game on screen.
+    pub fullscreen: bool,
+    /// Panels to leave out of every workspace.
+    pub hidden: Vec<String>,
+    pub memory_map: MemoryMap,
+    pub hotkeys: Hotkeys,
+    /// Move on to the next ROM after this long, or sooner if the game ends.
+    pub attract: Option<Duration>,
+    /// Run unattended: just the game, no emulator keys but the exit chord,
+    /// and a restart whenever it stops.
+    pub kiosk: bool,
+    /// The keys that quit together in kiosk mode.
+    pub kiosk_exit: Vec<u16>,
+    /// Say the program appears stuck once nothing has been drawn for this long.
+    pub stall: Option<Duration>,
+    /// What each class of instruction costs of the frame's budget.
+    pub cycles: Cycles,
+    /// How instructions are written in the disassembly and trace.
+    pub syntax: Syntax,
+    /// Where to log every instruction executed.
+    pub trace: Option<PathBuf>,
+    /// Frames of audio per buffer; smaller beeps sooner but may crackle.
+    pub audio_buffer: Option<u32>,
+    /// The output device to beep on, instead of the system's default.
+    pub audio_device: Option<String>,
+    /// Print the output devices and exit.
+    pub list_audio: bool,
+    /// Part of the name of a MIDI input to press keys from.
+    pub midi: Option<String>,
+    pub midi_map: MidiMap,
+    /// Print the MIDI inputs and exit.
+    pub list_midi: bool,
+    /// Where to write each frame's timing as CSV on exit.
+    pub timing_report: Option<PathBuf>,
+    /// Devices to map into memory.
+    pub peripherals: Vec<Spec>,
+    /// Where to copy what peripherals write to the console.
+    pub console_log: Option<PathBuf>,
+    pub profile: Option<Profile>,
+    /// Guess the quirks profile from the ROM.
+    pub detect_quirks: bool,
+    /// Individual quirks to change after the profile is applied.
+    pub quirk_overrides: Vec<(String, bool)>,
+    /// Bytes of memory, 4K unless more is asked for (XO-CHIP uses 64K).
+    pub memory_size: usize,
+    /// Where the ROM goes in memory.
+    pub load_address: u16,
+    /// Where to start running, if not where the ROM was loaded.
+    pub entry: Option<u16>,
+    /// Overrides the profile's limit on nested calls; `Some(None)` lifts it.
+    pub stack_limit: Option<Option<usize>>,
+    /// Show a speedrun timer over the game.
+    pub speedrun: bool,
+    /// Split the speedrun timer whenever the byte here changes.
+    pub split_on: Option<u16>,
+    /// Where to encode a video of the display and beeper, with ffmpeg.
+    pub record_video: Option<PathBuf>,
+    /// Instructions per frame, over what the ROM asks for.
+    pub speed: Option<usize>,
+    /// Frames drawn a second. The game's timers and instructions keep to
+    /// 60Hz however often it's drawn.
+    pub refresh: u32,
+    /// Draw as for an older Windows console even where escape sequences work.
+    pub legacy_console: bool,
+    /// Overrides the tmux or screen found in the environment; `Some(None)`
+    /// draws as if outside one.
+    pub multiplexer: Option<Option<Multiplexer>>,
+    /// Draw with no colors or text attributes, showing what they would with
+    /// characters instead. On by default when `NO_COLOR` is set.
+    pub monochrome: bool,
+    /// Overrides the background the terminal says it has; `None` asks it.
+    pub background: Option<Background>,
+    /// Send the even rows one frame and the odd ones the next.
+    pub interlace: bool,
+}
+
+impl Options {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, Box<dyn Error>> {
+        let mut paths = vec![];
+        let mut segments = vec![];
+        let mut palette = None;
+        let mut watch = false;
+        let mut emit_state = None;
+        #[cfg(unix)]
+        let mut control = None;
+        let mut http = None;
+        let mut telnet = None;
+        let mut autosave = false;
+        let mut fullscreen = false;
+        let mut hidden = vec![];
+        let mut memory_map = MemoryMap::default();
+        let mut hotkeys = Hotkeys::default();
+        let mut attract = None;
+        let mut kiosk = false;
+        let mut kiosk_exit = vec![KEY_ESC, KEY_F1, KEY_F12];
+        let mut stall = None;
+        let mut cycles = Cycles::default();
+        let mut syntax = Syntax::default();
+        let mut trace = None;
+        let mut audio_buffer = None;
+        let mut audio_device = None;
+        let mut list_audio = false;
+        let mut midi = None;
+        let mut midi_map = MidiMap::default();
+        let mut list_midi = false;
+        let mut timing_report = None;
+        let mut peripherals = vec![];
+        let mut console_log = None;
+        let mut profile = None;
+        let mut detect_quirks = false;
+        let mut quirk_overrides = vec![];
+        let mut memory_size = ADDR_PROGRAM_END as usize;
+        let mut load_address = ADDR_START_PROGRAM;
+        let mut entry = None;
+        let mut stack_limit = None;
+        let mut speedrun = false;
+        let mut split_on = None;
+        let mut record_video = None;
+        let mut speed = None;
+        let mut refresh = TIMER_RATE;
+        let mut legacy_console = false;
+        let mut multiplexer = None;
+        let mut background = None;
+        let mut interlace = false;
+        let mut monochrome = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--palette" => palette = Some(Palette::parse(&value()?)?),
+                "--watch" => watch = true,
+                "--emit-state" => emit_state = Some(value()?.into()),
+                #[cfg(unix)]
+                "--control" => control = Some(value()?.into()),
+                "--http" => http = Some(value()?),
+                "--telnet" => telnet = Some(value()?),
+                "--autosave" => autosave = true,
+                "--fullscreen" => fullscreen = true,
+                "--hide" => {
+                    for name in value()?.split(',') {
+                        if !Panels::NAMES.contains(&name) {
+                            return Err(format!(
+                                "unknown panel {}, expected one of {}",
+                                name,
+                                Panels::NAMES.join(", ")
+                            )
+                            .into());
+                        }
+                        hidden.push(name.to_string());
+                    }
+                }
+                "--memory-map" => memory_map = parse_memory_map(&value()?)?,
+                "--bind" => hotkeys.bind(&value()?)?,
+                "--attract" => attract = Some(Duration::from_secs(value()?.parse()?)),
+                "--kiosk" => kiosk = true,
+                "--kiosk-exit" => kiosk_exit = input::parse_chord(&value()?)?,
+                "--stall" => stall = Some(Duration::from_secs(value()?.parse()?)),
+                "--cycles" => cycles.parse(&value()?)?,
+                "--syntax" => syntax = parse_syntax(&value()?)?,
+                "--trace" => trace = Some(value()?.into()),
+                "--audio-buffer" => audio_buffer = Some(value()?.parse()?),
+                "--audio-device" => audio_device = Some(value()?),
+                "--list-audio" => list_audio = true,
+                "--midi" => midi = Some(value()?),
+                "--midi-map" => midi_map = MidiMap::parse(&value()?)?,
+                "--list-midi" => list_midi = true,
+                "--timing-report" => timing_report = Some(value()?.into()),
+                "--peripheral" => peripherals.push(Spec::parse(&value()?)?),
+                "--console-log" => console_log = Some(value()?.into()),
+                "--memory" => memory_size = parse_memory_size(&value()?)?,
+                "--load-addr" => load_address = parse_address(&value()?)?,
+                "--entry" => entry = Some(parse_address(&value()?)?),
+                "--stack-limit" => match value()?.as_str() {
+                    "unlimited" => stack_limit = Some(None),
+                    depth => stack_limit = Some(Some(depth.parse()?)),
+                },
+                "--quirk" => {
+                    let setting = value()?;
+                    let (name, on) = match setting.split_once('=') {
+                        Some((name, "on")) => (name, true),
+                        Some((name, "off")) => (name, false),
+                        _ => {
+                            return Err(
+                                format!("expected --quirk NAME=on|off, got {}", setting).into()
+                            )
+                        }
+                    };
+                    quirk_overrides.push((name.to_string(), on));
+                }
+                "--speedrun" => speedrun = true,
+                "--split-on" => {
+                    split_on = Some(parse_address(&value()?)?);
+                    speedrun = true;
+                }
+                "--record-video" => record_video = Some(value()?.into()),
+                "--speed" => speed = Some(value()?.parse::<usize>()?.max(1)),
+                "--refresh" => {
+                    refresh = value()?.parse()?;
+                    if !(1..=MAX_REFRESH).contains(&refresh) {
+                        return Err(format!("--refresh must be 1 to {}Hz", MAX_REFRESH).into());
+                    }
+                }
+                "--legacy-console" => legacy_console = true,
+                "--monochrome" => monochrome = true,
+                "--interlace" => interlace = true,
+                "--background" => {
+                    let name = value()?;
+                    background = Background::parse(&name).ok_or(format!(
+                        "unknown background {}, expected one of {}",
+                        name,
+                        Background::NAMES.join(", ")
+                    ))?;
+                }
+                "--multiplexer" => {
+                    let name = value()?;
+                    multiplexer = Some(Multiplexer::parse(&name).ok_or(format!(
+                        "unknown multiplexer {}, expected one of {}",
+                        name,
+                        Multiplexer::NAMES.join(", ")
+                    ))?);
+                }
+                "--quirks" => match value()?.as_str() {
+                    "auto" => detect_quirks = true,
+                    name => {
+                        profile = Some(
+                            Profile::parse(name)
+                                .ok_or(format!("unknown quirks profile {}", name))?,
+                        )
+                    }
+                },
+                _ if arg.starts_with("--") => {
+                    return Err(format!("unknown option {}\n{}", arg, USAGE).into())
+                }
+                _ => match parse_segment(&arg)? {
+                    Some(segment) => segments.push(segment),
+                    None => paths.extend(expand(arg.into())?),
+                },
+            }
+        }
+        if split_on.is_some_and(|addr| addr as usize >= memory_size) {
+            return Err(format!(
+                "--split-on is past the end of {} bytes of memory",
+                memory_size
+            )
+            .into());
+        }
+        for (path, addr) in &segments {
+            if *addr as usize >= memory_size {
+                return Err(format!(
+                    "{} is past the end of {} bytes of memory",
+                    path.display(),
+                    memory_size
+                )
+                .into());
+            }
+        }
+        if load_address as usize >= memory_size {
+            return Err(format!(
+                "--load-addr is past the end of {} bytes of memory",
+                memory_size
+            )
+            .into());
+        }
+        // The whole instruction has to be in memory
+        if entry.is_some_and(|addr| addr as usize + 1 >= memory_size) {
+            return Err(
+                format!("--entry is past the end of {} bytes of memory", memory_size).into(),
+            );
+        }
+        Ok(Options {
+            paths,
+            segments,
+            palette,
+            watch,
+            emit_state,
+            #[cfg(unix)]
+            control,
+            http,
+            telnet,
+            autosave,
+            fullscreen,
+            hidden,
+            memory_map,
+            hotkeys,
+            attract,
+            kiosk,
+            kiosk_exit,
+            stall,
+            cycles,
+            syntax,
+            trace,
+            audio_buffer,
+            audio_device,
+            list_audio,
+            midi,
+            midi_map,
+            list_midi,
+            timing_report,
+            peripherals,
+            console_log,
+            profile,
+            detect_quirks,
+            quirk_overrides,
+            memory_size,
+            load_address,
+            entry,
+            stack_limit,
+            speedrun,
+            split_on,
+            record_video,
+            speed,
+            refresh,
+            legacy_console,
+            multiplexer,
+            monochrome,
+            background,
+            interlace,
+        })
+    }
+}
+
+/// The file of options applied to every run, read before the command line.
+pub fn config_path() -> PathBuf {
+    config_dir().join("options")
+}
+
+/// The options in the config file, written as on the command line, or none
+/// if there isn't one. Anything after a `#` is a comment.
+pub fn config_args() -> Result<Vec<String>, Box<dyn Error>> {
+    let path = config_path();
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("{}: {}", path.display(), e).into()),
+    };
+    Ok(text
+        .lines()
+        .flat_map(|line| {
+            line.split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace()
+        })
+        .map(String::from)
+        .collect())
+}
+
+/// `FILE@ADDR`, the address in hex, unless a file is actually named that.
+/// Anything else with an `@` in it is a mistake in one or the other.
+fn parse_segment(arg: &str) -> Result<Option<(PathBuf, u16)>, String> {
+    if Path::new(arg).exists() {
+        return Ok(None);
+    }
+    let Some((path, addr)) = arg.rsplit_once('@') else {
+        return Ok(None);
+    };
+    let addr = parse_address(addr).map_err(|_| {
+        format!(
+            "{} isn't a file, and {} isn't an address to load {} at",
+            arg, addr, path
+        )
+    })?;
+    if !Path::new(path).exists() {
+        return Err(format!("{} isn't a file, and neither is {}", arg, path));
+    }
+    Ok(Some((path.into(), addr)))
+}
+
+/// A directory stands for the files in it, in name order.
+fn expand(path: PathBuf) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !path.is_dir() {
+        return Ok(vec![path]);
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(&path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|file| file.is_file());
+    files.sort();
+    if files.is_empty() {
+        return Err(format!("no ROMs in {}", path.display()).into());
+    }
+    Ok(files)
+}
+
+pub fn parse_syntax(name: &str) -> Result<Syntax, String> {
+    Syntax::parse(name).ok_or(format!(
+        "unknown syntax {}, expected one of {}",
+        name,
+        Syntax::NAMES.join(", ")
+    ))
+}
+
+/// `BYTES` per cell, optionally followed by `xROWS`.
+fn parse_memory_map(text: &str) -> Result<MemoryMap, String> {
+    let invalid = || format!("expected --memory-map BYTES[xROWS], got {}", text);
+    let (cell, rows) = match text.split_once('x') {
+        Some((cell, rows)) => (cell, Some(rows)),
+        None => (text, None),
+    };
+    let number = |n: &str| n.parse().ok().filter(|n| *n > 0).ok_or_else(invalid);
+    Ok(MemoryMap {
+        cell: Some(number(cell)?),
+        rows: rows.map(number).transpose()?,
+    })
+}
+
+fn parse_memory_size(text: &str) -> Result<usize, String> {
+    let size = match text.to_ascii_uppercase().strip_suffix('K') {
+        Some(kilobytes) => (kilobytes.parse::<usize>().ok()).and_then(|k| k.checked_mul(1024)),
+        None => text.parse().ok(),
+    }
+    .ok_or_else(|| format!("invalid memory size {}", text))?;
+    if !(ADDR_PROGRAM_END as usize..=MAX_MEMORY_SIZE).contains(&size) {
+        return Err(format!(
+            "memory size must be between 4K and 64K, not {}",
+            text
+        ));
+    }
+    Ok(size)
+}
+
+/// An address in hex, with or without `0x`.
+pub fn parse_address(text: &str) -> Result<u16, String> {
+    u16::from_str_radix(text.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid address {}", text))
+}
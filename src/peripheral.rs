@@ -0,0 +1,135 @@
+use crate::chip8::Opcode;
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::ops::{Range, RangeInclusive};
+
+// Needs the time of day, and so std
+#[cfg(feature = "clock")]
+mod clock;
+mod debug;
+
+/// A device mapped into memory, which real machines never had but programs
+/// can be written for: clocks, printers, debug ports and the like. Each is
+/// compiled in as a module here, optionally behind a feature, and attached by
+/// name with `--peripheral NAME[@ADDR]`.
+pub trait Peripheral: Send {
+    /// The addresses the device answers for.
+    fn addresses(&self) -> RangeInclusive<u16>;
+
+    /// The byte the program sees when it reads `addr`, or `None` to leave
+    /// memory as it is.
+    fn read(&mut self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    /// Called after the program writes `value` to `addr`.
+    fn write(&mut self, _addr: u16, _value: u8) {}
+
+    /// Called once a frame, with the timers.
+    fn tick(&mut self) {}
+
+    /// Text the device has produced since last asked, for the console.
+    fn output(&mut self) -> Vec<u8> {
+        vec![]
+    }
+
+    /// Stops depending on anything outside the machine, such as the time of
+    /// day, so a run can be repeated exactly.
+    fn pin(&mut self) {}
+}
+
+/// A peripheral asked for on the command line.
+pub struct Spec {
+    pub name: String,
+    /// Where to map it, if not where the device usually goes.
+    pub address: Option<u16>,
+}
+
+impl Spec {
+    /// Reads `NAME` or `NAME@ADDR`, the address in hex.
+    pub fn parse(text: &str) -> Result<Spec, String> {
+        let (name, address) = match text.split_once('@') {
+            Some((name, address)) => (
+                name,
+                Some(
+                    u16::from_str_radix(address, 16)
+                        .map_err(|_| format!("invalid peripheral address {}", address))?,
+                ),
+            ),
+            None => (text, None),
+        };
+        let spec = Spec {
+            name: name.to_string(),
+            address,
+        };
+        create(&spec)?;
+        Ok(spec)
+    }
+}
+
+/// The peripherals compiled into this build.
+pub const NAMES: &[&str] = &[
+    #[cfg(feature = "clock")]
+    "clock",
+    "debug",
+];
+
+/// A new instance of the peripheral `spec` names.
+pub fn create(spec: &Spec) -> Result<Box<dyn Peripheral>, String> {
+    match spec.name.as_str() {
+        #[cfg(feature = "clock")]
+        "clock" => Ok(Box::new(clock::Clock::new(spec.address))),
+        "debug" => Ok(Box::new(debug::DebugPort::new(spec.address))),
+        name => Err(format!(
+            "no peripheral called {} in this build (available: {})",
+            name,
+            NAMES.join(", ")
+        )),
+    }
+}
+
+/// The memory `op` reads and writes through I, other than being fetched.
+pub fn accesses(op: &Opcode, i: u16) -> (Range<usize>, Range<usize>) {
+    let i = i as usize;
+    let x = op.n1 as usize;
+    match (op.n0, op.n2, op.n3) {
+        (0xD, _, n) => (i..i + n as usize, 0..0),
+        (0xF, 0x6, 0x5) => (i..i + x + 1, 0..0),
+        (0xF, 0x5, 0x5) => (0..0, i..i + x + 1),
+        (0xF, 0x3, 0x3) => (0..0, i..i + 3),
+        _ => (0..0, 0..0),
+    }
+}
+
+/// Lets each peripheral fill in the bytes it maps among those about to be read.
+pub fn supply(peripherals: &mut [Box<dyn Peripheral>], memory: &mut [u8], reads: Range<usize>) {
+    let reads = reads.start.min(memory.len())..reads.end.min(memory.len());
+    for peripheral in peripherals {
+        let mapped = peripheral.addresses();
+        for addr in reads.clone() {
+            if mapped.contains(&(addr as u16)) {
+                if let Some(value) = peripheral.read(addr as u16) {
+                    memory[addr] = value;
+                }
+            }
+        }
+    }
+}
+
+/// Tells each peripheral about the bytes just written to addresses it maps.
+pub fn deliver(peripherals: &mut [Box<dyn Peripheral>], memory: &[u8], writes: Range<usize>) {
+    let writes = writes.start.min(memory.len())..writes.end.min(memory.len());
+    for peripheral in peripherals {
+        let mapped = peripheral.addresses();
+        for addr in writes.clone() {
+            if mapped.contains(&(addr as u16)) {
+                peripheral.write(addr as u16, memory[addr]);
+            }
+        }
+    }
+}
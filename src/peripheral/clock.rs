@@ -0,0 +1,52 @@
+use super::Peripheral;
+use core::ops::RangeInclusive;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where the clock goes unless told otherwise: the last three bytes of 4K.
+const ADDRESS: u16 = 0xFFD;
+
+/// The time of day in UTC as hours, minutes and seconds in three bytes, so a
+/// program can read it with `FX65`.
+pub struct Clock {
+    address: u16,
+    /// Once pinned, frames since midnight stand in for the time of day.
+    pinned: Option<u64>,
+}
+
+impl Clock {
+    pub fn new(address: Option<u16>) -> Clock {
+        Clock {
+            address: address.unwrap_or(ADDRESS),
+            pinned: None,
+        }
+    }
+}
+
+impl Peripheral for Clock {
+    fn addresses(&self) -> RangeInclusive<u16> {
+        self.address..=self.address.saturating_add(2)
+    }
+
+    fn read(&mut self, addr: u16) -> Option<u8> {
+        let seconds = match self.pinned {
+            Some(frames) => frames / 60,
+            None => SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs(),
+        };
+        let value = match addr - self.address {
+            0 => seconds / 3600 % 24,
+            1 => seconds / 60 % 60,
+            _ => seconds % 60,
+        };
+        Some(value as u8)
+    }
+
+    fn tick(&mut self) {
+        if let Some(frames) = &mut self.pinned {
+            *frames += 1;
+        }
+    }
+
+    fn pin(&mut self) {
+        self.pinned = Some(0);
+    }
+}
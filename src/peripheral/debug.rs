@@ -0,0 +1,36 @@
+use super::Peripheral;
+use alloc::{vec, vec::Vec};
+use core::{mem, ops::RangeInclusive};
+
+/// Where the port goes unless told otherwise, just below the clock's place.
+const ADDRESS: u16 = 0xFFC;
+
+/// A byte the program can write characters to, like printf, which ends up in
+/// the console panel.
+pub struct DebugPort {
+    address: u16,
+    written: Vec<u8>,
+}
+
+impl DebugPort {
+    pub fn new(address: Option<u16>) -> DebugPort {
+        DebugPort {
+            address: address.unwrap_or(ADDRESS),
+            written: vec![],
+        }
+    }
+}
+
+impl Peripheral for DebugPort {
+    fn addresses(&self) -> RangeInclusive<u16> {
+        self.address..=self.address
+    }
+
+    fn write(&mut self, _addr: u16, value: u8) {
+        self.written.push(value);
+    }
+
+    fn output(&mut self) -> Vec<u8> {
+        mem::take(&mut self.written)
+    }
+}
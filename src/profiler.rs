@@ -0,0 +1,125 @@
+use crate::chip8::{Chip8, Opcode};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Frames of call history kept, a minute's worth.
+const CALL_FRAMES: usize = 3600;
+
+/// How many times each address has been executed since the ROM was loaded.
+#[derive(Default)]
+pub struct Profiler {
+    counts: Vec<u64>,
+    total: u64,
+}
+
+impl Profiler {
+    pub fn record(&mut self, pc: u16, memory_size: usize) {
+        if self.counts.len() != memory_size {
+            self.counts = vec![0; memory_size];
+        }
+        self.counts[pc as usize % memory_size] += 1;
+        self.total += 1;
+    }
+
+    /// Executions per address, empty until something has run.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Fills `hot` with every executed address, most executed first.
+    pub fn hottest(&self, hot: &mut Vec<(u16, u64)>) {
+        hot.clear();
+        let counts = (0..).zip(self.counts.iter().copied());
+        hot.extend(counts.filter(|(_, count)| *count > 0));
+        hot.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    }
+}
+
+/// Running totals since the ROM was loaded, for checking pacing and for
+/// ROM authors curious what their program gets up to.
+#[derive(Default)]
+pub struct Counters {
+    pub instructions: u64,
+    pub frames: u64,
+    pub sprites: u64,
+    /// Sprites drawn that erased pixels, setting VF.
+    pub collisions: u64,
+    /// The deepest calls have nested.
+    pub max_depth: usize,
+}
+
+impl Counters {
+    /// Counts one instruction; `op` has just been executed by `chip8`.
+    pub fn record(&mut self, op: &Opcode, chip8: &Chip8) {
+        self.instructions += 1;
+        if op.n0 == 0xD {
+            self.sprites += 1;
+            self.collisions += (chip8.v[0xF] == 1) as u64;
+        }
+        self.max_depth = self.max_depth.max(chip8.stack.len());
+    }
+}
+
+/// Which subroutines the time goes to, and how deep calls nest frame by
+/// frame. A subroutine is known by the address it was called at, and the
+/// main program, or a subroutine entered before recording began, by `None`.
+#[derive(Default)]
+pub struct CallProfile {
+    /// The subroutines being run, innermost last, alongside the stack.
+    entries: Vec<Option<u16>>,
+    /// Instructions run in each subroutine, not counting those it called.
+    totals: BTreeMap<Option<u16>, u64>,
+    total: u64,
+    /// The frame being run: instructions per subroutine and the deepest calls went.
+    frame: BTreeMap<Option<u16>, u64>,
+    frame_depth: usize,
+    /// The deepest calls went and the subroutine that ran most, each frame.
+    pub frames: VecDeque<(usize, Option<u16>)>,
+}
+
+impl CallProfile {
+    /// Counts one instruction; `op` has just been executed by `chip8`.
+    pub fn record(&mut self, op: &Opcode, chip8: &Chip8) {
+        let routine = self.entries.last().copied().flatten();
+        *self.totals.entry(routine).or_default() += 1;
+        *self.frame.entry(routine).or_default() += 1;
+        self.total += 1;
+        if op.n0 == 0x2 {
+            self.entries.push(Some(op.a));
+        }
+        // Returns pop, and anything else that moved the stack, like a state
+        // being loaded, leaves what's under it unknown
+        self.entries.resize(chip8.stack.len(), None);
+        self.frame_depth = self.frame_depth.max(chip8.stack.len());
+    }
+
+    /// Closes the frame, once a frame's instructions have run.
+    pub fn end_frame(&mut self) {
+        let busiest = self.frame.iter().max_by_key(|(_, count)| **count);
+        let routine = busiest.and_then(|(routine, _)| *routine);
+        self.frames.push_back((self.frame_depth, routine));
+        if self.frames.len() > CALL_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frame.clear();
+        self.frame_depth = self.entries.len();
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Fills `busiest` with every subroutine that has run, most first, with how much.
+    pub fn busiest(&self, busiest: &mut Vec<(Option<u16>, u64)>) {
+        busiest.clear();
+        busiest.extend(
+            self.totals
+                .iter()
+                .map(|(routine, count)| (*routine, *count)),
+        );
+        busiest.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    }
+}
@@ -0,0 +1,467 @@
+use crate::{
+    analysis,
+    chip8::{Chip8, Opcode},
+};
+use alloc::{collections::BTreeSet, format, string::String, vec::Vec};
+
+/// A set of interpreter behaviors matching a family of historical interpreters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// The original COSMAC VIP interpreter.
+    Vip,
+    /// SUPER-CHIP on the HP48, which most 90s games were written for.
+    Schip,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 2] = [Profile::Vip, Profile::Schip];
+
+    pub fn parse(name: &str) -> Option<Profile> {
+        match name {
+            "vip" => Some(Profile::Vip),
+            "schip" => Some(Profile::Schip),
+            _ => None,
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Profile::Vip => Quirks {
+                add_i_sets_vf: false,
+                shift_vx: false,
+                jump_vx: false,
+                load_store_increments_i: true,
+                logic_resets_vf: true,
+                stack_limit: Some(12),
+            },
+            Profile::Schip => Quirks {
+                add_i_sets_vf: false,
+                shift_vx: true,
+                jump_vx: true,
+                load_store_increments_i: false,
+                logic_resets_vf: false,
+                stack_limit: Some(16),
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Profile::Vip => "vip",
+            Profile::Schip => "schip",
+        }
+    }
+}
+
+/// Interpreter behaviors that differ between CHIP-8 implementations. The
+/// default is what fe_o8 has always done; profiles pick a consistent set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// ADI (FX1E) sets VF when I passes 0xFFF, as the Amiga interpreter did.
+    pub add_i_sets_vf: bool,
+    /// RSH and LSH (8XY6, 8XYE) shift VX in place instead of shifting VY into VX.
+    pub shift_vx: bool,
+    /// J0N (BNNN) jumps to XNN plus VX rather than NNN plus V0.
+    pub jump_vx: bool,
+    /// RST and RLD (FX55, FX65) leave I pointing past the registers they copied.
+    pub load_store_increments_i: bool,
+    /// ORR, AND and XOR (8XY1, 8XY2, 8XY3) clear VF, a side effect of how the VIP ran them.
+    pub logic_resets_vf: bool,
+    /// How deep subroutine calls may nest, or `None` for as deep as memory allows.
+    pub stack_limit: Option<usize>,
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks {
+            add_i_sets_vf: true,
+            shift_vx: false,
+            jump_vx: false,
+            load_store_increments_i: false,
+            logic_resets_vf: false,
+            stack_limit: None,
+        }
+    }
+}
+
+impl Quirks {
+    /// Names accepted by `set`, for usage messages.
+    pub const NAMES: &'static [&'static str] =
+        &["fx1e-vf", "shift-vx", "jump-vx", "load-store-i", "vf-reset"];
+
+    /// Turns a single quirk on or off by name.
+    pub fn set(&mut self, name: &str, on: bool) -> Result<(), String> {
+        match name {
+            "fx1e-vf" => self.add_i_sets_vf = on,
+            "shift-vx" => self.shift_vx = on,
+            "jump-vx" => self.jump_vx = on,
+            "load-store-i" => self.load_store_increments_i = on,
+            "vf-reset" => self.logic_resets_vf = on,
+            _ => {
+                return Err(format!(
+                    "unknown quirk {}, expected one of {}",
+                    name,
+                    Quirks::NAMES.join(", ")
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A profile guessed from a ROM, along with what gave it away.
+pub struct Detection {
+    pub profile: Profile,
+    /// The profile's quirks, with any the code showed it needs otherwise.
+    pub quirks: Quirks,
+    pub reasons: Vec<String>,
+}
+
+/// Guesses which profile a ROM was written for by scanning its reachable code
+/// from `entry` for SCHIP instructions and for idioms whose meaning depends
+/// on the quirks: how shifts are written, which register was just set before
+/// BXNN, and whether the next FX55 or FX65 counts on where I was left.
+pub fn detect(memory: &[u8], entry: u16) -> Detection {
+    let code = analysis::reachable(memory, entry);
+    let decode = |addr: u16| Opcode::from_slice(&memory[addr as usize..]);
+    let mut schip_opcodes = Vec::new();
+    let (mut vy_shifts, mut vx_shifts) = (0, 0);
+    let (mut v0_jumps, mut vx_jumps) = (0, 0);
+    let (mut moved, mut stayed) = (0, 0);
+    for &addr in &code {
+        let op = decode(addr);
+        if op.extension() == Some("SCHIP") {
+            schip_opcodes.push(addr);
+        }
+        match dependence(&op).map(|(quirk, _)| quirk) {
+            // SCHIP era assemblers wrote shifts as SHR VX, leaving VY as 0
+            Some("shift-vx") if op.n2 == 0 => vx_shifts += 1,
+            Some("shift-vx") => vy_shifts += 1,
+            // A jump table is indexed by the register set just before
+            Some("jump-vx") => {
+                let before = addr.checked_sub(2).filter(|before| code.contains(before));
+                match before.and_then(|before| sets_register(&decode(before))) {
+                    Some(0) => v0_jumps += 1,
+                    Some(x) if x == op.n1 => vx_jumps += 1,
+                    _ => (),
+                }
+            }
+            Some("load-store-i") => {
+                match next_use_of_i(memory, &code, addr).map(|next| moves_i(op.n2, &decode(next))) {
+                    Some(Some(true)) => moved += 1,
+                    Some(Some(false)) => stayed += 1,
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Each idiom votes for the profile it's written for, and the quirk it needs
+    let (mut schip, mut vip) = (0, 0);
+    let mut reasons = Vec::new();
+    let mut vote = |profile: Profile, reason: String| {
+        match profile {
+            Profile::Schip => schip += 1,
+            Profile::Vip => vip += 1,
+        }
+        reasons.push(reason);
+    };
+    let shift_vx = match (vx_shifts, vy_shifts) {
+        (vx, vy) if vx > vy => {
+            vote(
+                Profile::Schip,
+                format!("{} shifts written as SHR/SHL VX", vx),
+            );
+            Some(true)
+        }
+        (vx, vy) if vy > vx => {
+            vote(Profile::Vip, format!("{} shifts from VY into VX", vy));
+            Some(false)
+        }
+        _ => None,
+    };
+    let jump_vx = match (vx_jumps, v0_jumps) {
+        (vx, v0) if vx > v0 => {
+            vote(
+                Profile::Schip,
+                format!("{} BXNN right after setting VX", vx),
+            );
+            Some(true)
+        }
+        (vx, v0) if v0 > vx => {
+            vote(Profile::Vip, format!("{} BXNN right after setting V0", v0));
+            Some(false)
+        }
+        _ => None,
+    };
+    let increments = match (moved, stayed) {
+        (moved, stayed) if moved > stayed => {
+            let reason = format!(
+                "{} FX55/FX65 counting on I moving past the registers",
+                moved
+            );
+            vote(Profile::Vip, reason);
+            Some(true)
+        }
+        (moved, stayed) if stayed > moved => {
+            let reason = format!("{} FX55/FX65 counting on I staying put", stayed);
+            vote(Profile::Schip, reason);
+            Some(false)
+        }
+        _ => None,
+    };
+    if let Some(addr) = schip_opcodes.first() {
+        reasons.insert(
+            0,
+            format!(
+                "{} SCHIP instructions, first at {:03X}",
+                schip_opcodes.len(),
+                addr
+            ),
+        );
+    }
+    if reasons.is_empty() {
+        reasons.push("nothing profile specific found".into());
+    }
+
+    let profile = match !schip_opcodes.is_empty() || schip > vip {
+        true => Profile::Schip,
+        false => Profile::Vip,
+    };
+    let quirks = Quirks {
+        shift_vx: shift_vx.unwrap_or(profile.quirks().shift_vx),
+        jump_vx: jump_vx.unwrap_or(profile.quirks().jump_vx),
+        load_store_increments_i: increments.unwrap_or(profile.quirks().load_store_increments_i),
+        ..profile.quirks()
+    };
+    Detection {
+        profile,
+        quirks,
+        reasons,
+    }
+}
+
+/// The register `op` sets, if it sets just one.
+fn sets_register(op: &Opcode) -> Option<u8> {
+    match (op.n0, op.n2, op.n3) {
+        (0x6 | 0x7 | 0xC, ..) | (0x8, _, 0x0..=0x7 | 0xE) | (0xF, 0x0, 0x7 | 0xA) => Some(op.n1),
+        _ => None,
+    }
+}
+
+/// Whether `op` reads or sets I.
+fn uses_i(op: &Opcode) -> bool {
+    match (op.n0, op.n2, op.n3) {
+        (0xA | 0xD, ..) => true,
+        (0xF, n2, n3) => matches!(
+            (n2, n3),
+            (0x1, 0xE) | (0x2, 0x9) | (0x3, 0x0 | 0x3) | (0x5 | 0x6, 0x5)
+        ),
+        _ => false,
+    }
+}
+
+/// Whether `op` is RST or RLD (FX55, FX65).
+fn is_load_store(op: &Opcode) -> bool {
+    op.n0 == 0xF && op.n3 == 0x5 && matches!(op.n2, 0x5 | 0x6)
+}
+
+/// Whether `next`, the first instruction to use I after an RST or RLD
+/// (`first` being its third nibble), counts on I having moved past the
+/// registers. Storing or loading the same way twice only makes sense if it
+/// did, storing what was just loaded (or loading what was stored) only if it
+/// didn't. Anything else says nothing.
+fn moves_i(first: u8, next: &Opcode) -> Option<bool> {
+    is_load_store(next).then_some(first == next.n2)
+}
+
+/// The first instruction to use I after the one at `addr`, along straight
+/// line code that doesn't branch.
+fn next_use_of_i(memory: &[u8], code: &BTreeSet<u16>, addr: u16) -> Option<u16> {
+    let mut addr = addr;
+    // Far enough for the bookkeeping between two copies of the registers
+    for _ in 0..8 {
+        let op = Opcode::from_slice(&memory[addr as usize..]);
+        let next = addr.checked_add(2)?;
+        if analysis::successors(&op, addr)? != [next] || !code.contains(&next) {
+            return None;
+        }
+        addr = next;
+        if uses_i(&Opcode::from_slice(&memory[addr as usize..])) {
+            return Some(addr);
+        }
+    }
+    None
+}
+
+/// Watches instructions as they run for what a scan of the ROM can't see,
+/// such as where a BXNN really lands, changing a quirk the first time the
+/// program shows which way it needs it.
+#[derive(Default)]
+pub struct Check {
+    /// The last RST or RLD, by address and its third nibble, until the next
+    /// instruction to use I.
+    load_store: Option<(u16, u8)>,
+    /// Quirks settled, by asking or by the program, not to be changed again.
+    jump_settled: bool,
+    load_store_settled: bool,
+    changes: Vec<String>,
+}
+
+impl Check {
+    /// Leaves a quirk alone, for those asked for by name.
+    pub fn settle(&mut self, name: &str) {
+        match name {
+            "jump-vx" => self.jump_settled = true,
+            "load-store-i" => self.load_store_settled = true,
+            _ => (),
+        }
+    }
+
+    /// Looks at `op`, about to run at PC, before it does.
+    pub fn before(&mut self, chip8: &mut Chip8, op: &Opcode) {
+        let pc = chip8.pc;
+        // A jump that lands on something that isn't an instruction, when the
+        // other register would have made it land on one
+        if dependence(op).is_some_and(|(quirk, _)| quirk == "jump-vx") && !self.jump_settled {
+            let target = |vx: bool| {
+                let x = if vx { op.n1 } else { 0 };
+                op.a.wrapping_add(chip8.v[x as usize] as u16)
+            };
+            let runs = |addr: u16| {
+                addr as usize + 2 <= chip8.memory.len()
+                    && analysis::successors(
+                        &Opcode::from_slice(&chip8.memory[addr as usize..]),
+                        addr,
+                    )
+                    .is_some()
+            };
+            let jump_vx = chip8.quirks.jump_vx;
+            if !runs(target(jump_vx)) && runs(target(!jump_vx)) {
+                chip8.quirks.jump_vx = !jump_vx;
+                self.jump_settled = true;
+                self.changes.push(format!(
+                    "jump-vx {}, as BXNN at {:03X} would have jumped into data at {:03X}",
+                    if jump_vx { "off" } else { "on" },
+                    pc,
+                    target(jump_vx)
+                ));
+            }
+        }
+        if !uses_i(op) {
+            return;
+        }
+        if let (Some((first, kind)), false) = (self.load_store.take(), self.load_store_settled) {
+            if let Some(moved) = moves_i(kind, op) {
+                self.load_store_settled = true;
+                if moved != chip8.quirks.load_store_increments_i {
+                    chip8.quirks.load_store_increments_i = moved;
+                    self.changes.push(format!(
+                        "load-store-i {}, as {:03X} counts on where {:03X} left I",
+                        if moved { "on" } else { "off" },
+                        pc,
+                        first
+                    ));
+                }
+            }
+        }
+        if is_load_store(op) {
+            self.load_store = Some((pc, op.n2));
+        }
+    }
+
+    /// The quirks changed since last asked, and why.
+    pub fn changes(&mut self) -> impl Iterator<Item = String> + '_ {
+        self.changes.drain(..)
+    }
+}
+
+/// The quirk that changes what `op` does, by its name in `Quirks::NAMES`,
+/// and how, or `None` if every profile runs it alike.
+pub fn dependence(op: &Opcode) -> Option<(&'static str, String)> {
+    let (x, y) = (op.n1, op.n2);
+    let dependence = match (op.n0, op.n3) {
+        (0x8, 0x6 | 0xE) if x != y => (
+            "shift-vx",
+            format!(
+                "shifts V{:X} into V{:X} on the VIP, V{:X} in place on SCHIP",
+                y, x, x
+            ),
+        ),
+        (0x8, 0x1..=0x3) => ("vf-reset", "clears VF on the VIP, not on SCHIP".into()),
+        (0xB, _) if x != 0 => ("jump-vx", format!("adds V0 on the VIP, V{:X} on SCHIP", x)),
+        (0xF, 0x5) if y == 0x5 || y == 0x6 => (
+            "load-store-i",
+            "moves I past the registers on the VIP, not on SCHIP".into(),
+        ),
+        (0xF, 0xE) if y == 0x1 => (
+            "fx1e-vf",
+            "sets VF when I passes FFF on the Amiga, no other".into(),
+        ),
+        _ => return None,
+    };
+    Some(dependence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// 4K of memory with `program` at 200.
+    fn memory(program: &[u8]) -> Vec<u8> {
+        let mut memory = vec![0; 0x1000];
+        memory[0x200..0x200 + program.len()].copy_from_slice(program);
+        memory
+    }
+
+    #[test]
+    fn jump_indexed_by_vx_is_schip() {
+        let detection = detect(&memory(&[0x61, 0x02, 0xB1, 0x10]), 0x200);
+        assert_eq!(detection.profile, Profile::Schip);
+        assert!(detection.quirks.jump_vx);
+    }
+
+    #[test]
+    fn loading_twice_counts_on_i_moving() {
+        let program = [0xA3, 0x00, 0xF1, 0x65, 0xF1, 0x65, 0x12, 0x06];
+        let detection = detect(&memory(&program), 0x200);
+        assert_eq!(detection.profile, Profile::Vip);
+        assert!(detection.quirks.load_store_increments_i);
+    }
+
+    #[test]
+    fn storing_what_was_loaded_counts_on_i_staying() {
+        let program = [0xA3, 0x00, 0xF1, 0x65, 0x70, 0x01, 0xF1, 0x55, 0x12, 0x08];
+        let detection = detect(&memory(&program), 0x200);
+        assert!(!detection.quirks.load_store_increments_i);
+    }
+
+    #[test]
+    fn check_follows_a_jump_that_lands_in_data() {
+        // B210 lands on 0000 at 210 with V0, on JMP 214 at 214 with V2
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0xB2, 0x10]);
+        chip8.memory[0x214..0x216].copy_from_slice(&[0x12, 0x14]);
+        chip8.v[2] = 4;
+        let mut check = Check::default();
+        check.before(&mut chip8, &Opcode::from_slice(&[0xB2, 0x10]));
+        assert!(chip8.quirks.jump_vx);
+        assert_eq!(check.changes().count(), 1);
+    }
+
+    #[test]
+    fn check_leaves_quirks_asked_for() {
+        let mut chip8 = Chip8::new();
+        chip8.load(&[0xF1, 0x65, 0xF1, 0x65]);
+        let mut check = Check::default();
+        check.settle("load-store-i");
+        for _ in 0..2 {
+            let op = Opcode::from_slice(&chip8.memory[chip8.pc as usize..]);
+            check.before(&mut chip8, &op);
+            chip8.step().unwrap();
+        }
+        assert!(!chip8.quirks.load_store_increments_i);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::legacy::Legacy;
+use crossterm::{
+    cursor::MoveTo,
+    style::{Colors, SetColors},
+    terminal::{Clear, ClearType},
+    QueueableCommand,
+};
+use ratatui::{
+    buffer::{Buffer, Cell},
+    layout::Rect,
+    style::{Color, Modifier},
+};
+use std::{
+    io::{self, stdout, Write},
+    mem,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// SGR codes for turning each modifier on. crossterm's own attribute
+/// commands build a string each time they're written, so these go out by hand.
+const MODIFIERS: [(Modifier, u8); 9] = [
+    (Modifier::BOLD, 1),
+    (Modifier::DIM, 2),
+    (Modifier::ITALIC, 3),
+    (Modifier::UNDERLINED, 4),
+    (Modifier::SLOW_BLINK, 5),
+    (Modifier::RAPID_BLINK, 6),
+    (Modifier::REVERSED, 7),
+    (Modifier::HIDDEN, 8),
+    (Modifier::CROSSED_OUT, 9),
+];
+
+const RESET: &[u8] = b"\x1b[0m";
+
+/// Draws the screen into one of a pair of buffers and writes the cells that
+/// changed since the other was drawn straight out as bytes. Nothing is built
+/// up along the way, so once the buffers have grown to the terminal's size a
+/// frame allocates nothing.
+pub struct Renderer {
+    /// Escape sequences waiting for the caller to send.
+    output: Vec<u8>,
+    previous: Buffer,
+    current: Buffer,
+    /// The whole screen goes out next frame, not just what changed.
+    full: bool,
+    /// An older Windows console, which is written to directly instead,
+    /// leaving the output empty.
+    legacy: Option<Legacy>,
+    /// RGB colors go out as the nearest of the 256 indexed ones, for
+    /// terminals and multiplexers that have no more.
+    indexed: bool,
+    /// Only every other row goes out each frame, alternating between the
+    /// even and odd ones, for halving what's sent over slow links.
+    interlace: bool,
+    /// Which rows go out next frame when interlacing, 0 for the even ones.
+    field: u16,
+    /// Rows that changed were held back for the next frame.
+    pending: bool,
+}
+
+impl Renderer {
+    pub fn new(legacy: Option<Legacy>, indexed: bool, interlace: bool) -> Renderer {
+        Renderer {
+            output: Vec::new(),
+            previous: Buffer::empty(Rect::ZERO),
+            current: Buffer::empty(Rect::ZERO),
+            full: true,
+            legacy,
+            indexed,
+            interlace,
+            field: 0,
+            pending: false,
+        }
+    }
+
+    /// Matches the buffers to the terminal, redrawing everything when it changed.
+    pub fn resize(&mut self, area: Rect) {
+        if self.current.area != area {
+            self.previous.resize(area);
+            self.current.resize(area);
+            self.full = true;
+        }
+    }
+
+    /// Clears the terminal and redraws everything next frame, for when what's
+    /// on it can't be trusted.
+    pub fn clear(&mut self) -> io::Result<()> {
+        if self.legacy.is_some() {
+            stdout().queue(Clear(ClearType::All))?;
+        } else {
+            self.output.queue(Clear(ClearType::All))?;
+        }
+        self.full = true;
+        Ok(())
+    }
+
+    /// Whether rows that changed are still to go out, so that drawing
+    /// again is needed even if nothing else has changed.
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Draws a frame and adds what changed on screen to the output. While
+    /// interlacing, that's only the changes in this frame's rows, except
+    /// when everything is to go out.
+    pub fn draw(&mut self, draw: impl FnOnce(Rect, &mut Buffer)) -> io::Result<()> {
+        self.current.reset();
+        draw(self.current.area, &mut self.current);
+
+        let Renderer {
+            output,
+            previous,
+            current,
+            full,
+            legacy,
+            indexed,
+            interlace,
+            field,
+            pending,
+        } = self;
+        let held = |y: u16| *interlace && !*full && y % 2 != *field;
+        // As in ratatui's own diff: a wide character covers the cells after
+        // it, and replacing one means redrawing the cells it covered
+        let (mut covered, mut invalidated) = (0, 0);
+        let changed = (current.content.iter().zip(&previous.content))
+            .enumerate()
+            .filter_map(|(index, (cell, previous))| {
+                let (x, y) = current.pos_of(index);
+                let changed = *full || cell != previous || invalidated > 0;
+                let drawn = changed && !cell.skip && covered == 0 && !held(y);
+                let width = cell.symbol().width();
+                covered = width.saturating_sub(1);
+                invalidated = width
+                    .max(previous.symbol().width())
+                    .max(invalidated)
+                    .saturating_sub(1);
+                drawn.then_some((x, y, cell))
+            });
+        match legacy {
+            Some(legacy) => legacy.write(&mut stdout().lock(), changed)?,
+            None => write_vt(output, changed, *indexed)?,
+        }
+
+        // The rows held back keep what's on screen, so that what changed in
+        // them is found again and goes out next frame
+        *pending = false;
+        let width = current.area.width as usize;
+        for y in (0..current.area.height).filter(|y| held(*y)) {
+            let row = y as usize * width..(y as usize + 1) * width;
+            if current.content[row.clone()] != previous.content[row.clone()] {
+                current.content[row.clone()].clone_from_slice(&previous.content[row]);
+                *pending = true;
+            }
+        }
+        *field ^= 1;
+
+        mem::swap(previous, current);
+        *full = false;
+        Ok(())
+    }
+
+    /// What's waiting to be sent to the terminal, to be cleared once it has been.
+    pub fn output(&mut self) -> &mut Vec<u8> {
+        &mut self.output
+    }
+}
+
+/// Writes cells as escape sequences, each at its position.
+fn write_vt<'c>(
+    output: &mut Vec<u8>,
+    cells: impl Iterator<Item = (u16, u16, &'c Cell)>,
+    indexed: bool,
+) -> io::Result<()> {
+    let (mut fg, mut bg, mut modifier) = (Color::Reset, Color::Reset, Modifier::empty());
+    let mut last = None;
+    for (x, y, cell) in cells {
+        if !matches!(last, Some((last_x, last_y)) if x == last_x + 1 && y == last_y) {
+            output.queue(MoveTo(x, y))?;
+        }
+        last = Some((x, y));
+        if cell.modifier != modifier {
+            // Resetting takes the colours with it, so they go out again below
+            output.extend_from_slice(RESET);
+            for (flag, code) in MODIFIERS {
+                if cell.modifier.contains(flag) {
+                    write!(output, "\x1b[{}m", code)?;
+                }
+            }
+            (fg, bg, modifier) = (Color::Reset, Color::Reset, cell.modifier);
+        }
+        let colors = match indexed {
+            true => (nearest_indexed(cell.fg), nearest_indexed(cell.bg)),
+            false => (cell.fg, cell.bg),
+        };
+        if colors != (fg, bg) {
+            output.queue(SetColors(Colors::new(colors.0.into(), colors.1.into())))?;
+            (fg, bg) = colors;
+        }
+        output.extend_from_slice(cell.symbol().as_bytes());
+    }
+    if last.is_some() {
+        output.extend_from_slice(RESET);
+    }
+    Ok(())
+}
+
+/// The closest of the 256 indexed colors to an RGB one, from the 6x6x6 cube
+/// or the grey ramp, and any other color as it is.
+fn nearest_indexed(color: Color) -> Color {
+    let Color::Rgb(r, g, b) = color else {
+        return color;
+    };
+    // The cube's levels are 0, then 95 to 255 in steps of 40
+    let level = |c: u8| match c {
+        0..=47 => 0,
+        48..=114 => 1,
+        c => (c - 35) / 40,
+    };
+    let value = |n: u8| if n == 0 { 0 } else { 55 + 40 * n };
+    let cube = [level(r), level(g), level(b)];
+    let average = (r as u16 + g as u16 + b as u16) / 3;
+    let grey = (average.saturating_sub(3) / 10).min(23) as u8;
+    let distance = |to: [u8; 3]| {
+        [(r, to[0]), (g, to[1]), (b, to[2])]
+            .iter()
+            .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+            .sum::<i32>()
+    };
+    let cube_distance = distance(cube.map(value));
+    let grey_distance = distance([8 + 10 * grey; 3]);
+    match cube_distance <= grey_distance {
+        true => Color::Indexed(16 + 36 * cube[0] + 6 * cube[1] + cube[2]),
+        false => Color::Indexed(232 + grey),
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{chip8::Chip8, savestate::Machine};
+use std::{collections::VecDeque, error::Error, result::Result};
+
+/// Frames of history kept, ten seconds at 60Hz.
+const CAPACITY: usize = 600;
+
+/// A snapshot of every recent frame, to scrub back through and resume from.
+#[derive(Default)]
+pub struct Rewind {
+    snapshots: VecDeque<Machine>,
+    /// The snapshot on screen while scrubbing.
+    cursor: Option<usize>,
+}
+
+impl Rewind {
+    pub fn record(&mut self, chip8: &Chip8) {
+        if self.snapshots.len() == CAPACITY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Machine::capture(chip8));
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.cursor = None;
+    }
+
+    pub fn scrubbing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Starts scrubbing from the machine as it is now.
+    pub fn start(&mut self, chip8: &Chip8) {
+        self.record(chip8);
+        self.cursor = Some(self.snapshots.len() - 1);
+    }
+
+    /// Moves the cursor by `frames` and shows the snapshot it lands on.
+    pub fn scrub(&mut self, chip8: &mut Chip8, frames: isize) -> Result<(), Box<dyn Error>> {
+        if let Some(cursor) = self.cursor.as_mut() {
+            *cursor = cursor
+                .saturating_add_signed(frames)
+                .min(self.snapshots.len() - 1);
+            self.snapshots[*cursor].restore(chip8)?;
+        }
+        Ok(())
+    }
+
+    /// Continues from the snapshot on screen, forgetting everything after it.
+    pub fn resume(&mut self) {
+        if let Some(cursor) = self.cursor.take() {
+            self.snapshots.truncate(cursor + 1);
+        }
+    }
+
+    /// Stops scrubbing and puts back the machine as it was when scrubbing started.
+    pub fn cancel(&mut self, chip8: &mut Chip8) -> Result<(), Box<dyn Error>> {
+        if self.cursor.take().is_some() {
+            if let Some(newest) = self.snapshots.back() {
+                newest.restore(chip8)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The cursor and the number of snapshots, while scrubbing.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.cursor.map(|cursor| (cursor, self.snapshots.len()))
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{chip8::Chip8, storage::data_dir};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    result::Result,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The save state format written now. Older ones are brought up to it step
+/// by step in `migrate` as they're read, so states saved by earlier releases
+/// keep loading; version 1 is from before states said which they were.
+const VERSION: u64 = 2;
+
+/// Everything needed to put a machine back the way it was.
+#[derive(Serialize, Deserialize)]
+pub struct Machine {
+    pub memory: Vec<u8>,
+    pub display: Vec<u64>,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub delay: u8,
+    pub sound: u8,
+    pub v: [u8; 16],
+    pub i: u16,
+    /// The keypad as latched this frame and the one before, which FX0A
+    /// compares to see a key released. Since version 2.
+    pub keys: [bool; 16],
+    pub last_keys: [bool; 16],
+}
+
+impl Machine {
+    pub fn capture(chip8: &Chip8) -> Machine {
+        Machine {
+            memory: chip8.memory.to_vec(),
+            display: chip8.display.to_vec(),
+            pc: chip8.pc,
+            stack: chip8.stack.clone(),
+            delay: chip8.delay,
+            sound: chip8.sound,
+            v: chip8.v,
+            i: chip8.i,
+            keys: chip8.keys,
+            last_keys: chip8.last_keys,
+        }
+    }
+
+    pub fn restore(&self, chip8: &mut Chip8) -> Result<(), Box<dyn Error>> {
+        if self.memory.len() != chip8.memory.len() || self.display.len() != chip8.display.len() {
+            return Err("save state doesn't match this machine".into());
+        }
+        chip8.memory.copy_from_slice(&self.memory);
+        chip8.display.copy_from_slice(&self.display);
+        chip8.dirty_rows = u32::MAX;
+        chip8.pc = self.pc;
+        chip8.stack = self.stack.clone();
+        chip8.delay = self.delay;
+        chip8.sound = self.sound;
+        chip8.v = self.v;
+        chip8.i = self.i;
+        chip8.keys = self.keys;
+        chip8.last_keys = self.last_keys;
+        Ok(())
+    }
+}
+
+/// A saved machine along with what a slot picker needs to describe it.
+#[derive(Serialize, Deserialize)]
+pub struct SaveState {
+    pub version: u64,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub rom_hash: String,
+    /// The display at half height, two pixel rows per line of block characters.
+    pub thumbnail: Vec<String>,
+    pub machine: Machine,
+}
+
+impl SaveState {
+    pub fn capture(chip8: &Chip8, rom_hash: &str) -> SaveState {
+        SaveState {
+            version: VERSION,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |t| t.as_secs()),
+            rom_hash: rom_hash.into(),
+            thumbnail: thumbnail(&chip8.display),
+            machine: Machine::capture(chip8),
+        }
+    }
+
+    /// How long ago the state was saved, roughly, for prompts.
+    pub fn age(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_secs());
+        match now.saturating_sub(self.timestamp) {
+            s if s < 120 => format!("{} seconds ago", s),
+            s if s < 2 * 3600 => format!("{} minutes ago", s / 60),
+            s if s < 2 * 86400 => format!("{} hours ago", s / 3600),
+            s => format!("{} days ago", s / 86400),
+        }
+    }
+
+    pub fn restore(&self, chip8: &mut Chip8) -> Result<(), Box<dyn Error>> {
+        self.machine.restore(chip8)
+    }
+
+    /// Reads a state written by this or any earlier release.
+    pub fn parse(state: Value) -> Result<SaveState, Box<dyn Error>> {
+        Ok(serde_json::from_value(migrate(state)?)?)
+    }
+}
+
+/// Brings a state in any earlier format up to the current one, refusing
+/// those written by a later release.
+fn migrate(mut state: Value) -> Result<Value, Box<dyn Error>> {
+    let version = match state.get("version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .ok_or("save state version isn't a number")?,
+    };
+    if version > VERSION {
+        return Err(format!(
+            "save state is version {}, from a newer fe_o8 that this one (version {}) can't read",
+            version, VERSION
+        )
+        .into());
+    }
+    for from in version..VERSION {
+        let machine = (state.get_mut("machine"))
+            .and_then(Value::as_object_mut)
+            .ok_or("save state has no machine")?;
+        match from {
+            // Nothing was held down, as far as the state knows
+            1 => {
+                machine.insert("keys".into(), Value::from([false; 16].to_vec()));
+                machine.insert("last_keys".into(), Value::from([false; 16].to_vec()));
+            }
+            _ => unreachable!("no migration from save state version {}", from),
+        }
+    }
+    state["version"] = VERSION.into();
+    Ok(state)
+}
+
+/// For a state inside another file, such as an exported debugger session.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SaveState, D::Error> {
+    SaveState::parse(Value::deserialize(deserializer)?).map_err(de::Error::custom)
+}
+
+/// The file backing a numbered save slot for a ROM.
+pub fn slot_path(rom_hash: &str, slot: usize) -> PathBuf {
+    data_dir()
+        .join("states")
+        .join(format!("{}.{}.json", rom_hash, slot))
+}
+
+/// The state written on exit when autosave is on.
+pub fn autosave_path(rom_hash: &str) -> PathBuf {
+    data_dir()
+        .join("states")
+        .join(format!("{}.autosave.json", rom_hash))
+}
+
+pub fn save(chip8: &Chip8, rom_hash: &str, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(
+        path,
+        serde_json::to_vec(&SaveState::capture(chip8, rom_hash))?,
+    )?;
+    Ok(())
+}
+
+/// Reads a state, refusing one that was saved from a different ROM.
+pub fn read(rom_hash: &str, path: &Path) -> Result<SaveState, Box<dyn Error>> {
+    let state = SaveState::parse(serde_json::from_slice(&fs::read(path)?)?)?;
+    if state.rom_hash != rom_hash {
+        return Err("saved from a different ROM".into());
+    }
+    Ok(state)
+}
+
+pub fn save_slot(chip8: &Chip8, rom_hash: &str, slot: usize) -> Result<(), Box<dyn Error>> {
+    save(chip8, rom_hash, &slot_path(rom_hash, slot))
+}
+
+/// Restores a slot, returning the state to say when it was saved.
+pub fn load_slot(
+    chip8: &mut Chip8,
+    rom_hash: &str,
+    slot: usize,
+) -> Result<SaveState, Box<dyn Error>> {
+    let state = read(rom_hash, &slot_path(rom_hash, slot))?;
+    state.restore(chip8)?;
+    Ok(state)
+}
+
+fn thumbnail(display: &[u64; 32]) -> Vec<String> {
+    display
+        .chunks(2)
+        .map(|rows| {
+            (0..64)
+                .map(|bit| {
+                    let lit = |row: u64| row >> (63 - bit) & 1 == 1;
+                    match (lit(rows[0]), lit(rows[1])) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,81 @@
+use crate::{chip8::Chip8, TIMER_RATE};
+
+/// Splits shown under the time, the latest ones.
+const SHOWN_SPLITS: usize = 5;
+
+/// A speedrun timer counting the frames the game has run since it was
+/// reset, so it keeps game time however fast the host is, with the times of
+/// the splits marked so far.
+#[derive(Hash)]
+pub struct Speedrun {
+    frames: u64,
+    splits: Vec<u64>,
+    /// The address whose changes split on their own, like a level counter,
+    /// and the byte there as of the last frame.
+    split_on: Option<(u16, u8)>,
+}
+
+impl Speedrun {
+    pub fn new(split_on: Option<u16>, chip8: &Chip8) -> Speedrun {
+        let mut speedrun = Speedrun {
+            frames: 0,
+            splits: vec![],
+            split_on: split_on.map(|addr| (addr, 0)),
+        };
+        speedrun.reset(chip8);
+        speedrun
+    }
+
+    /// Starts again from nothing, as the game just has.
+    pub fn reset(&mut self, chip8: &Chip8) {
+        self.frames = 0;
+        self.splits.clear();
+        if let Some((addr, value)) = &mut self.split_on {
+            *value = chip8.memory[*addr as usize];
+        }
+    }
+
+    /// Counts a frame the game ran, splitting if the watched byte changed.
+    pub fn tick(&mut self, chip8: &Chip8) {
+        self.frames += 1;
+        if let Some((addr, value)) = &mut self.split_on {
+            let now = chip8.memory[*addr as usize];
+            if now != *value {
+                *value = now;
+                self.splits.push(self.frames);
+            }
+        }
+    }
+
+    pub fn split(&mut self) {
+        self.splits.push(self.frames);
+    }
+
+    /// The time so far and then the latest splits, each with how long it
+    /// took since the one before.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![clock(self.frames)];
+        let skipped = self.splits.len().saturating_sub(SHOWN_SPLITS);
+        for (index, &split) in self.splits.iter().enumerate().skip(skipped) {
+            let previous = index.checked_sub(1).map_or(0, |index| self.splits[index]);
+            lines.push(format!(
+                "{:>2} {} +{}",
+                index + 1,
+                clock(split),
+                clock(split - previous)
+            ));
+        }
+        lines
+    }
+}
+
+/// Frames as minutes, seconds and hundredths, counting the game's 60Hz ticks.
+fn clock(frames: u64) -> String {
+    let hundredths = frames * 100 / TIMER_RATE as u64;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
@@ -0,0 +1,37 @@
+use crate::chip8::fnv1a;
+use std::{env, path::PathBuf};
+
+/// Where per-ROM data such as save states is kept: `$XDG_DATA_HOME/fe_o8`,
+/// falling back to `~/.local/share/fe_o8`.
+pub fn data_dir() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("fe_o8")
+}
+
+/// Where settings are kept: `$XDG_CONFIG_HOME/fe_o8`, falling back to
+/// `~/.config/fe_o8`.
+pub fn config_dir() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("fe_o8")
+}
+
+/// Where downloads that can be fetched again are kept: `$XDG_CACHE_HOME/fe_o8`,
+/// falling back to `~/.cache/fe_o8`.
+pub fn cache_dir() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("fe_o8")
+}
+
+/// Identifies a ROM by its contents, so data follows it across renames.
+pub fn rom_hash(rom: &[u8]) -> String {
+    format!("{:016x}", fnv1a(rom))
+}
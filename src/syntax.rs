@@ -0,0 +1,152 @@
+use crate::chip8::Opcode;
+use std::fmt;
+
+/// Ways of writing instructions out, to match whichever documentation is at hand.
+#[derive(Clone, Copy, Default)]
+pub enum Syntax {
+    /// The three-letter codes used throughout this interpreter.
+    #[default]
+    Fe,
+    /// CHIPPER and Cowgod's reference: `LD V1, #14`.
+    Chipper,
+    /// Octo's pseudo-code: `v1 := 0x14`.
+    Octo,
+}
+
+impl Syntax {
+    pub const NAMES: [&'static str; 3] = ["fe", "chipper", "octo"];
+
+    pub fn parse(name: &str) -> Option<Syntax> {
+        match name {
+            "fe" => Some(Syntax::Fe),
+            "chipper" | "cowgod" => Some(Syntax::Chipper),
+            "octo" => Some(Syntax::Octo),
+            _ => None,
+        }
+    }
+
+    /// The instruction with its operands.
+    pub fn format(self, op: &Opcode) -> String {
+        self.display(op).to_string()
+    }
+
+    /// The instruction with its operands, written out wherever it's
+    /// displayed rather than kept as a string.
+    pub fn display(self, op: &Opcode) -> Instruction<'_> {
+        Instruction(self, op)
+    }
+}
+
+pub struct Instruction<'a>(Syntax, &'a Opcode);
+
+impl fmt::Display for Instruction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Syntax::Fe => fe(f, self.1),
+            Syntax::Chipper => chipper(f, self.1),
+            Syntax::Octo => octo(f, self.1),
+        }
+    }
+}
+
+fn fe(f: &mut fmt::Formatter, op: &Opcode) -> fmt::Result {
+    let (x, y, n) = (op.n1, op.n2, op.n3);
+    let mnemonic = op.mnemonic();
+    if mnemonic == "???" {
+        return write!(f, "??? {:02X}{:02X}", (op.n0 << 4) | x, op.v);
+    }
+    f.write_str(mnemonic)?;
+    match op.n0 {
+        0x0 => Ok(()),
+        0x1 | 0x2 | 0xA | 0xB => write!(f, " {:03X}", op.a),
+        0x3 | 0x4 | 0x6 | 0x7 | 0xC => write!(f, " V{:X} {:02X}", x, op.v),
+        0x5 | 0x8 | 0x9 => write!(f, " V{:X} V{:X}", x, y),
+        0xD => write!(f, " V{:X} V{:X} {:X}", x, y, n),
+        _ => write!(f, " V{:X}", x),
+    }
+}
+
+fn chipper(f: &mut fmt::Formatter, op: &Opcode) -> fmt::Result {
+    let (x, y, n) = (op.n1, op.n2, op.n3);
+    let (nnn, kk) = (op.a, op.v);
+    match (op.n0, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => f.write_str("CLS"),
+        (0x0, 0x0, 0xE, 0xE) => f.write_str("RET"),
+        (0x1, ..) => write!(f, "JP #{:03X}", nnn),
+        (0x2, ..) => write!(f, "CALL #{:03X}", nnn),
+        (0x3, ..) => write!(f, "SE V{:X}, #{:02X}", x, kk),
+        (0x4, ..) => write!(f, "SNE V{:X}, #{:02X}", x, kk),
+        (0x5, _, _, 0x0) => write!(f, "SE V{:X}, V{:X}", x, y),
+        (0x6, ..) => write!(f, "LD V{:X}, #{:02X}", x, kk),
+        (0x7, ..) => write!(f, "ADD V{:X}, #{:02X}", x, kk),
+        (0x8, _, _, 0x0) => write!(f, "LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => write!(f, "OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => write!(f, "AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => write!(f, "XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => write!(f, "ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => write!(f, "SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => write!(f, "SHR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x7) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xE) => write!(f, "SHL V{:X}, V{:X}", x, y),
+        (0x9, _, _, 0x0) => write!(f, "SNE V{:X}, V{:X}", x, y),
+        (0xA, ..) => write!(f, "LD I, #{:03X}", nnn),
+        (0xB, ..) => write!(f, "JP V0, #{:03X}", nnn),
+        (0xC, ..) => write!(f, "RND V{:X}, #{:02X}", x, kk),
+        (0xD, ..) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => write!(f, "SKP V{:X}", x),
+        (0xE, _, 0xA, 0x1) => write!(f, "SKNP V{:X}", x),
+        (0xF, _, 0x0, 0x7) => write!(f, "LD V{:X}, DT", x),
+        (0xF, _, 0x0, 0xA) => write!(f, "LD V{:X}, K", x),
+        (0xF, _, 0x1, 0x5) => write!(f, "LD DT, V{:X}", x),
+        (0xF, _, 0x1, 0x8) => write!(f, "LD ST, V{:X}", x),
+        (0xF, _, 0x1, 0xE) => write!(f, "ADD I, V{:X}", x),
+        (0xF, _, 0x2, 0x9) => write!(f, "LD F, V{:X}", x),
+        (0xF, _, 0x3, 0x3) => write!(f, "LD B, V{:X}", x),
+        (0xF, _, 0x5, 0x5) => write!(f, "LD [I], V{:X}", x),
+        (0xF, _, 0x6, 0x5) => write!(f, "LD V{:X}, [I]", x),
+        _ => write!(f, "DW #{:02X}{:02X}", (op.n0 << 4) | x, kk),
+    }
+}
+
+fn octo(f: &mut fmt::Formatter, op: &Opcode) -> fmt::Result {
+    let (x, y, n) = (op.n1, op.n2, op.n3);
+    let (nnn, kk) = (op.a, op.v);
+    // Skips are written as the condition under which the next instruction runs
+    match (op.n0, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => f.write_str("clear"),
+        (0x0, 0x0, 0xE, 0xE) => f.write_str("return"),
+        (0x1, ..) => write!(f, "jump 0x{:03X}", nnn),
+        (0x2, ..) => write!(f, ":call 0x{:03X}", nnn),
+        (0x3, ..) => write!(f, "if v{:x} != 0x{:02X} then", x, kk),
+        (0x4, ..) => write!(f, "if v{:x} == 0x{:02X} then", x, kk),
+        (0x5, _, _, 0x0) => write!(f, "if v{:x} != v{:x} then", x, y),
+        (0x6, ..) => write!(f, "v{:x} := 0x{:02X}", x, kk),
+        (0x7, ..) => write!(f, "v{:x} += 0x{:02X}", x, kk),
+        (0x8, _, _, 0x0) => write!(f, "v{:x} := v{:x}", x, y),
+        (0x8, _, _, 0x1) => write!(f, "v{:x} |= v{:x}", x, y),
+        (0x8, _, _, 0x2) => write!(f, "v{:x} &= v{:x}", x, y),
+        (0x8, _, _, 0x3) => write!(f, "v{:x} ^= v{:x}", x, y),
+        (0x8, _, _, 0x4) => write!(f, "v{:x} += v{:x}", x, y),
+        (0x8, _, _, 0x5) => write!(f, "v{:x} -= v{:x}", x, y),
+        (0x8, _, _, 0x6) => write!(f, "v{:x} >>= v{:x}", x, y),
+        (0x8, _, _, 0x7) => write!(f, "v{:x} =- v{:x}", x, y),
+        (0x8, _, _, 0xE) => write!(f, "v{:x} <<= v{:x}", x, y),
+        (0x9, _, _, 0x0) => write!(f, "if v{:x} == v{:x} then", x, y),
+        (0xA, ..) => write!(f, "i := 0x{:03X}", nnn),
+        (0xB, ..) => write!(f, "jump0 0x{:03X}", nnn),
+        (0xC, ..) => write!(f, "v{:x} := random 0x{:02X}", x, kk),
+        (0xD, ..) => write!(f, "sprite v{:x} v{:x} {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => write!(f, "if v{:x} -key then", x),
+        (0xE, _, 0xA, 0x1) => write!(f, "if v{:x} key then", x),
+        (0xF, _, 0x0, 0x7) => write!(f, "v{:x} := delay", x),
+        (0xF, _, 0x0, 0xA) => write!(f, "v{:x} := key", x),
+        (0xF, _, 0x1, 0x5) => write!(f, "delay := v{:x}", x),
+        (0xF, _, 0x1, 0x8) => write!(f, "buzzer := v{:x}", x),
+        (0xF, _, 0x1, 0xE) => write!(f, "i += v{:x}", x),
+        (0xF, _, 0x2, 0x9) => write!(f, "i := hex v{:x}", x),
+        (0xF, _, 0x3, 0x3) => write!(f, "bcd v{:x}", x),
+        (0xF, _, 0x5, 0x5) => write!(f, "save v{:x}", x),
+        (0xF, _, 0x6, 0x5) => write!(f, "load v{:x}", x),
+        _ => write!(f, "0x{:02X} 0x{:02X}", (op.n0 << 4) | x, kk),
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    error::Error,
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    result::Result,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+const IAC: u8 = 255;
+const WILL: u8 = 251;
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+
+/// Keys typed by the controlling connection, in the same layout as the local keyboard.
+const KEY_CHARS: &[u8; 16] = b"x123qweasdzc4rfv";
+
+/// How long a frame may take to reach a viewer before it is disconnected,
+/// so one slow link can't stall the emulator.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(50);
+
+struct Client {
+    id: u64,
+    stream: TcpStream,
+}
+
+/// Serves the rendered TUI to any number of telnet viewers. The longest
+/// connected client controls the keypad; everyone else just watches.
+pub struct TelnetServer {
+    clients: Arc<Mutex<Vec<Client>>>,
+    keys: Receiver<usize>,
+    joined: Arc<AtomicBool>,
+}
+
+impl TelnetServer {
+    pub fn bind(addr: &str) -> Result<TelnetServer, Box<dyn Error>> {
+        let listener = TcpListener::bind(addr)?;
+        let clients = Arc::new(Mutex::new(Vec::<Client>::new()));
+        let (sender, keys) = channel();
+        let accepting = clients.clone();
+        let joined = Arc::new(AtomicBool::new(false));
+        let joining = joined.clone();
+        thread::spawn(move || {
+            for (id, stream) in (0..).zip(listener.incoming().flatten()) {
+                let Ok(mut writer) = stream.try_clone() else {
+                    continue;
+                };
+                // Character at a time with no local echo, then a clean screen
+                let setup = [IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD];
+                let _ = writer.write_all(&setup);
+                let _ = writer.write_all(b"\x1b[2J\x1b[?25l");
+                let _ = writer.set_write_timeout(Some(WRITE_TIMEOUT));
+                let _ = writer.set_nodelay(true);
+                accepting
+                    .lock()
+                    .unwrap()
+                    .push(Client { id, stream: writer });
+                joining.store(true, Ordering::Relaxed);
+                let clients = accepting.clone();
+                let sender = sender.clone();
+                thread::spawn(move || read_keys(id, stream, clients, sender));
+            }
+        });
+        Ok(TelnetServer {
+            clients,
+            keys,
+            joined,
+        })
+    }
+
+    /// Sends a rendered frame to every viewer, dropping any that can't keep up.
+    pub fn broadcast(&self, frame: &[u8]) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| client.stream.write_all(frame).is_ok());
+    }
+
+    /// Whether anyone connected since the last call.
+    pub fn joined(&self) -> bool {
+        self.joined.swap(false, Ordering::Relaxed)
+    }
+
+    /// Keys typed by the controlling connection since the last call.
+    pub fn pressed_keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.keys.try_iter()
+    }
+}
+
+fn read_keys(
+    id: u64,
+    mut stream: TcpStream,
+    clients: Arc<Mutex<Vec<Client>>>,
+    keys: Sender<usize>,
+) {
+    let mut buf = [0; 64];
+    let mut skip = 0;
+    while let Ok(len) = stream.read(&mut buf) {
+        if len == 0 {
+            break;
+        }
+        let controlling = clients.lock().unwrap().first().map(|c| c.id) == Some(id);
+        for &byte in &buf[..len] {
+            // Drop the client's option negotiation, which is always three bytes here
+            if byte == IAC || skip > 0 {
+                skip = if byte == IAC && skip == 0 {
+                    2
+                } else {
+                    skip - 1
+                };
+                continue;
+            }
+            let key = KEY_CHARS
+                .iter()
+                .position(|c| *c == byte.to_ascii_lowercase());
+            if let (true, Some(key)) = (controlling, key) {
+                if keys.send(key).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+    clients.lock().unwrap().retain(|client| client.id != id);
+}
@@ -0,0 +1,81 @@
+use crate::TIMER_RATE;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+/// Frames at the chosen refresh rate, each owing the game however many of
+/// its 60Hz ticks have come due since the last, so that timers and
+/// instructions keep to time whatever rate the screen is drawn at.
+pub struct Pacing {
+    /// Frames a second.
+    refresh: u32,
+    /// Sixtieths of a second passed and not yet ticked, in frames.
+    owed: u32,
+}
+
+impl Pacing {
+    pub fn new(refresh: u32) -> Pacing {
+        Pacing { refresh, owed: 0 }
+    }
+
+    pub fn refresh(&self) -> u32 {
+        self.refresh
+    }
+
+    pub fn frame(&self) -> Duration {
+        Duration::from_secs(1) / self.refresh
+    }
+
+    /// How many ticks this frame runs, carrying what's left over to the
+    /// next: at 50Hz one and sometimes two, at 30Hz always two.
+    pub fn ticks(&mut self) -> u32 {
+        self.owed += TIMER_RATE;
+        let ticks = self.owed / self.refresh;
+        self.owed %= self.refresh;
+        ticks
+    }
+}
+
+/// Where one frame's time went.
+#[derive(Default)]
+pub struct FrameTiming {
+    /// From the start of the previous frame to the start of this one.
+    pub frame: Duration,
+    pub emulate: Duration,
+    pub render: Duration,
+    /// Spent waiting for this frame to be due.
+    pub sleep: Duration,
+}
+
+/// Every frame's timing, kept until exit to be written out as CSV.
+#[derive(Default)]
+pub struct TimingReport {
+    frames: Vec<FrameTiming>,
+}
+
+impl TimingReport {
+    pub fn push(&mut self, timing: FrameTiming) {
+        self.frames.push(timing);
+    }
+
+    /// One row per frame, in microseconds.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "frame,frame_us,emulate_us,render_us,sleep_us")?;
+        for (number, timing) in self.frames.iter().enumerate() {
+            writeln!(
+                out,
+                "{},{},{},{},{}",
+                number,
+                timing.frame.as_micros(),
+                timing.emulate.as_micros(),
+                timing.render.as_micros(),
+                timing.sleep.as_micros()
+            )?;
+        }
+        out.flush()
+    }
+}
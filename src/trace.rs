@@ -0,0 +1,46 @@
+use crate::{
+    chip8::{Chip8, Opcode},
+    syntax::Syntax,
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// A log of every instruction executed, with the registers as they were just
+/// before it ran, for comparing runs against each other or other emulators.
+pub struct Trace {
+    out: BufWriter<File>,
+    syntax: Syntax,
+}
+
+impl Trace {
+    pub fn create(path: &Path, syntax: Syntax) -> io::Result<Trace> {
+        Ok(Trace {
+            out: BufWriter::new(File::create(path)?),
+            syntax,
+        })
+    }
+
+    /// Writes the instruction `chip8` is about to execute.
+    pub fn record(&mut self, chip8: &Chip8) {
+        let addr = chip8.pc as usize;
+        if addr + 2 > chip8.memory.len() {
+            return;
+        }
+        let bytes = &chip8.memory[addr..];
+        let registers: String = chip8.v.iter().map(|v| format!("{:02X}", v)).collect();
+        // A trace that can't be written isn't worth stopping the game for
+        let _ = writeln!(
+            self.out,
+            "{:03X}  {:02X}{:02X}  I={:03X} V={}  {}",
+            addr,
+            bytes[0],
+            bytes[1],
+            chip8.i,
+            registers,
+            self.syntax.format(&Opcode::from_slice(bytes))
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{chip8::ADDR_START_PROGRAM, options::parse_address};
+use std::{error::Error, fs, result::Result};
+
+const USAGE: &str = "usage: fe_o8 tracediff A.log B.log [--context N] [--entry ADDR]";
+
+/// What a trace line says about the machine. Only the address and opcode
+/// are needed; registers are compared when both traces give them.
+struct Step {
+    pc: u16,
+    opcode: u16,
+    i: Option<u16>,
+    v: [Option<u8>; 16],
+}
+
+impl Step {
+    /// Reads `--trace` lines, `200  00E0  I=000 V=00...  CLS`, and those of
+    /// emulators that write the address (maybe as `PC:0200`) and opcode
+    /// first with the registers after as `I:0200`, `V3=1F` and the like.
+    fn parse(line: &str) -> Option<Step> {
+        let mut words = line.split_whitespace();
+        let hex = |word: &str| u16::from_str_radix(word.trim_end_matches(':'), 16).ok();
+        let pc = hex(words
+            .next()?
+            .rsplit(['=', ':'])
+            .find(|part| !part.is_empty())?)?;
+        let opcode = words.next().filter(|word| word.len() == 4).and_then(hex)?;
+        let mut step = Step {
+            pc,
+            opcode,
+            i: None,
+            v: [None; 16],
+        };
+        for word in words {
+            let Some((name, value)) = word.split_once(['=', ':']) else {
+                continue;
+            };
+            match name.to_ascii_uppercase().as_str() {
+                "I" => step.i = hex(value),
+                "V" if value.len() == 32 => {
+                    for (v, byte) in step.v.iter_mut().zip(value.as_bytes().chunks(2)) {
+                        *v = std::str::from_utf8(byte)
+                            .ok()
+                            .and_then(|byte| u8::from_str_radix(byte, 16).ok());
+                    }
+                }
+                name => {
+                    let register = name.strip_prefix('V').and_then(&hex);
+                    if let Some(x @ 0..=0xF) = register {
+                        step.v[x as usize] = u8::from_str_radix(value, 16).ok();
+                    }
+                }
+            }
+        }
+        Some(step)
+    }
+
+    /// What differs from `other`, among what both say.
+    fn differences(&self, other: &Step) -> Vec<String> {
+        let mut differences = vec![];
+        if self.pc != other.pc {
+            differences.push(format!("PC {:03X} vs {:03X}", self.pc, other.pc));
+        }
+        if self.opcode != other.opcode {
+            differences.push(format!(
+                "opcode {:04X} vs {:04X}",
+                self.opcode, other.opcode
+            ));
+        }
+        if let (Some(a), Some(b)) = (self.i, other.i) {
+            if a != b {
+                differences.push(format!("I {:03X} vs {:03X}", a, b));
+            }
+        }
+        for (x, (a, b)) in self.v.iter().zip(other.v).enumerate() {
+            if let (Some(a), Some(b)) = (a, b) {
+                if *a != b {
+                    differences.push(format!("V{:X} {:02X} vs {:02X}", x, a, b));
+                }
+            }
+        }
+        differences
+    }
+}
+
+/// A trace's instructions from the first at `entry`, where the program starts,
+/// so a trace that includes an emulator's own start up still lines up.
+fn read(path: &str, entry: u16) -> Result<Vec<(String, Step)>, Box<dyn Error>> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut steps: Vec<(String, Step)> = text
+        .lines()
+        .filter_map(|line| Some((line.to_string(), Step::parse(line)?)))
+        .collect();
+    let start = steps.iter().position(|(_, step)| step.pc == entry);
+    steps.drain(..start.unwrap_or(0));
+    Ok(steps)
+}
+
+/// Compares two instruction traces, such as fe_o8's `--trace` and another
+/// emulator's, and shows where they first part ways along with the
+/// instructions leading up to it. Fails if they do.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let mut paths = vec![];
+    let mut context = 5;
+    let mut entry = ADDR_START_PROGRAM;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--context" => context = args.next().ok_or(USAGE)?.parse()?,
+            "--entry" => entry = parse_address(&args.next().ok_or(USAGE)?)?,
+            _ => paths.push(arg),
+        }
+    }
+    let [a_path, b_path] = paths.as_slice() else {
+        return Err(USAGE.into());
+    };
+    let (a, b) = (read(a_path, entry)?, read(b_path, entry)?);
+
+    let differs = (a.iter().zip(&b)).position(|((_, a), (_, b))| !a.differences(b).is_empty());
+    let Some(index) = differs else {
+        let (longer, shorter) = match a.len() >= b.len() {
+            true => ((a_path, a.len()), (b_path, b.len())),
+            false => ((b_path, b.len()), (a_path, a.len())),
+        };
+        println!("the traces agree for {} instructions", shorter.1);
+        if longer.1 > shorter.1 {
+            println!(
+                "{} ends there; {} goes on for {} more",
+                shorter.0,
+                longer.0,
+                longer.1 - shorter.1
+            );
+        }
+        return Ok(());
+    };
+
+    println!("the traces agree for {} instructions, then differ:", index);
+    for (line, _) in &a[index.saturating_sub(context)..index] {
+        println!("    {}", line);
+    }
+    println!("A > {}", a[index].0);
+    println!("B > {}", b[index].0);
+    println!("    {}", a[index].1.differences(&b[index].1).join(", "));
+    for (name, steps) in [("A", &a), ("B", &b)] {
+        let end = steps.len().min(index + 1 + context);
+        for (line, _) in &steps[index + 1..end] {
+            println!("{}   {}", name, line);
+        }
+    }
+    Err(format!("the traces differ at instruction {}", index).into())
+}
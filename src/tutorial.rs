@@ -0,0 +1,367 @@
+use crate::{
+    chip8::{Chip8, Opcode, SideEffects, ADDR_START_PROGRAM},
+    explain::explain,
+    syntax::Syntax,
+    ui::{screen, Palette},
+};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::Stylize,
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use std::{error::Error, result::Result};
+
+/// Counts from 7 to 9 across the screen in the built in font.
+const PROGRAM: &[u8] = &[
+    0x00, 0xE0, // 200  clear the screen
+    0x60, 0x08, // 202  V0 = 8, the x coordinate
+    0x61, 0x04, // 204  V1 = 4, the y coordinate
+    0x62, 0x07, // 206  V2 = 7, the digit to draw
+    0xF2, 0x29, // 208  point I at the font sprite for V2
+    0xD0, 0x15, // 20A  draw it, 5 rows tall
+    0x72, 0x01, // 20C  next digit
+    0x70, 0x06, // 20E  move right
+    0x32, 0x0A, // 210  skip the jump once V2 reaches 10
+    0x12, 0x08, // 212  back to drawing
+    0x12, 0x14, // 214  stop by jumping here forever
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Fetch,
+    Decode,
+    Execute,
+}
+
+/// The registers and memory as they were before the last instruction ran,
+/// to pick out what it changed.
+struct Before {
+    pc: u16,
+    i: u16,
+    v: [u8; 16],
+    delay: u8,
+    sound: u8,
+    depth: usize,
+    memory: Vec<u8>,
+}
+
+struct Tutorial {
+    chip8: Chip8,
+    phase: Phase,
+    steps: usize,
+    before: Option<Before>,
+    effects: SideEffects,
+    /// What the last instruction did, worked out before it ran.
+    explanation: String,
+}
+
+impl Tutorial {
+    fn new() -> Tutorial {
+        let mut chip8 = Chip8::new();
+        chip8.load(PROGRAM);
+        Tutorial {
+            chip8,
+            phase: Phase::Fetch,
+            steps: 0,
+            before: None,
+            effects: SideEffects::default(),
+            explanation: String::new(),
+        }
+    }
+
+    fn op(&self) -> Opcode {
+        Opcode::from_slice(&self.chip8.memory[self.chip8.pc as usize..])
+    }
+
+    fn advance(&mut self) {
+        self.phase = match self.phase {
+            Phase::Fetch => Phase::Decode,
+            Phase::Decode => {
+                let chip8 = &self.chip8;
+                self.before = Some(Before {
+                    pc: chip8.pc,
+                    i: chip8.i,
+                    v: chip8.v,
+                    delay: chip8.delay,
+                    sound: chip8.sound,
+                    depth: chip8.stack.len(),
+                    memory: chip8.memory.clone(),
+                });
+                self.explanation = explain(chip8, Syntax::default());
+                match self.chip8.step() {
+                    Ok(effects) => self.effects = effects,
+                    Err(error) => {
+                        self.effects = SideEffects::default();
+                        self.explanation = format!("{}, which halts: {}", self.explanation, error);
+                    }
+                }
+                self.steps += 1;
+                Phase::Execute
+            }
+            Phase::Execute => {
+                self.before = None;
+                Phase::Fetch
+            }
+        };
+    }
+
+    /// What's happening in this phase, in a few sentences.
+    fn narration(&self) -> Vec<Line<'static>> {
+        let chip8 = &self.chip8;
+        let pc = chip8.pc as usize;
+        let bytes = [chip8.memory[pc], chip8.memory[pc + 1]];
+        let op = self.op();
+        match self.phase {
+            Phase::Fetch => vec![
+                "Fetch".bold().into(),
+                Line::raw(format!(
+                    "The interpreter reads the two bytes at PC, {:03X}: {:02X} and {:02X}. \
+                     Together they make the opcode {:02X}{:02X}. \
+                     PC moves on to {:03X} so the next fetch gets the next instruction.",
+                    pc,
+                    bytes[0],
+                    bytes[1],
+                    bytes[0],
+                    bytes[1],
+                    pc + 2
+                )),
+            ],
+            Phase::Decode => vec![
+                "Decode".bold().into(),
+                Line::raw(decode(&op)),
+                Line::raw(format!(
+                    "Written out: {} (CHIPPER {}, Octo {})",
+                    Syntax::Fe.format(&op),
+                    Syntax::Chipper.format(&op),
+                    Syntax::Octo.format(&op)
+                )),
+            ],
+            Phase::Execute => {
+                let mut lines = vec![
+                    "Execute".bold().into(),
+                    Line::raw(self.explanation.clone()),
+                    Line::raw("What changed is highlighted."),
+                ];
+                if self.effects.display {
+                    lines.push(Line::raw(
+                        "The display changed, so a frontend would draw it again.",
+                    ));
+                }
+                if self.effects.halted {
+                    lines.push(Line::raw(
+                        "The program has ended the usual way, by jumping to itself forever. \
+                         Press r to run it again.",
+                    ));
+                }
+                lines
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, narration] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(32 + 2),
+            Constraint::Min(0),
+        ])
+        .areas(frame.area());
+        let [display, side] =
+            Layout::horizontal([Constraint::Length(64 * 2 + 2), Constraint::Min(0)]).areas(body);
+        let [registers, memory] =
+            Layout::vertical([Constraint::Length(9), Constraint::Min(0)]).areas(side);
+
+        frame.render_widget(
+            Line::from(vec![
+                format!(" tutorial, instruction {} ", self.steps + 1).bold(),
+                " Space steps through fetch, decode and execute, r starts over, Esc quits".into(),
+            ]),
+            header,
+        );
+        let palette = Palette::default();
+        let block = Block::bordered().title(" display ");
+        frame.render_widget(screen(&self.chip8, &palette), block.inner(display));
+        frame.render_widget(block, display);
+        frame.render_widget(
+            Paragraph::new(self.registers()).block(Block::bordered().title(" registers ")),
+            registers,
+        );
+        frame.render_widget(
+            Paragraph::new(self.memory()).block(Block::bordered().title(" program ")),
+            memory,
+        );
+        frame.render_widget(
+            Paragraph::new(self.narration())
+                .wrap(Wrap { trim: true })
+                .block(Block::bordered()),
+            narration,
+        );
+    }
+
+    fn registers(&self) -> Vec<Line<'static>> {
+        let chip8 = &self.chip8;
+        let before = self.before.as_ref();
+        let changed = |text: String, changed: bool| -> Span<'static> {
+            if changed {
+                text.black().on_yellow()
+            } else {
+                text.into()
+            }
+        };
+        let mut lines = vec![Line::from(vec![
+            changed(
+                format!("PC {:03X}", chip8.pc),
+                before.is_some_and(|b| b.pc != chip8.pc),
+            ),
+            "  ".into(),
+            changed(
+                format!("I {:03X}", chip8.i),
+                before.is_some_and(|b| b.i != chip8.i),
+            ),
+        ])];
+        for row in 0..4 {
+            let mut spans = vec![];
+            for n in row * 4..row * 4 + 4 {
+                spans.push(changed(
+                    format!("V{:X} {:02X}", n, chip8.v[n]),
+                    before.is_some_and(|b| b.v[n] != chip8.v[n]),
+                ));
+                spans.push(" ".into());
+            }
+            lines.push(Line::from(spans));
+        }
+        lines.push(Line::from(vec![
+            changed(
+                format!("DT {:02X}", chip8.delay),
+                before.is_some_and(|b| b.delay != chip8.delay),
+            ),
+            " ".into(),
+            changed(
+                format!("ST {:02X}", chip8.sound),
+                before.is_some_and(|b| b.sound != chip8.sound),
+            ),
+        ]));
+        lines.push(Line::from(changed(
+            format!("stack depth {}", chip8.stack.len()),
+            before.is_some_and(|b| b.depth != chip8.stack.len()),
+        )));
+        lines
+    }
+
+    /// The program in memory, the instruction being fetched or decoded in
+    /// reverse and anything just written in yellow.
+    fn memory(&self) -> Vec<Line<'static>> {
+        let chip8 = &self.chip8;
+        let start = ADDR_START_PROGRAM as usize;
+        let current = chip8.pc as usize..chip8.pc as usize + 2;
+        (start..start + PROGRAM.len())
+            .step_by(2)
+            .map(|addr| {
+                let op = Opcode::from_slice(&chip8.memory[addr..]);
+                let mut spans = vec![Span::raw(format!("{:03X}  ", addr))];
+                for addr in addr..addr + 2 {
+                    let text = format!("{:02X}", chip8.memory[addr]);
+                    let written = (self.before.as_ref())
+                        .is_some_and(|b| b.memory[addr] != chip8.memory[addr]);
+                    spans.push(if written {
+                        text.black().on_yellow()
+                    } else if self.phase != Phase::Execute && current.contains(&addr) {
+                        text.reversed()
+                    } else {
+                        text.into()
+                    });
+                }
+                spans.push(format!("  {}", Syntax::Fe.format(&op)).dark_gray());
+                Line::from(spans)
+            })
+            .collect()
+    }
+}
+
+/// How the nibbles of an opcode pick the instruction and its operands.
+fn decode(op: &Opcode) -> String {
+    let (x, y, n) = (op.n1, op.n2, op.n3);
+    let operands = match op.n0 {
+        0x0 => "The first nibble 0 is a group of instructions with no operands, \
+                picked by the rest: 00E0 clears the screen and 00EE returns."
+            .to_string(),
+        0x1 => format!("1NNN jumps to NNN, here {:03X}.", op.a),
+        0x2 => format!("2NNN calls the subroutine at NNN, here {:03X}.", op.a),
+        0x3 => format!(
+            "3XNN skips the next instruction if VX equals NN: X is {:X}, NN is {:02X}.",
+            x, op.v
+        ),
+        0x4 => format!(
+            "4XNN skips the next instruction unless VX equals NN: X is {:X}, NN is {:02X}.",
+            x, op.v
+        ),
+        0x5 => format!(
+            "5XY0 skips the next instruction if VX equals VY: X is {:X}, Y is {:X}.",
+            x, y
+        ),
+        0x6 => format!("6XNN sets VX to NN: X is {:X}, NN is {:02X}.", x, op.v),
+        0x7 => format!("7XNN adds NN to VX: X is {:X}, NN is {:02X}.", x, op.v),
+        0x8 => format!(
+            "8XYN does arithmetic on VX and VY, the last nibble choosing which: \
+             X is {:X}, Y is {:X}, N is {:X}.",
+            x, y, n
+        ),
+        0x9 => format!(
+            "9XY0 skips the next instruction unless VX equals VY: X is {:X}, Y is {:X}.",
+            x, y
+        ),
+        0xA => format!("ANNN points I at NNN, here {:03X}.", op.a),
+        0xB => format!("BNNN jumps to NNN plus V0, NNN here {:03X}.", op.a),
+        0xC => format!(
+            "CXNN sets VX to a random byte masked with NN: X is {:X}, NN is {:02X}.",
+            x, op.v
+        ),
+        0xD => format!(
+            "DXYN draws the N rows of sprite at I to (VX, VY): X is {:X}, Y is {:X}, N is {}.",
+            x, y, n
+        ),
+        0xE => format!(
+            "EX9E and EXA1 skip depending on whether the key in VX is held: X is {:X}.",
+            x
+        ),
+        _ => format!(
+            "FXNN works with VX and the timers, keypad, font or memory, NN choosing which: \
+             X is {:X}, NN is {:02X}.",
+            x, op.v
+        ),
+    };
+    format!(
+        "The first nibble, {:X}, says what kind of instruction this is. {}",
+        op.n0, operands
+    )
+}
+
+/// Walks through a small built in program an instruction at a time,
+/// explaining how an interpreter fetches, decodes and executes each one.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let mut terminal = ratatui::init();
+    let result = tutor(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+fn tutor(terminal: &mut DefaultTerminal) -> Result<(), Box<dyn Error>> {
+    let mut tutorial = Tutorial::new();
+    loop {
+        terminal.draw(|frame| tutorial.draw(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+            KeyCode::Char(' ') | KeyCode::Enter | KeyCode::Right => tutorial.advance(),
+            KeyCode::Char('r') => tutorial = Tutorial::new(),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,1676 @@
+use crate::{
+    analysis,
+    background::Background,
+    chip8::{Chip8, Chip8Error, Class, Opcode},
+    debugger::Debugger,
+    explain::explain,
+    input::{key_name, Action, Hotkeys},
+    speedrun::Speedrun,
+    syntax::Syntax,
+    watchdog::Stuck,
+    INSTRUCTIONS_PER_FRAME, TIMER_RATE,
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    widgets::{Block, Clear, Widget},
+};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt::{self, Write},
+    hash::{DefaultHasher, Hash, Hasher},
+    ops::Range,
+    result::Result,
+    time::{Duration, Instant},
+};
+
+/// Where the sound indicator goes on the status line, clear of the frame rate.
+const SPEAKER_COLUMN: usize = 36;
+
+/// How long the newest log message stays on the line under the frame rate.
+const MESSAGE_TIME: Duration = Duration::from_secs(2);
+
+/// Messages kept for the log panel.
+const LOG_LENGTH: usize = 100;
+
+/// Frames of sound kept, more than the sound widget is wide.
+const SOUND_LENGTH: usize = 64;
+
+/// Columns inside the sound widget's border.
+const SOUND_WIDTH: u16 = 24;
+
+/// Display colors, indexed by which planes are lit at a pixel: 0 is the
+/// background, 1 and 2 are the first and second plane, and 3 is where they overlap.
+#[derive(Clone, Hash)]
+pub struct Palette {
+    colors: [Color; 4],
+    /// Draw the first plane's unlit pixels as lit and its lit ones as unlit,
+    /// for games drawn dark on light.
+    inverted: bool,
+}
+
+impl Palette {
+    /// The colors Octo uses by default.
+    const OCTO: [Color; 4] = [
+        Color::Rgb(0x99, 0x66, 0x00),
+        Color::Rgb(0xFF, 0xCC, 0x00),
+        Color::Rgb(0xFF, 0x66, 0x00),
+        Color::Rgb(0x66, 0x22, 0x00),
+    ];
+
+    /// Parses either a preset name (`default`, `octo`) or four comma separated
+    /// `#RRGGBB` colors in background, plane 1, plane 2, overlap order.
+    pub fn parse(spec: &str) -> Result<Palette, String> {
+        match spec {
+            "default" => return Ok(Palette::default()),
+            "octo" => {
+                return Ok(Palette {
+                    colors: Palette::OCTO,
+                    inverted: false,
+                })
+            }
+            _ => (),
+        }
+        let mut colors = [Color::Reset; 4];
+        let parts: Vec<&str> = spec.split(',').collect();
+        if parts.len() != colors.len() {
+            return Err(format!("expected 4 colors in palette '{}'", spec));
+        }
+        for (color, part) in colors.iter_mut().zip(parts) {
+            let hex = part.trim().trim_start_matches('#');
+            let rgb = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .ok_or(format!("invalid color '{}'", part))?;
+            *color = Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8);
+        }
+        Ok(Palette {
+            colors,
+            inverted: false,
+        })
+    }
+
+    pub fn from_rgb(colors: [[u8; 3]; 4]) -> Palette {
+        Palette {
+            colors: colors.map(|[r, g, b]| Color::Rgb(r, g, b)),
+            inverted: false,
+        }
+    }
+
+    /// The same colors, swapped between lit and unlit pixels.
+    pub fn inverted(&self) -> Palette {
+        Palette {
+            inverted: !self.inverted,
+            ..self.clone()
+        }
+    }
+
+    /// The block and its color for a pixel with the planes in `index` lit.
+    fn cell(&self, index: usize) -> (&'static str, Color) {
+        let index = index ^ self.inverted as usize;
+        (if index == 0 { "░" } else { "█" }, self.colors[index])
+    }
+
+    /// The color of a pixel with the planes in `index` lit, for pictures
+    /// made outside the terminal, where its own colors are black and white.
+    pub fn pixel_rgb(&self, index: usize) -> [u8; 3] {
+        let index = index ^ self.inverted as usize;
+        match self.colors[index] {
+            Color::Rgb(r, g, b) => [r, g, b],
+            _ if index == 0 => [0x00; 3],
+            _ => [0xFF; 3],
+        }
+    }
+
+    /// The colors as RGB, unless some are left to the terminal.
+    pub fn rgb(&self) -> Option<[[u8; 3]; 4]> {
+        let mut colors = [[0; 3]; 4];
+        for (rgb, color) in colors.iter_mut().zip(self.colors) {
+            let Color::Rgb(r, g, b) = color else {
+                return None;
+            };
+            *rgb = [r, g, b];
+        }
+        Some(colors)
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette {
+            colors: [Color::Reset; 4],
+            inverted: false,
+        }
+    }
+}
+
+/// How the memory strip lays memory out, as chosen on the command line.
+#[derive(Clone, Copy, Default)]
+pub struct MemoryMap {
+    /// Bytes per cell; by default, as many as fit memory into the rows.
+    pub cell: Option<usize>,
+    /// By default, as many as it takes to show all of memory.
+    pub rows: Option<usize>,
+}
+
+impl MemoryMap {
+    /// Bytes per cell and rows for a strip `width` cells wide.
+    fn layout(&self, memory: usize, width: usize) -> (usize, usize) {
+        match (self.cell, self.rows) {
+            (Some(cell), Some(rows)) => (cell, rows),
+            (Some(cell), None) => (cell, memory.div_ceil(width * cell)),
+            (None, rows) => {
+                let rows = rows.unwrap_or(1);
+                (memory.div_ceil(width * rows), rows)
+            }
+        }
+    }
+}
+
+/// Recent messages; the newest is also shown under the frame rate for a couple of seconds.
+#[derive(Default)]
+pub struct Log {
+    entries: VecDeque<(Instant, String)>,
+}
+
+impl Log {
+    pub fn push(&mut self, text: String) {
+        if self.entries.len() == LOG_LENGTH {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((Instant::now(), text));
+    }
+
+    /// The newest message, if it was pushed recently enough to still show.
+    fn current(&self) -> Option<&str> {
+        self.entries
+            .back()
+            .filter(|(time, _)| time.elapsed() < MESSAGE_TIME)
+            .map(|(_, text)| text.as_str())
+    }
+}
+
+/// What the program has written through peripherals like the debug port,
+/// kept a line at a time.
+#[derive(Default)]
+pub struct Console {
+    lines: VecDeque<String>,
+}
+
+impl Console {
+    /// Adds text, showing anything that isn't printable ASCII as a hex escape.
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            if self.lines.is_empty() {
+                self.lines.push_back(String::new());
+            }
+            match byte {
+                b'\n' => {
+                    if self.lines.len() == LOG_LENGTH {
+                        self.lines.pop_front();
+                    }
+                    self.lines.push_back(String::new());
+                }
+                _ => {
+                    let line = self.lines.back_mut().unwrap();
+                    if (0x20..=0x7E).contains(byte) {
+                        line.push(*byte as char);
+                    } else {
+                        line.push_str(&format!("\\x{:02X}", byte));
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+/// The sound timer frame by frame, for lining beeps up with what the game
+/// was doing when they started and stopped.
+#[derive(Default)]
+pub struct SoundHistory {
+    /// Frames the game has run.
+    frame: u64,
+    timers: VecDeque<u8>,
+}
+
+impl SoundHistory {
+    /// Notes the sound timer of a frame the game ran, before it counts down.
+    pub fn record(&mut self, sound: u8) {
+        if self.timers.len() == SOUND_LENGTH {
+            self.timers.pop_front();
+        }
+        self.timers.push_back(sound);
+        self.frame += 1;
+    }
+
+    /// A column per recent frame across `row` of the area, newest on the
+    /// right: a wave where the beeper sounded, moving along with the frames,
+    /// and a flat line where it didn't.
+    fn draw(&self, buf: &mut Buffer, area: Rect, row: u16) {
+        const WAVE: [char; 6] = ['▁', '▃', '▅', '▇', '▅', '▃'];
+        let shown = self.timers.len().min(area.width as usize);
+        let first = self.frame - shown as u64;
+        let mut trace = Cells::new(buf, area, row);
+        trace.column((area.width as usize - shown) as u16);
+        for (frame, &sound) in (first..).zip(self.timers.range(self.timers.len() - shown..)) {
+            let _ = match sound {
+                0 => trace.styled(Style::new().dark_gray()).write_char('─'),
+                _ => (trace.styled(Style::new().yellow()))
+                    .write_char(WAVE[frame as usize % WAVE.len()]),
+            };
+        }
+    }
+}
+
+/// A key going down or up, after the frame it happened in.
+struct InputEvent<'a>(&'a (u64, usize, bool));
+
+impl fmt::Display for InputEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let &(frame, key, down) = self.0;
+        write!(f, "{} {:X}{} ", frame, key, if down { '↓' } else { '↑' })
+    }
+}
+
+/// Keypad keys going down and up, stamped with the frame the game saw it,
+/// for telling what a program did with its input.
+#[derive(Default)]
+pub struct InputHistory {
+    /// Frames the game has run.
+    frame: u64,
+    keys: [bool; 16],
+    /// Frame, key and whether it went down.
+    events: VecDeque<(u64, usize, bool)>,
+}
+
+impl InputHistory {
+    /// Notes the keys the game is about to run a frame with.
+    pub fn record(&mut self, keys: [bool; 16]) {
+        for (key, (&now, was)) in keys.iter().zip(self.keys).enumerate() {
+            if now != was {
+                if self.events.len() == LOG_LENGTH {
+                    self.events.pop_front();
+                }
+                self.events.push_back((self.frame, key, now));
+            }
+        }
+        self.keys = keys;
+        self.frame += 1;
+    }
+
+    /// As many of the latest events as fit on the line, oldest first.
+    fn draw(&self, buf: &mut Buffer, area: Rect) {
+        let mut used = 0;
+        let fitting = (self.events.iter().rev())
+            .take_while(|event| {
+                used += text_width(format_args!("{}", InputEvent(event)));
+                used <= area.width as usize
+            })
+            .count();
+        let mut ticker = Cells::new(buf, area, 0);
+        for event in self.events.range(self.events.len() - fitting..) {
+            let style = match event.2 {
+                true => Style::new().green(),
+                false => Style::new().dark_gray(),
+            };
+            let _ = write!(ticker.styled(style), "{}", InputEvent(event));
+        }
+    }
+}
+
+/// What the debugging panels work out from the machine, kept from frame to
+/// frame so that working it out again reuses the same memory.
+#[derive(Default)]
+pub struct PanelData {
+    /// Memory as it was when the branch targets were found.
+    memory: Vec<u8>,
+    targets: BTreeSet<u16>,
+    hottest: Vec<(u16, u64)>,
+    busiest: Vec<(Option<u16>, u64)>,
+}
+
+impl PanelData {
+    /// Catches up with the machine, for the panels that are shown.
+    pub fn update(&mut self, chip8: &Chip8, debugger: &Debugger, panels: &Panels) {
+        if panels.disassembly && self.memory != chip8.memory {
+            self.memory.clone_from(&chip8.memory);
+            self.targets = analysis::branch_targets(&chip8.memory, chip8.entry);
+        }
+        if panels.profiler {
+            debugger.profiler.hottest(&mut self.hottest);
+        }
+        if panels.calls {
+            debugger.calls.busiest(&mut self.busiest);
+        }
+    }
+}
+
+/// A preset selection of panels, for what the emulator is being used for at the moment.
+#[derive(Clone, Copy)]
+pub enum Workspace {
+    /// Just the game and the keypad.
+    Play,
+    Debug,
+    /// Where the program spends its time.
+    Analyze,
+}
+
+impl Workspace {
+    pub fn name(self) -> &'static str {
+        match self {
+            Workspace::Play => "play",
+            Workspace::Debug => "debug",
+            Workspace::Analyze => "analyze",
+        }
+    }
+
+    /// The workspace the hotkey switches to from this one.
+    pub fn next(self) -> Workspace {
+        match self {
+            Workspace::Play => Workspace::Debug,
+            Workspace::Debug => Workspace::Analyze,
+            Workspace::Analyze => Workspace::Play,
+        }
+    }
+}
+
+/// Which of the panels around the game are drawn.
+#[derive(Hash)]
+pub struct Panels {
+    /// The frame rate line at the top.
+    pub status: bool,
+    pub keypad: bool,
+    pub registers: bool,
+    /// The delay and sound timers, as bars.
+    pub timers: bool,
+    pub memory: bool,
+    pub disassembly: bool,
+    pub log: bool,
+    pub profiler: bool,
+    /// Stack depth and the busiest subroutine over time, and each one's share.
+    pub calls: bool,
+    pub heatmap: bool,
+    /// The bytes around PC and I.
+    pub hex: bool,
+    /// Running totals drawn over the corner of the game.
+    pub counters: bool,
+    /// The sound timer and the beeper over recent frames, over the game's
+    /// other corner.
+    pub sound: bool,
+    /// Only drawn once there's something in it.
+    pub console: bool,
+    /// The latest keypad presses and releases, along the bottom.
+    pub inputs: bool,
+}
+
+impl Panels {
+    pub const NAMES: &'static [&'static str] = &[
+        "status",
+        "keypad",
+        "registers",
+        "timers",
+        "memory",
+        "disassembly",
+        "log",
+        "profiler",
+        "calls",
+        "heatmap",
+        "hex",
+        "counters",
+        "sound",
+        "console",
+        "inputs",
+    ];
+
+    fn by_name(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "status" => Some(&mut self.status),
+            "keypad" => Some(&mut self.keypad),
+            "registers" => Some(&mut self.registers),
+            "timers" => Some(&mut self.timers),
+            "memory" => Some(&mut self.memory),
+            "disassembly" => Some(&mut self.disassembly),
+            "log" => Some(&mut self.log),
+            "profiler" => Some(&mut self.profiler),
+            "calls" => Some(&mut self.calls),
+            "heatmap" => Some(&mut self.heatmap),
+            "hex" => Some(&mut self.hex),
+            "counters" => Some(&mut self.counters),
+            "sound" => Some(&mut self.sound),
+            "console" => Some(&mut self.console),
+            "inputs" => Some(&mut self.inputs),
+            _ => None,
+        }
+    }
+
+    /// Turns off the named panels, whatever the workspace wanted.
+    pub fn hide(mut self, names: &[String]) -> Panels {
+        for name in names {
+            if let Some(shown) = self.by_name(name) {
+                *shown = false;
+            }
+        }
+        self
+    }
+}
+
+impl From<Workspace> for Panels {
+    fn from(workspace: Workspace) -> Panels {
+        let none = Panels {
+            status: true,
+            keypad: false,
+            registers: false,
+            timers: false,
+            memory: false,
+            disassembly: false,
+            log: false,
+            profiler: false,
+            calls: false,
+            heatmap: false,
+            hex: false,
+            counters: false,
+            sound: false,
+            console: false,
+            inputs: false,
+        };
+        match workspace {
+            Workspace::Play => Panels {
+                keypad: true,
+                console: true,
+                ..none
+            },
+            Workspace::Debug => Panels {
+                registers: true,
+                timers: true,
+                memory: true,
+                disassembly: true,
+                hex: true,
+                console: true,
+                inputs: true,
+                ..none
+            },
+            Workspace::Analyze => Panels {
+                profiler: true,
+                calls: true,
+                heatmap: true,
+                ..none
+            },
+        }
+    }
+}
+
+/// Everything that goes on screen for one frame.
+pub struct View<'a> {
+    pub chip8: &'a Chip8,
+    pub debugger: &'a Debugger,
+    pub log: &'a Log,
+    pub console: &'a Console,
+    pub inputs: &'a InputHistory,
+    pub sounds: &'a SoundHistory,
+    pub data: &'a PanelData,
+    pub palette: &'a Palette,
+    pub panels: &'a Panels,
+    /// Draw just the game, as large as fits, and nothing else.
+    pub fullscreen: bool,
+    /// Leave out colors and text attributes, marking with characters
+    /// whatever was only told apart by them.
+    pub monochrome: bool,
+    /// Behind everything not given a background of its own.
+    pub background: Background,
+    /// The keyboard keys that press each keypad key, to label the keypad
+    /// with, while the legend is on.
+    pub legend: Option<&'a [char; 16]>,
+    /// Shown over the middle of the game, like the name of a ROM that just started.
+    pub title: Option<&'a str>,
+    pub frame_time: Duration,
+    /// Frames drawn a second, which the frame time is measured against.
+    pub refresh: u32,
+    /// How many instructions run each 60Hz tick, changed with the speed keys.
+    pub instructions_per_frame: usize,
+    pub paused: bool,
+    pub beeping: bool,
+    pub halted: Option<&'a Chip8Error>,
+    pub profile_label: &'a str,
+    pub syntax: Syntax,
+    pub memory_map: MemoryMap,
+    /// Bytes loaded from the ROM file.
+    pub rom_size: usize,
+    /// Which of how many recorded frames is on screen, while rewinding.
+    pub rewind: Option<(usize, usize)>,
+    pub speedrun: Option<&'a Speedrun>,
+    /// What the program seems to have stopped at, said over the game.
+    pub stuck: Option<Stuck>,
+    /// For naming the keys that get out of being stuck.
+    pub hotkeys: &'a Hotkeys,
+}
+
+impl View<'_> {
+    /// A hash of everything on screen except the game, whose changes the
+    /// machine tracks itself, and the frame rate, which changes every frame.
+    /// When it's the same as last frame's, drawing can be skipped.
+    pub fn fingerprint(&self) -> u64 {
+        let (chip8, debugger, panels) = (self.chip8, self.debugger, self.panels);
+        let mut hasher = DefaultHasher::new();
+        chip8.display.hash(&mut hasher);
+        (
+            panels,
+            self.fullscreen,
+            self.legend,
+            self.palette,
+            self.instructions_per_frame,
+            self.title,
+            self.paused,
+            self.beeping,
+        )
+            .hash(&mut hasher);
+        (self.halted, self.log.current()).hash(&mut hasher);
+        (self.rewind, self.speedrun, self.stuck).hash(&mut hasher);
+        if panels.keypad {
+            chip8.keys.hash(&mut hasher);
+        }
+        if panels.registers {
+            (chip8.v, &chip8.stack).hash(&mut hasher);
+        }
+        if panels.timers {
+            (chip8.delay, chip8.sound).hash(&mut hasher);
+        }
+        if panels.registers || panels.memory || panels.disassembly {
+            (chip8.pc, chip8.i, &debugger.breakpoints).hash(&mut hasher);
+            (&debugger.labels, &debugger.notes).hash(&mut hasher);
+        }
+        if panels.hex {
+            chip8.memory[hex_page(chip8, chip8.pc)].hash(&mut hasher);
+            chip8.memory[hex_page(chip8, chip8.i)].hash(&mut hasher);
+        }
+        if panels.memory || panels.profiler || panels.heatmap {
+            debugger.profiler.total().hash(&mut hasher);
+        }
+        if panels.counters {
+            debugger.counters.instructions.hash(&mut hasher);
+        }
+        if panels.sound {
+            (chip8.sound, self.sounds.frame).hash(&mut hasher);
+        }
+        if panels.calls {
+            (debugger.calls.total(), debugger.calls.frames.len()).hash(&mut hasher);
+        }
+        if panels.log {
+            (self.log.entries.len(), self.log.entries.back()).hash(&mut hasher);
+        }
+        if panels.console {
+            (self.console.lines.len(), self.console.lines.back()).hash(&mut hasher);
+        }
+        if panels.inputs {
+            self.inputs.events.back().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Draws everything into the buffer, writing text straight into its
+    /// cells so that a frame allocates nothing.
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        self.draw(area, buf);
+        if self.monochrome {
+            buf.set_style(area, Style::reset());
+        } else if self.background == Background::Light {
+            // Text straight on the terminal's background is in colors
+            // picked for a dark one, which wash out on a light one
+            for cell in &mut buf.content {
+                if cell.bg == Color::Reset {
+                    cell.fg = on_light(cell.fg);
+                }
+            }
+        }
+    }
+
+    fn draw(&self, area: Rect, buf: &mut Buffer) {
+        if self.fullscreen {
+            let scale = (area.width / (64 * 2)).min(area.height / 32).max(1);
+            let game = centered(area, 64 * 2 * scale, 32 * scale);
+            self.game_display(scale).render(game, buf);
+            self.draw_title(buf, game);
+            self.draw_stuck(buf, game);
+            if self.panels.counters {
+                self.draw_counters(buf, game);
+            }
+            if self.panels.sound {
+                self.draw_sound(buf, game);
+            }
+            self.draw_speedrun(buf, game);
+            return;
+        }
+        let panels = self.panels;
+        let shown = |on: bool, length: u16| Constraint::Length(if on { length } else { 0 });
+        let [status, message, body, inputs, log] = split(
+            area,
+            Direction::Vertical,
+            [
+                shown(panels.status, 1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+                shown(panels.inputs, 1),
+                shown(panels.log, 8),
+            ],
+        );
+        let [left, side] = split(
+            body,
+            Direction::Horizontal,
+            [Constraint::Length(64 * 2 + 2), Constraint::Min(0)],
+        );
+        let (cell, rows) = self.memory_map.layout(self.chip8.memory.len(), 64 * 2);
+        let [game, memory, heatmap] = split(
+            left,
+            Direction::Vertical,
+            [
+                Constraint::Length(32 + 2),
+                shown(panels.memory, rows as u16 + 2),
+                shown(panels.heatmap, 16 + 2),
+            ],
+        );
+        // The panels with no natural height share what's left of the side column
+        let fill = |on: bool| {
+            if on {
+                Constraint::Fill(1)
+            } else {
+                Constraint::Length(0)
+            }
+        };
+        let console_shown = panels.console && !self.console.is_empty();
+        let [keypad, registers, timers, hex, disassembly, profiler, calls, console] = split(
+            side,
+            Direction::Vertical,
+            [
+                shown(panels.keypad, 6),
+                shown(panels.registers, 9),
+                shown(panels.timers, 4),
+                shown(panels.hex, 2 * (HEX_ROWS + 1) as u16 + 2),
+                fill(panels.disassembly),
+                fill(panels.profiler),
+                fill(panels.calls),
+                fill(console_shown),
+            ],
+        );
+
+        if panels.status {
+            self.draw_status(buf, status);
+        }
+        self.draw_message(buf, message);
+        if panels.inputs {
+            self.inputs.draw(buf, inputs);
+        }
+        // Flash the border while beeping, for anyone who can't hear it
+        let border = if self.beeping {
+            Color::Yellow
+        } else {
+            Color::Reset
+        };
+        let title = match self.beeping && self.monochrome {
+            true => " ♪ ",
+            false => "",
+        };
+        let inner = boxed(buf, game, title, Style::new().fg(border));
+        self.game_display(1).render(inner, buf);
+        self.draw_title(buf, inner);
+        self.draw_stuck(buf, inner);
+        self.draw_speedrun(buf, inner);
+        if panels.counters {
+            self.draw_counters(buf, inner);
+        }
+        if panels.sound {
+            self.draw_sound(buf, inner);
+        }
+        if panels.memory {
+            let title = format_args!(" memory, {} ", self.coverage());
+            let inner = boxed(buf, memory, title, Style::new());
+            let strip = MemoryStrip {
+                chip8: self.chip8,
+                counts: self.debugger.profiler.counts(),
+                cell,
+                monochrome: self.monochrome,
+                background: self.background,
+            };
+            strip.render(inner, buf);
+            let bottom = Rect {
+                y: memory.bottom().saturating_sub(1),
+                height: memory.height.min(1),
+                ..inner
+            };
+            stack_legend(buf, bottom, self.chip8.stack.len(), self.monochrome);
+        }
+        if panels.heatmap {
+            let inner = boxed(buf, heatmap, " heatmap ", Style::new());
+            let counts = self.debugger.profiler.counts();
+            let monochrome = self.monochrome;
+            Heatmap { counts, monochrome }.render(inner, buf);
+        }
+        if panels.keypad {
+            let title = if self.legend.is_some() {
+                " keyboard → keypad "
+            } else {
+                " keypad "
+            };
+            let inner = boxed(buf, keypad, title, Style::new());
+            draw_keypad(buf, inner, self.chip8.keys, self.legend, self.monochrome);
+        }
+        if panels.registers {
+            let inner = boxed(buf, registers, " registers ", Style::new());
+            self.draw_registers(buf, inner);
+        }
+        if panels.timers {
+            let inner = boxed(buf, timers, " timers ", Style::new());
+            let chip8 = self.chip8;
+            for (row, (name, value)) in [("DT", chip8.delay), ("ST", chip8.sound)]
+                .into_iter()
+                .enumerate()
+            {
+                let _ = write!(
+                    Cells::new(buf, inner, row as u16),
+                    "{}",
+                    TimerReadout(name, value)
+                );
+            }
+        }
+        if panels.hex {
+            let inner = boxed(buf, hex, " hex ", Style::new());
+            self.draw_hex(buf, inner);
+        }
+        if panels.disassembly {
+            let inner = boxed(buf, disassembly, " disassembly ", Style::new());
+            self.draw_disassembly(buf, inner);
+        }
+        if panels.profiler {
+            let inner = boxed(buf, profiler, " profiler ", Style::new());
+            self.draw_profiler(buf, inner);
+        }
+        if panels.calls {
+            let inner = boxed(buf, calls, " calls ", Style::new());
+            self.draw_calls(buf, inner);
+        }
+        if console_shown {
+            let inner = boxed(buf, console, " console ", Style::new());
+            draw_tail(buf, inner, self.console.lines.iter().map(String::as_str));
+        }
+        if panels.log {
+            let inner = boxed(buf, log, " log ", Style::new());
+            let entries = self.log.entries.iter().map(|(_, text)| text.as_str());
+            draw_tail(buf, inner, entries);
+        }
+    }
+
+    fn draw_title(&self, buf: &mut Buffer, game: Rect) {
+        let Some(title) = self.title else {
+            return;
+        };
+        let area = centered(game, title.chars().count() as u16 + 4, 3);
+        Clear.render(area, buf);
+        let inner = boxed(buf, area, "", Style::new());
+        let _ = write!(Cells::new(buf, inner, 0), " {}", title);
+    }
+
+    /// What the program stopped at, or seems to have, boxed over the middle
+    /// of the game, with the keys that reset it or quit.
+    fn draw_stuck(&self, buf: &mut Buffer, game: Rect) {
+        let (text, border) = match (self.halted, self.stuck) {
+            (Some(error), _) => (error.to_string(), Style::new().red()),
+            (None, Some(stuck)) => (stuck.to_string(), Style::new().yellow()),
+            (None, None) => return,
+        };
+        let lines = [
+            text,
+            format!(
+                "{} resets, {} quits",
+                key_name(self.hotkeys.key(Action::Reset)),
+                key_name(self.hotkeys.key(Action::Quit))
+            ),
+        ];
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0) as u16;
+        let area = centered(game, width + 4, lines.len() as u16 + 2);
+        Clear.render(area, buf);
+        let inner = boxed(buf, area, "", border);
+        for (row, line) in lines.iter().enumerate() {
+            let _ = write!(Cells::new(buf, inner, row as u16), " {}", line);
+        }
+    }
+
+    /// The counters in a box in the game's top right corner.
+    fn draw_counters(&self, buf: &mut Buffer, game: Rect) {
+        let counters = &self.debugger.counters;
+        let values = [
+            ("instructions", counters.instructions),
+            ("frames", counters.frames),
+            ("sprites", counters.sprites),
+            ("collisions", counters.collisions),
+            ("max depth", counters.max_depth as u64),
+        ];
+        let area = corner(game, 12 + 1 + 10 + 2, values.len() as u16 + 2, false);
+        Clear.render(area, buf);
+        let inner = boxed(buf, area, " counters ", Style::new());
+        for (row, (name, value)) in values.into_iter().enumerate() {
+            let _ = write!(
+                Cells::new(buf, inner, row as u16),
+                "{:<12} {:>10}",
+                name,
+                value
+            );
+        }
+    }
+
+    /// The sound timer and the beeper's trace in a box in the game's
+    /// bottom right corner.
+    fn draw_sound(&self, buf: &mut Buffer, game: Rect) {
+        let area = corner(game, SOUND_WIDTH + 2, 2 + 2, true);
+        Clear.render(area, buf);
+        let inner = boxed(buf, area, " sound ", Style::new());
+        let mut readout = Cells::new(buf, inner, 0);
+        let _ = write!(readout, "{}  ", TimerReadout("ST", self.chip8.sound));
+        if self.beeping {
+            readout.style = Style::new().yellow();
+            let _ = readout.write_str("♪");
+        }
+        self.sounds.draw(buf, inner, 1);
+    }
+
+    /// The speedrun timer and its latest splits in a box in the game's top left corner.
+    fn draw_speedrun(&self, buf: &mut Buffer, game: Rect) {
+        let Some(speedrun) = self.speedrun else {
+            return;
+        };
+        let lines = speedrun.lines();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0) as u16;
+        let area = Rect {
+            width: (width.max(9) + 2).min(game.width),
+            height: (lines.len() as u16 + 2).min(game.height),
+            ..game
+        };
+        Clear.render(area, buf);
+        let inner = boxed(buf, area, " timer ", Style::new());
+        draw_tail(buf, inner, lines.iter().map(String::as_str));
+    }
+
+    /// How much of the ROM has been executed, counting two bytes per instruction.
+    fn coverage(&self) -> Coverage {
+        let start = self.chip8.program_start as usize;
+        let rom = self
+            .debugger
+            .profiler
+            .counts()
+            .get(start..start + self.rom_size);
+        let executed = rom.map_or(0, |counts| counts.iter().filter(|n| **n > 0).count() * 2);
+        Coverage(executed.min(self.rom_size) as f64 * 100.0 / self.rom_size.max(1) as f64)
+    }
+
+    fn game_display(&self, scale: u16) -> GameDisplay<'_> {
+        GameDisplay {
+            planes: [&self.chip8.display],
+            palette: self.palette,
+            scale,
+        }
+    }
+
+    fn draw_status(&self, buf: &mut Buffer, area: Rect) {
+        let seconds = self.frame_time.as_secs_f32();
+        let mut status = Cells::new(buf, area, 0);
+        let _ = write!(
+            status,
+            "{:.1}fps {:.4}fpf {:.2}x {}",
+            1.0 / seconds,
+            seconds * self.refresh as f32,
+            self.instructions_per_frame as f32 / INSTRUCTIONS_PER_FRAME as f32,
+            if self.paused { "paused" } else { "" }
+        );
+        if self.beeping {
+            status.column(SPEAKER_COLUMN as u16);
+            status.style = Style::new().yellow();
+            let _ = status.write_str("♪");
+        }
+    }
+
+    fn draw_message(&self, buf: &mut Buffer, area: Rect) {
+        if let Some((cursor, frames)) = self.rewind {
+            draw_timeline(buf, area, cursor, frames);
+            return;
+        }
+        let mut message = Cells::new(buf, area, 0);
+        let _ = match (self.halted, self.log.current()) {
+            (Some(error), _) => {
+                message.style = Style::new().red();
+                write!(message, "halted: {}", error)
+            }
+            (None, Some(text)) => message.write_str(text),
+            // While stepping, what the next instruction will do
+            (None, None) if self.paused => {
+                message.style = Style::new().dark_gray();
+                message.write_str(&explain(self.chip8, self.syntax))
+            }
+            (None, None) => Ok(()),
+        };
+    }
+
+    /// Pages of memory around PC, with the instruction there reversed, and
+    /// around I, with the byte it points at reversed, or in monochrome
+    /// marked with `>`.
+    fn draw_hex(&self, buf: &mut Buffer, area: Rect) {
+        let chip8 = self.chip8;
+        let mut row = 0;
+        for (name, addr, length) in [("PC", chip8.pc, 2), ("I", chip8.i, 1)] {
+            let mut heading = Cells::new(buf, area, row);
+            let _ = write!(heading.styled(Style::new().bold()), "{} {:03X}", name, addr);
+            row += 1;
+            let highlighted = addr as usize..addr as usize + length;
+            for start in hex_page(chip8, addr).step_by(16) {
+                let mut cells = Cells::new(buf, area, row);
+                let _ = write!(cells, "{:03X} ", start);
+                for (offset, byte) in chip8.memory[start..start + 16].iter().enumerate() {
+                    let highlighted = highlighted.contains(&(start + offset));
+                    let style = if highlighted {
+                        Style::new().reversed()
+                    } else if *byte == 0 {
+                        Style::new().dark_gray()
+                    } else {
+                        Style::new()
+                    };
+                    let gap = match highlighted && self.monochrome {
+                        true => '>',
+                        false => ' ',
+                    };
+                    let _ = write!(cells.styled(style), "{}{:02X}", gap, byte);
+                }
+                row += 1;
+            }
+        }
+    }
+
+    fn draw_registers(&self, buf: &mut Buffer, area: Rect) {
+        let chip8 = self.chip8;
+        let mut pointers = Cells::new(buf, area, 0);
+        let _ = write!(pointers, "PC {:03X}  I {:03X}", chip8.pc, chip8.i);
+        for (row, values) in chip8.v.chunks(4).enumerate() {
+            let mut cells = Cells::new(buf, area, row as u16 + 1);
+            for (n, value) in values.iter().enumerate() {
+                let gap = if n == 0 { "" } else { " " };
+                let _ = write!(cells, "{}V{:X} {:02X}", gap, row * 4 + n, value);
+            }
+        }
+        let mut stack = Cells::new(buf, area, 5);
+        let _ = write!(stack, "SP {:X} ", chip8.stack.len());
+        for (n, addr) in chip8.stack.iter().rev().enumerate() {
+            let gap = if n == 0 { "" } else { " " };
+            let _ = write!(stack, "{}{:03X}", gap, addr);
+        }
+        let _ = Cells::new(buf, area, 6).write_str(self.profile_label);
+    }
+
+    /// Instructions around the program counter, which sits a third of the way down.
+    fn draw_disassembly(&self, buf: &mut Buffer, area: Rect) {
+        let chip8 = self.chip8;
+        let height = area.height as usize;
+        let start = chip8.pc.saturating_sub(2 * (height / 3) as u16);
+        // Up to the last whole instruction in memory
+        let addrs = (start as usize..chip8.memory.len() - 1)
+            .step_by(2)
+            .map(|addr| addr as u16)
+            .take(height);
+        for (row, addr) in addrs.enumerate() {
+            let bytes = &chip8.memory[addr as usize..];
+            let op = Opcode::from_slice(bytes);
+            let mut cells = Cells::new(buf, area, row as u16);
+            let (marker, marker_style) = match self.debugger.breakpoints.contains(&addr) {
+                true => ("●", Style::new().red()),
+                false => (" ", Style::new()),
+            };
+            let _ = cells.styled(marker_style).write_str(marker);
+            let current = match addr == chip8.pc {
+                true => Modifier::REVERSED,
+                false => Modifier::empty(),
+            };
+            // Where jumps and calls land stands out, like a label would
+            let (arrow, address) = match self.data.targets.contains(&addr) {
+                true => ("▸", Style::new().yellow().bold()),
+                false => (" ", Style::new()),
+            };
+            let _ = write!(
+                cells.styled(address.add_modifier(current)),
+                "{}{:03X}",
+                arrow,
+                addr
+            );
+            let plain = Style::new().add_modifier(current);
+            // Without reversing, the current instruction is pointed at instead
+            let gap = match addr == chip8.pc && self.monochrome {
+                true => "◂ ",
+                false => "  ",
+            };
+            let _ = write!(
+                cells.styled(plain),
+                "{}{:02X}{:02X}  ",
+                gap,
+                bytes[0],
+                bytes[1]
+            );
+            let instruction = plain.fg(class_color(op.class()));
+            let _ = write!(cells.styled(instruction), "{}", self.syntax.display(&op));
+            if let Some(name) = self.debugger.labels.get(&addr) {
+                let _ = write!(cells.styled(Style::new().cyan()), "  {}:", name);
+            }
+            if let Some(note) = self.debugger.notes.get(&addr) {
+                let _ = write!(cells.styled(Style::new().dark_gray()), "  ; {}", note);
+            }
+        }
+    }
+
+    /// The last frames as columns, newest on the right: how deep calls went
+    /// and, by letter, which subroutine ran most. Under them the busiest
+    /// subroutines with their share of everything executed.
+    fn draw_calls(&self, buf: &mut Buffer, area: Rect) {
+        let calls = &self.debugger.calls;
+        let (width, height) = (area.width as usize, area.height as usize);
+        if calls.total() == 0 || height < 3 {
+            return;
+        }
+        let busiest = &self.data.busiest;
+        let busiest = &busiest[..busiest.len().min((height - 2).min(26))];
+        let key = |routine: Option<u16>| {
+            let index = busiest.iter().position(|(busy, _)| *busy == routine);
+            match (routine, index) {
+                (None, _) => ('·', Style::new()),
+                (Some(_), Some(index)) => (
+                    (b'a' + index as u8) as char,
+                    Style::new().fg(ROUTINE_COLORS[index % ROUTINE_COLORS.len()]),
+                ),
+                _ => ('?', Style::new()),
+            }
+        };
+
+        let recent = calls
+            .frames
+            .iter()
+            .skip(calls.frames.len().saturating_sub(width));
+        let deepest = recent.clone().map(|(depth, _)| *depth).max().unwrap_or(0);
+        let mut depths = Cells::new(buf, area, 0);
+        for (depth, _) in recent.clone() {
+            let bar = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'][depth * 8 / deepest.max(1)];
+            let _ = depths.write_char(bar);
+        }
+        let mut keys = Cells::new(buf, area, 1);
+        for (_, routine) in recent {
+            let (letter, style) = key(*routine);
+            let _ = keys.styled(style).write_char(letter);
+        }
+        for (row, &(routine, count)) in (2..).zip(busiest) {
+            // Names are cut to fit a column of their own
+            let column = Rect {
+                x: area.x + 2.min(area.width),
+                width: area.width.saturating_sub(2).min(16),
+                ..area
+            };
+            let mut name = Cells::new(buf, column, row);
+            let _ = match routine {
+                Some(addr) => match self.debugger.labels.get(&addr) {
+                    Some(label) => write!(name, "{:03X} {}", addr, label),
+                    None => write!(name, "{:03X}", addr),
+                },
+                None => name.write_str("main"),
+            };
+            let share = count as f64 / calls.total() as f64;
+            let (letter, style) = key(routine);
+            let mut cells = Cells::new(buf, area, row);
+            let _ = cells.styled(style).write_char(letter);
+            cells.column(18);
+            let _ = write!(cells.styled(Style::new()), " {:5.1}% ", share * 100.0);
+            cells.style = style;
+            for _ in 0..(share * 20.0).round() as usize {
+                let _ = cells.write_char('█');
+            }
+        }
+    }
+
+    /// The most executed addresses and their share of everything executed.
+    fn draw_profiler(&self, buf: &mut Buffer, area: Rect) {
+        let profiler = &self.debugger.profiler;
+        let hottest = self.data.hottest.iter().take(area.height as usize);
+        for (row, &(addr, count)) in (0..).zip(hottest) {
+            let mut cells = Cells::new(buf, area, row);
+            let share = count as f64 * 100.0 / profiler.total() as f64;
+            let _ = write!(cells, "{:03X}  {:5.1}%  ", addr, share);
+            let bytes = &self.chip8.memory[addr as usize..];
+            let _ = match bytes.len() > 2 {
+                true => write!(cells, "{}", self.syntax.display(&Opcode::from_slice(bytes))),
+                false => cells.write_str("???"),
+            };
+        }
+    }
+}
+
+/// Colors for telling subroutines apart in the calls panel, in turn.
+const ROUTINE_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Red,
+];
+
+/// A bar across the whole history with a marker at the frame on screen.
+fn draw_timeline(buf: &mut Buffer, area: Rect, cursor: usize, frames: usize) {
+    let seconds = (frames - 1 - cursor) as f32 / TIMER_RATE as f32;
+    let label = format_args!(
+        " -{:.2}s  ←/→ scrub, Enter resumes here, Esc goes back",
+        seconds
+    );
+    let bar_width = (area.width as usize)
+        .saturating_sub(text_width(label))
+        .max(1);
+    let marker = cursor * (bar_width - 1) / (frames - 1).max(1);
+    let mut cells = Cells::new(buf, area, 0);
+    cells.style = Style::new().cyan();
+    for n in 0..bar_width {
+        let _ = cells.write_char(if n == marker { '╋' } else { '━' });
+    }
+    cells.style = Style::new();
+    let _ = cells.write_fmt(label);
+}
+
+/// A darker color for text on a light background, in place of one that
+/// only shows up on a dark background.
+fn on_light(color: Color) -> Color {
+    match color {
+        Color::Yellow | Color::LightYellow => Color::Indexed(136),
+        Color::Cyan | Color::LightCyan => Color::Indexed(30),
+        Color::Green | Color::LightGreen => Color::Indexed(28),
+        Color::White => Color::Black,
+        Color::Gray => Color::DarkGray,
+        color => color,
+    }
+}
+
+/// How each class of instruction is colored in listings.
+pub fn class_color(class: Class) -> Color {
+    match class {
+        Class::Flow => Color::Magenta,
+        Class::Alu => Color::Reset,
+        Class::Memory => Color::Blue,
+        Class::Draw => Color::Green,
+        Class::Input => Color::Yellow,
+        Class::Timer => Color::Cyan,
+        Class::Unknown => Color::DarkGray,
+    }
+}
+
+/// The keypad in its physical layout, with held keys lit, or in monochrome
+/// followed by `*`, and with the keyboard key for each in front of it when
+/// given a `legend`.
+fn draw_keypad(
+    buf: &mut Buffer,
+    area: Rect,
+    keys: [bool; 16],
+    legend: Option<&[char; 16]>,
+    monochrome: bool,
+) {
+    const LAYOUT: [[usize; 4]; 4] = [
+        [0x1, 0x2, 0x3, 0xC],
+        [0x4, 0x5, 0x6, 0xD],
+        [0x7, 0x8, 0x9, 0xE],
+        [0xA, 0x0, 0xB, 0xF],
+    ];
+    for (row, keys_in_row) in LAYOUT.iter().enumerate() {
+        let mut line = Cells::new(buf, area, row as u16);
+        for &key in keys_in_row {
+            // Reversing lights a key against whichever background the terminal has
+            let style = match keys[key] {
+                true => Style::new().reversed(),
+                false => Style::new(),
+            };
+            let held = match (keys[key], monochrome, legend) {
+                (true, true, _) => "*",
+                (false, true, _) | (_, false, Some(_)) => " ",
+                (_, false, None) => "",
+            };
+            let _ = match legend {
+                Some(labels) => write!(line.styled(style), "{}→{:x}{}", labels[key], key, held),
+                None => write!(line.styled(style), "{:x}{}", key, held),
+            };
+        }
+    }
+}
+
+/// A readout of a timer's value with an eight cell bar, each cell worth 32 ticks.
+struct TimerReadout(&'static str, u8);
+
+impl fmt::Display for TimerReadout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+        let TimerReadout(name, value) = *self;
+        write!(f, "{} {:02X} ", name, value)?;
+        let eighths = (value as usize * 64).div_ceil(255);
+        for cell in 0..8 {
+            f.write_char(match eighths.saturating_sub(cell * 8) {
+                0 => ' ',
+                n if n >= 8 => '█',
+                n => PARTIAL[n],
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// How much of the ROM has run, as a percentage.
+struct Coverage(f64);
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.0}% of the ROM executed", self.0)
+    }
+}
+
+/// Text written straight into a row of cells, clipped at the area's right
+/// edge, so drawing formatted text needs no `String`.
+struct Cells<'b> {
+    buf: &'b mut Buffer,
+    left: u16,
+    x: u16,
+    y: u16,
+    right: u16,
+    style: Style,
+}
+
+impl<'b> Cells<'b> {
+    /// From the start of `row` within the area, writing nothing if the area
+    /// hasn't that many rows.
+    fn new(buf: &'b mut Buffer, area: Rect, row: u16) -> Cells<'b> {
+        let right = match row < area.height {
+            true => area.right(),
+            false => area.left(),
+        };
+        Cells {
+            buf,
+            left: area.left(),
+            x: area.left(),
+            y: area.top() + row.min(area.height.saturating_sub(1)),
+            right,
+            style: Style::new(),
+        }
+    }
+
+    /// Moves on to a column, counted from the left of the area.
+    fn column(&mut self, column: u16) {
+        self.x = self.x.max(self.left.saturating_add(column).min(self.right));
+    }
+
+    /// Writes what follows in this style.
+    fn styled(&mut self, style: Style) -> &mut Self {
+        self.style = style;
+        self
+    }
+}
+
+impl fmt::Write for Cells<'_> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        for c in text.chars() {
+            if self.x >= self.right {
+                break;
+            }
+            self.buf[(self.x, self.y)].set_char(c).set_style(self.style);
+            self.x += 1;
+        }
+        Ok(())
+    }
+}
+
+/// How many cells formatted text takes, without keeping it.
+fn text_width(text: fmt::Arguments) -> usize {
+    struct Count(usize);
+    impl fmt::Write for Count {
+        fn write_str(&mut self, text: &str) -> fmt::Result {
+            self.0 += text.chars().count();
+            Ok(())
+        }
+    }
+    let mut count = Count(0);
+    let _ = count.write_fmt(text);
+    count.0
+}
+
+/// The last lines that fit in the area, one to a row.
+fn draw_tail<'t>(buf: &mut Buffer, area: Rect, lines: impl ExactSizeIterator<Item = &'t str>) {
+    let skip = lines.len().saturating_sub(area.height as usize);
+    for (row, line) in lines.skip(skip).enumerate() {
+        let _ = Cells::new(buf, area, row as u16).write_str(line);
+    }
+}
+
+/// A plain border around the area with a title on its top edge, returning
+/// what's inside. Drawn straight into the buffer, as a titled Block keeps
+/// its title in a Vec.
+fn boxed(buf: &mut Buffer, area: Rect, title: impl fmt::Display, border: Style) -> Rect {
+    let block = Block::bordered().border_style(border);
+    let inner = block.inner(area);
+    block.render(area, buf);
+    if area.height > 0 {
+        let top = Rect {
+            x: area.x + 1,
+            width: area.width.saturating_sub(2),
+            height: 1,
+            ..area
+        };
+        let _ = write!(Cells::new(buf, top, 0), "{}", title);
+    }
+    inner
+}
+
+/// Lays areas out one after another, as Layout does with the lengths,
+/// minimums and fills used here, without building a Vec every frame. Lengths
+/// get their space first, in order while there's room; what's left is shared
+/// by the rest by weight, or goes to the last area when there are none.
+fn split<const N: usize>(
+    area: Rect,
+    direction: Direction,
+    constraints: [Constraint; N],
+) -> [Rect; N] {
+    let extent = match direction {
+        Direction::Horizontal => area.width,
+        Direction::Vertical => area.height,
+    };
+    let mut sizes = [0u16; N];
+    let mut weights = [0u16; N];
+    let mut left = extent;
+    for (index, constraint) in constraints.iter().enumerate() {
+        let (length, weight) = match *constraint {
+            Constraint::Length(length) => (length, 0),
+            Constraint::Min(length) => (length, 1),
+            Constraint::Fill(weight) => (0, weight.max(1)),
+            _ => (0, 1),
+        };
+        sizes[index] = length.min(left);
+        weights[index] = weight;
+        left -= sizes[index];
+    }
+    let total: u32 = weights.iter().map(|&weight| weight as u32).sum();
+    match weights.iter().rposition(|&weight| weight > 0) {
+        Some(last) => {
+            let shared = left;
+            for (index, &weight) in weights.iter().enumerate() {
+                let share = match index == last {
+                    true => left,
+                    false => (shared as u32 * weight as u32 / total) as u16,
+                };
+                sizes[index] += share;
+                left -= share;
+            }
+        }
+        None if N > 0 => sizes[N - 1] += left,
+        None => {}
+    }
+    let mut offset = 0;
+    sizes.map(|size| {
+        let rect = match direction {
+            Direction::Horizontal => Rect {
+                x: area.x + offset,
+                width: size,
+                ..area
+            },
+            Direction::Vertical => Rect {
+                y: area.y + offset,
+                height: size,
+                ..area
+            },
+        };
+        offset += size;
+        rect
+    })
+}
+
+/// An area of this size in the middle of another, clipped to it.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let (width, height) = (width.min(area.width), height.min(area.height));
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// An area of this size in the top or bottom right corner of another.
+fn corner(area: Rect, width: u16, height: u16, bottom: bool) -> Rect {
+    let (width, height) = (width.min(area.width), height.min(area.height));
+    Rect {
+        x: area.right() - width,
+        y: match bottom {
+            true => area.bottom() - height,
+            false => area.y,
+        },
+        width,
+        height,
+    }
+}
+
+/// Rows of sixteen bytes in a hex page.
+const HEX_ROWS: usize = 4;
+
+/// The memory shown around `addr`, which sits in the second row unless
+/// that would run off either end of memory.
+fn hex_page(chip8: &Chip8, addr: u16) -> Range<usize> {
+    let length = HEX_ROWS * 16;
+    let start = (addr as usize & !0xF).saturating_sub(16);
+    let start = start.min(chip8.memory.len() - length);
+    start..start + length
+}
+
+/// Return addresses going down the stack, deep blue through teal and green
+/// to amber, so long call chains can still be told apart.
+const STACK_RAMP: [u8; 15] = [
+    21, 27, 33, 39, 45, 44, 43, 42, 41, 40, 76, 112, 148, 184, 220,
+];
+
+/// The background for the return address `index` places down the stack;
+/// the topmost, where the current subroutine returns to, stands out.
+fn color_from_index(index: usize) -> Color {
+    match index {
+        0 => Color::Magenta,
+        n => Color::Indexed(STACK_RAMP[(n - 1).min(STACK_RAMP.len() - 1)]),
+    }
+}
+
+/// The character a return address `index` places down the stack is marked
+/// with in monochrome, as deep as the stack can go.
+fn stack_marker(index: usize) -> char {
+    char::from_digit(index as u32 % 16, 16).unwrap_or('?')
+}
+
+/// Which colors stand for which depths, as deep as the stack is now, or in
+/// monochrome which characters.
+fn stack_legend(buf: &mut Buffer, area: Rect, depth: usize, monochrome: bool) {
+    if depth == 0 {
+        return;
+    }
+    let mut legend = Cells::new(buf, area, 0);
+    let _ = legend.write_str(" stack ");
+    for index in 0..depth.min(STACK_RAMP.len() + 1) {
+        let style = Style::new().bg(color_from_index(index)).fg(Color::Black);
+        let _ = match (index, monochrome) {
+            (0, false) => legend.styled(style).write_str(" top "),
+            (0, true) => write!(legend, " {} top ", stack_marker(0)),
+            (index, false) => write!(legend.styled(style), " {} ", index),
+            (index, true) => write!(legend, " {} ", stack_marker(index)),
+        };
+    }
+    let _ = legend.styled(Style::new()).write_str(" ");
+}
+
+/// The display, blending the planes into palette indices. Each pixel is
+/// `scale` cells high and twice that wide, so it comes out roughly square.
+struct GameDisplay<'a> {
+    planes: [&'a [u64; 32]; 1],
+    palette: &'a Palette,
+    scale: u16,
+}
+
+impl Widget for GameDisplay<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            let row = ((y - area.y) / self.scale) as usize;
+            for x in area.left()..area.right() {
+                let bit = (x - area.x) / (2 * self.scale);
+                if row >= 32 || bit >= 64 {
+                    continue;
+                }
+                let index = self.planes.iter().enumerate().fold(0, |index, (p, plane)| {
+                    index | (((plane[row] >> (63 - bit)) & 1) as usize) << p
+                });
+                let (symbol, color) = self.palette.cell(index);
+                buf[(x, y)].set_symbol(symbol).set_fg(color);
+            }
+        }
+    }
+}
+
+/// Just the display, one pixel to two cells, for other screens than the main one.
+pub fn screen<'a>(chip8: &'a Chip8, palette: &'a Palette) -> impl Widget + 'a {
+    GameDisplay {
+        planes: [&chip8.display],
+        palette,
+        scale: 1,
+    }
+}
+
+/// A one line map of memory marking the program counter, I and the return addresses
+/// on the stack, with code that has run in green and anything else that isn't
+/// empty in grey. Each cell covers as many bytes as it takes to fit the width.
+struct MemoryStrip<'a> {
+    chip8: &'a Chip8,
+    /// Executions per address, from the profiler.
+    counts: &'a [u64],
+    /// Bytes per cell.
+    cell: usize,
+    /// Return addresses are marked with their depth and code that has run
+    /// with `▒`, instead of with colors.
+    monochrome: bool,
+    /// The interpreter's memory below the program is shaded apart from the
+    /// background, in black on a dark one and in grey on a light one.
+    background: Background,
+}
+
+impl Widget for MemoryStrip<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let c8 = self.chip8;
+        if area.is_empty() {
+            return;
+        }
+        let cell = self.cell.max(1);
+        let row = cell * area.width as usize;
+        // When memory doesn't fit, the rows around PC are shown
+        let total_rows = c8.memory.len().div_ceil(row);
+        let first_row = (c8.pc as usize / row)
+            .saturating_sub(area.height as usize / 2)
+            .min(total_rows.saturating_sub(area.height as usize));
+        let start = first_row * row;
+        let end = (start + row * area.height as usize).min(c8.memory.len());
+        for (n, i) in (start..end).step_by(cell).enumerate() {
+            let x = area.left() + (n % area.width as usize) as u16;
+            let y = area.top() + (n / area.width as usize) as u16;
+            let rng = i..(i + cell).min(c8.memory.len());
+            let slice = &c8.memory[rng.clone()];
+            let mut color: Color;
+            let mut character = if rng.contains(&(c8.pc as usize)) {
+                '╫'
+            } else if rng.contains(&(c8.i as usize)) {
+                '┼'
+            } else if slice.iter().all(|n| *n == 0) {
+                ' '
+            } else if slice.iter().filter(|n| **n == 1).count() > cell / 4 {
+                '─'
+            } else if slice.iter().filter(|n| **n == 1).count() > cell / 2 {
+                '━'
+            } else if slice.iter().filter(|n| **n == 1).count() > cell * 3 / 4 {
+                '═'
+            } else {
+                '┄'
+            };
+            if i < c8.program_start as usize {
+                color = match self.background {
+                    Background::Dark => Color::Black,
+                    Background::Light => Color::Indexed(252),
+                };
+            } else {
+                color = Color::Reset;
+            }
+
+            // The topmost return address wins a cell shared with deeper ones
+            let depth = (c8.stack.iter().rev()).position(|addr| rng.contains(&(*addr as usize)));
+            if let Some(depth) = depth {
+                color = color_from_index(depth);
+            }
+            let executed = self
+                .counts
+                .get(rng.clone())
+                .is_some_and(|counts| counts.iter().any(|n| *n > 0));
+            // PC and I keep their own markers
+            if self.monochrome && !matches!(character, '╫' | '┼') {
+                match depth {
+                    Some(depth) => character = stack_marker(depth),
+                    None if executed => character = '▒',
+                    None => {}
+                }
+            }
+            let foreground = if executed {
+                Color::Green
+            } else {
+                Color::DarkGray
+            };
+            buf[(x, y)]
+                .set_char(character)
+                .set_fg(foreground)
+                .set_bg(color);
+        }
+    }
+}
+
+/// Memory as a grid, each cell colored by how often the instructions in it
+/// have run, on a log scale up to the hottest cell.
+struct Heatmap<'a> {
+    counts: &'a [u64],
+    /// Shade the cells with characters instead.
+    monochrome: bool,
+}
+
+impl Widget for Heatmap<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // Cold to hot: dark blue through green and yellow to red
+        const RAMP: [u8; 8] = [17, 19, 27, 35, 71, 154, 220, 196];
+        const SHADES: [char; 8] = ['·', ':', '░', '░', '▒', '▒', '▓', '█'];
+        if area.is_empty() || self.counts.is_empty() {
+            return;
+        }
+        let cells = area.width as usize * area.height as usize;
+        let cell = self.counts.len().div_ceil(cells);
+        let sums = self.counts.chunks(cell).map(|c| c.iter().sum::<u64>());
+        let hottest = (sums.clone().max().unwrap_or(0) as f64).ln_1p();
+        for (n, sum) in sums.enumerate() {
+            let x = area.x + (n % area.width as usize) as u16;
+            let y = area.y + (n / area.width as usize) as u16;
+            let (shade, color) = if sum == 0 {
+                (' ', Color::Reset)
+            } else {
+                let heat = (sum as f64).ln_1p() / hottest;
+                let level = ((heat * (RAMP.len() - 1) as f64).round()) as usize;
+                (SHADES[level], Color::Indexed(RAMP[level]))
+            };
+            let shade = if self.monochrome { shade } else { ' ' };
+            buf[(x, y)].set_char(shade).set_bg(color);
+        }
+    }
+}
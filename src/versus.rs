@@ -0,0 +1,179 @@
+use crate::{
+    chip8::{Chip8, ADDR_PROGRAM_END},
+    input::{Keyboard, KEY_ESC, KEY_RIGHT, KEY_SPACE},
+    quirks::{Profile, Quirks},
+    read_rom,
+    ui::{screen, Palette},
+    INSTRUCTIONS_PER_FRAME,
+};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::Block,
+    DefaultTerminal, Frame,
+};
+use std::{
+    error::Error,
+    path::Path,
+    result::Result,
+    thread,
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "usage: fe_o8 versus File [PROFILE PROFILE]";
+
+/// A display and its border.
+const SCREEN_WIDTH: u16 = 64 * 2 + 2;
+const SCREEN_HEIGHT: u16 = 32 + 2;
+
+/// One of the two machines, and how it stopped if it has.
+struct Side {
+    name: String,
+    chip8: Chip8,
+    stopped: Option<String>,
+}
+
+impl Side {
+    fn new(name: &str, rom: &[u8], seed: u64) -> Result<Side, String> {
+        let quirks = match name {
+            "default" => Quirks::default(),
+            name => Profile::parse(name)
+                .ok_or(format!("unknown quirks profile {}", name))?
+                .quirks(),
+        };
+        let mut chip8 = Chip8::new();
+        chip8.quirks = quirks;
+        chip8.seed(seed);
+        chip8.load(rom);
+        Ok(Side {
+            name: name.into(),
+            chip8,
+            stopped: None,
+        })
+    }
+
+    fn run_frame(&mut self, keys: [bool; 16], frame: u64) {
+        if self.stopped.is_some() {
+            return;
+        }
+        self.chip8.set_keys(keys);
+        // An error stops this side, not the comparison
+        self.stopped = match self.chip8.run_frame(INSTRUCTIONS_PER_FRAME) {
+            Ok(()) => None,
+            Err(error) => Some(format!("halted in frame {}: {}", frame, error)),
+        };
+    }
+}
+
+struct Versus {
+    sides: [Side; 2],
+    frame: u64,
+    paused: bool,
+    /// The first frame after which the displays weren't the same.
+    diverged: Option<u64>,
+}
+
+impl Versus {
+    fn step(&mut self, keys: [bool; 16]) {
+        for side in &mut self.sides {
+            side.run_frame(keys, self.frame);
+        }
+        self.frame += 1;
+        let [first, second] = &self.sides;
+        if self.diverged.is_none() && first.chip8.display != second.chip8.display {
+            // Stop where it happened, so it can be looked at
+            self.diverged = Some(self.frame);
+            self.paused = true;
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(frame.area());
+        // Side by side where there's room, one above the other where there isn't
+        let (direction, size) = match body.width >= 2 * SCREEN_WIDTH {
+            true => (Direction::Horizontal, SCREEN_WIDTH),
+            false => (Direction::Vertical, SCREEN_HEIGHT),
+        };
+        let areas = Layout::new(direction, [Constraint::Length(size); 2]).split(body);
+
+        let state = match (self.paused, self.diverged) {
+            (_, Some(diverged)) => format!(
+                "frame {}, displays differ since frame {}",
+                self.frame, diverged
+            ),
+            (true, None) => format!("frame {}, paused", self.frame),
+            (false, None) => format!("frame {}, displays the same", self.frame),
+        };
+        frame.render_widget(
+            Line::from(vec![
+                format!(" {} ", state).bold(),
+                " keypad plays both, Space pauses, → steps a frame, Esc quits".into(),
+            ]),
+            header,
+        );
+        let palette = Palette::default();
+        for (side, &area) in self.sides.iter().zip(areas.iter()) {
+            let area = Rect {
+                width: area.width.min(SCREEN_WIDTH),
+                ..area
+            };
+            let title = match &side.stopped {
+                Some(stopped) => format!(" {}: {} ", side.name, stopped),
+                None => format!(" {}  PC {:03X} ", side.name, side.chip8.pc),
+            };
+            let block = Block::bordered().title(title);
+            frame.render_widget(screen(&side.chip8, &palette), block.inner(area));
+            frame.render_widget(block, area);
+        }
+    }
+}
+
+/// Runs a ROM under two quirks profiles at once, vip and schip unless others
+/// are named, with the keypad pressing the same keys on both and the same
+/// random numbers, and pauses at the first frame their displays differ.
+pub fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+    let path = args.next().ok_or(USAGE)?;
+    let names: Vec<String> = args.collect();
+    let [first, second] = match names.as_slice() {
+        [] => ["vip", "schip"],
+        [first, second] => [first.as_str(), second.as_str()],
+        _ => return Err(USAGE.into()),
+    };
+    let rom = read_rom(Path::new(&path), ADDR_PROGRAM_END as usize)?;
+    let seed = rand::random();
+    let versus = Versus {
+        sides: [
+            Side::new(first, &rom, seed)?,
+            Side::new(second, &rom, seed)?,
+        ],
+        frame: 0,
+        paused: false,
+        diverged: None,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = compare(&mut terminal, versus);
+    ratatui::restore();
+    result
+}
+
+fn compare(terminal: &mut DefaultTerminal, mut versus: Versus) -> Result<(), Box<dyn Error>> {
+    let mut keyboard = Keyboard::new();
+    loop {
+        let started = Instant::now();
+        keyboard.poll();
+        if keyboard.pressed(KEY_ESC) {
+            return Ok(());
+        }
+        if keyboard.pressed(KEY_SPACE) {
+            versus.paused = !versus.paused;
+        }
+        if !versus.paused || keyboard.pressed(KEY_RIGHT) {
+            versus.step(keyboard.keypad());
+        }
+        terminal.draw(|frame| versus.draw(frame))?;
+        thread::sleep((Duration::from_secs(1) / 60).saturating_sub(started.elapsed()));
+    }
+}
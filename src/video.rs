@@ -0,0 +1,145 @@
+use crate::{
+    audio::{PITCH, VOLUME},
+    chip8::Chip8,
+    ui::Palette,
+};
+use std::{
+    env,
+    error::Error,
+    f32::consts::TAU,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{self, Child, ChildStdin, Command, Stdio},
+    result::Result,
+};
+
+/// Video pixels on each side of a display pixel.
+const SCALE: usize = 10;
+const WIDTH: usize = 64 * SCALE;
+const HEIGHT: usize = 32 * SCALE;
+const FRAME_RATE: u32 = 60;
+const SAMPLE_RATE: u32 = 44_100;
+
+/// A capture of the display and beeper, one video frame per emulated frame,
+/// encoded by ffmpeg. The frames are piped to it as they're drawn and the
+/// sound kept aside, then the two are put together when recording finishes.
+pub struct Video {
+    out: PathBuf,
+    ffmpeg: Child,
+    frames: BufWriter<ChildStdin>,
+    /// The picture on its own, in the format asked for.
+    silent: PathBuf,
+    /// The beeper, as mono 16 bit samples.
+    sound: PathBuf,
+    samples: BufWriter<File>,
+    phase: f32,
+    /// Frames recorded so far, and samples written for them.
+    frame: u64,
+    sample: u64,
+}
+
+impl Video {
+    pub fn start(out: &Path) -> Result<Video, Box<dyn Error>> {
+        let extension = out
+            .extension()
+            .map_or("mp4".into(), |e| e.to_string_lossy());
+        let temp = env::temp_dir().join(format!("fe_o8-{}", process::id()));
+        let silent = temp.with_extension(format!("video.{}", extension));
+        let sound = temp.with_extension("pcm");
+        let size = format!("{}x{}", WIDTH, HEIGHT);
+        let rate = FRAME_RATE.to_string();
+        let mut ffmpeg = ffmpeg()
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24"])
+            .args(["-s", &size, "-r", &rate])
+            .args(["-i", "-", "-pix_fmt", "yuv420p"])
+            .arg(&silent)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("can't start ffmpeg: {}", e))?;
+        let frames = BufWriter::new(ffmpeg.stdin.take().ok_or("ffmpeg has no input")?);
+        Ok(Video {
+            out: out.to_path_buf(),
+            ffmpeg,
+            frames,
+            samples: BufWriter::new(File::create(&sound)?),
+            silent,
+            sound,
+            phase: 0.0,
+            frame: 0,
+            sample: 0,
+        })
+    }
+
+    /// Adds the display as it is now, and a frame's worth of sound.
+    pub fn record(&mut self, chip8: &Chip8, palette: &Palette) -> Result<(), Box<dyn Error>> {
+        let colors = [palette.pixel_rgb(0), palette.pixel_rgb(1)];
+        let mut line = Vec::with_capacity(WIDTH * 3);
+        for row in chip8.display {
+            line.clear();
+            for bit in 0..64 {
+                let color = colors[(row >> (63 - bit) & 1) as usize];
+                for _ in 0..SCALE {
+                    line.extend(color);
+                }
+            }
+            for _ in 0..SCALE {
+                self.frames
+                    .write_all(&line)
+                    .map_err(|e| format!("ffmpeg stopped taking frames: {}", e))?;
+            }
+        }
+
+        // Samples are counted from the start so frames that don't divide
+        // the rate evenly don't drift
+        self.frame += 1;
+        let until = self.frame * SAMPLE_RATE as u64 / FRAME_RATE as u64;
+        for _ in self.sample..until {
+            let value = match chip8.sound > 0 {
+                true => VOLUME * (self.phase * TAU).sin(),
+                false => 0.0,
+            };
+            self.phase = (self.phase + PITCH / SAMPLE_RATE as f32).fract();
+            let sample = (value * i16::MAX as f32) as i16;
+            self.samples.write_all(&sample.to_le_bytes())?;
+        }
+        self.sample = until;
+        Ok(())
+    }
+
+    /// Waits for the picture to be encoded, then adds the sound to it.
+    pub fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        self.frames.flush()?;
+        self.samples.flush()?;
+        drop(self.frames);
+        let encoded = self.ffmpeg.wait()?;
+        let muxed = encoded.success().then(|| {
+            ffmpeg()
+                .arg("-i")
+                .arg(&self.silent)
+                .args(["-f", "s16le", "-ar", &SAMPLE_RATE.to_string(), "-ac", "1"])
+                .arg("-i")
+                .arg(&self.sound)
+                .args(["-c:v", "copy", "-shortest"])
+                .arg(&self.out)
+                .status()
+        });
+        let _ = fs::remove_file(&self.silent);
+        let _ = fs::remove_file(&self.sound);
+        match muxed {
+            Some(Ok(status)) if status.success() => Ok(()),
+            Some(Err(e)) => Err(format!("can't start ffmpeg: {}", e).into()),
+            _ => Err(format!("ffmpeg couldn't write {}", self.out.display()).into()),
+        }
+    }
+}
+
+/// ffmpeg overwriting its output, quietly so the screen isn't disturbed.
+fn ffmpeg() -> Command {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-y", "-loglevel", "error", "-nostdin"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
@@ -0,0 +1,52 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    result::Result,
+    sync::mpsc::{channel, Receiver},
+};
+
+/// Watches a file, such as the ROM, for changes. The parent directory is watched
+/// rather than the file itself, since assemblers and editors often replace the
+/// file outright.
+pub struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    file_name: PathBuf,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> Result<FileWatcher, Box<dyn Error>> {
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let dir = match path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(FileWatcher {
+            _watcher: watcher,
+            events,
+            file_name: path
+                .file_name()
+                .ok_or("watched path has no file name")?
+                .into(),
+        })
+    }
+
+    /// Drains pending events, returning whether any of them wrote to the file.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter().flatten() {
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == Some(self.file_name.as_os_str()))
+            {
+                changed = true;
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,63 @@
+use crate::chip8::Chip8;
+use std::{
+    fmt::{self, Display, Formatter},
+    time::{Duration, Instant},
+};
+
+/// Why a program looks like it has stopped for good.
+#[derive(Clone, Copy, Hash, PartialEq)]
+pub enum Stuck {
+    /// It jumped to the jump it's on, the usual way to end.
+    Finished,
+    /// Nothing has been drawn for this many seconds.
+    Stalled(u64),
+}
+
+impl Display for Stuck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Stuck::Finished => write!(f, "program finished"),
+            Stuck::Stalled(seconds) => {
+                write!(f, "appears stuck: nothing drawn for {}s", seconds)
+            }
+        }
+    }
+}
+
+/// Notices a program that won't do anything more, so it can be said so
+/// rather than left looking like it's still running.
+pub struct Watchdog {
+    /// How long the screen can stay the same before it counts as stuck,
+    /// if it ever does.
+    stall: Option<Duration>,
+    changed: Instant,
+}
+
+impl Watchdog {
+    pub fn new(stall: Option<Duration>) -> Watchdog {
+        Watchdog {
+            stall,
+            changed: Instant::now(),
+        }
+    }
+
+    /// Starts counting again, as when the program isn't running anyway.
+    pub fn reset(&mut self) {
+        self.changed = Instant::now();
+    }
+
+    /// Checks once a frame, told whether anything was drawn since the last.
+    /// Waiting for a key isn't stuck; the player just hasn't pressed one.
+    pub fn check(&mut self, chip8: &Chip8, drawn: bool) -> Option<Stuck> {
+        if chip8.jumps_to_itself() {
+            return Some(Stuck::Finished);
+        }
+        if drawn || chip8.waiting_for_key() {
+            self.reset();
+        }
+        let still = self.changed.elapsed();
+        (self.stall)
+            .filter(|&stall| still >= stall)
+            .map(|_| Stuck::Stalled(still.as_secs()))
+    }
+}